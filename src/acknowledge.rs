@@ -0,0 +1,267 @@
+use crate::changes::ChangeType;
+use crate::file::{find_newest_file, find_newest_file_excluding, FileScanResult};
+use crate::helpers::{get_cur_username, load_results_from_file, store_json, ScanResults};
+use crate::osfig_state::{load_osfig_settings, OsfigSettings};
+use chrono::{DateTime, Utc};
+use log::{error, info, warn};
+use serde::{Deserialize, Serialize};
+use std::fs::{create_dir_all, OpenOptions};
+use std::io::{Error, ErrorKind, Write};
+use std::path::{Path, PathBuf};
+use std::time::SystemTime;
+
+const APPROVED_BASELINE_DIR: &str = "approved";
+const APPROVED_BASELINE_FILE: &str = "baseline.json";
+const ACKNOWLEDGMENT_LOG_FILE: &str = "acknowledgments.log";
+
+#[allow(unused)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Acknowledgment {
+    pub(crate) timestamp: String,
+    pub(crate) user: String,
+    pub(crate) reason: String,
+    pub(crate) ticket_id: Option<String>,
+    pub(crate) path: Box<PathBuf>,
+    pub(crate) previous_path: Option<Box<PathBuf>>,
+    pub(crate) change_type: ChangeType,
+    pub(crate) changed_fields: Vec<String>,
+    pub(crate) results_file: String,
+}
+
+pub fn get_approved_baseline_path(osfig_settings: &OsfigSettings) -> PathBuf {
+    // The baseline is kept in its own directory so it never gets picked up as the newest results
+    Path::new(&osfig_settings.scan_result_path)
+        .join(APPROVED_BASELINE_DIR)
+        .join(APPROVED_BASELINE_FILE)
+}
+
+pub fn get_acknowledgment_log_path(osfig_settings: &OsfigSettings) -> PathBuf {
+    Path::new(&osfig_settings.scan_result_path).join(ACKNOWLEDGMENT_LOG_FILE)
+}
+
+pub fn run_acknowledge(options: &[String]) -> std::io::Result<()> {
+    let mut paths: Vec<PathBuf> = Vec::new();
+    let mut reason: Option<&String> = None;
+    let mut ticket_id: Option<&String> = None;
+    let mut options_iter = options.iter();
+    while let Some(option) = options_iter.next() {
+        match option.as_str() {
+            "--reason" => {
+                reason = options_iter.next();
+                if reason.is_none() {
+                    return Err(acknowledge_usage_error("Missing text after --reason"));
+                }
+            }
+            "--ticket" => {
+                ticket_id = options_iter.next();
+                if ticket_id.is_none() {
+                    return Err(acknowledge_usage_error("Missing ID after --ticket"));
+                }
+            }
+            _ => paths.push(PathBuf::from(option)),
+        }
+    }
+    if paths.is_empty() {
+        return Err(acknowledge_usage_error("Expected at least one path"));
+    }
+    // An approval nobody can explain later isn't worth much in an audit
+    let reason = match reason {
+        Some(reason) if !reason.trim().is_empty() => reason,
+        _ => return Err(acknowledge_usage_error("A reason is required")),
+    };
+
+    let osfig_settings = load_osfig_settings();
+    let acknowledgments = acknowledge_changes(
+        &osfig_settings,
+        &paths,
+        &get_cur_username(),
+        reason,
+        ticket_id.map(String::as_str),
+    )
+    .map_err(|e| {
+        error!("{}", e);
+        Error::new(ErrorKind::InvalidData, e)
+    })?;
+
+    for acknowledgment in &acknowledgments {
+        println!(
+            "Acknowledged [{}] {}",
+            format!("{:?}", acknowledgment.change_type).to_uppercase(),
+            acknowledgment.path.display()
+        );
+    }
+    println!("{} change(s) acknowledged", acknowledgments.len());
+
+    Ok(())
+}
+
+fn acknowledge_usage_error(message: &str) -> Error {
+    error!("Invalid ack options: {}", message);
+    println!("Usage: osfig ack <path>... --reason <reason> [--ticket <ticket id>]");
+    Error::new(ErrorKind::InvalidInput, message)
+}
+
+pub fn acknowledge_changes(
+    osfig_settings: &OsfigSettings,
+    paths: &Vec<PathBuf>,
+    user: &str,
+    reason: &str,
+    ticket_id: Option<&str>,
+) -> Result<Vec<Acknowledgment>, String> {
+    let results_glob = format!("{}/*.json", osfig_settings.scan_result_path.as_str());
+    let results_path = find_newest_file(results_glob.as_str());
+    if !results_path.is_file() {
+        return Err(format!(
+            "No results found in {} to acknowledge",
+            osfig_settings.scan_result_path
+        ));
+    }
+    let results_file = results_path.to_str().unwrap().to_string();
+    let latest_results = load_results_from_file(&results_file)?;
+
+    let approved_baseline_path = get_approved_baseline_path(osfig_settings);
+    let mut approved_baseline = if approved_baseline_path.is_file() {
+        load_results_from_file(approved_baseline_path.to_str().unwrap())?.files
+    } else {
+        // The first acknowledgment has to start from whatever the latest results were compared
+        // against, which is the results file before them.
+        let prior_results_path = find_newest_file_excluding(results_glob.as_str(), &results_path);
+        if prior_results_path.is_file() {
+            info!(
+                "Seeding approved baseline from {}",
+                prior_results_path.display()
+            );
+            load_results_from_file(prior_results_path.to_str().unwrap())?
+                .files
+                .into_iter()
+                .filter(|result| result.exists)
+                .map(get_approved_result)
+                .collect()
+        } else {
+            Vec::new()
+        }
+    };
+
+    let mut acknowledgments: Vec<Acknowledgment> = Vec::new();
+    for path in paths {
+        let result = match latest_results
+            .files
+            .iter()
+            .find(|result| result.path.as_path() == path.as_path())
+        {
+            Some(result) if result.change_type != ChangeType::Unchanged => result,
+            _ => {
+                warn!(
+                    "No detected change for {} in {}: Skipping",
+                    path.display(),
+                    results_file
+                );
+                continue;
+            }
+        };
+
+        apply_acknowledgment(&mut approved_baseline, result);
+        acknowledgments.push(Acknowledgment {
+            timestamp: DateTime::<Utc>::from(SystemTime::now()).to_string(),
+            user: user.to_string(),
+            reason: reason.to_string(),
+            ticket_id: ticket_id.map(str::to_string),
+            path: result.path.clone(),
+            previous_path: result.previous_path.clone(),
+            change_type: result.change_type,
+            changed_fields: result.changed_fields.clone(),
+            results_file: results_file.clone(),
+        });
+    }
+
+    if acknowledgments.is_empty() {
+        return Ok(acknowledgments);
+    }
+
+    let approved_baseline = ScanResults {
+        files: approved_baseline,
+        ..Default::default()
+    };
+    if let Err(e) = store_json(&approved_baseline, approved_baseline_path.to_str().unwrap()) {
+        return Err(format!("Cannot save approved baseline: {}", e));
+    }
+    info!(
+        "Approved baseline saved to file {}",
+        approved_baseline_path.display()
+    );
+
+    let log_path = get_acknowledgment_log_path(osfig_settings);
+    if let Err(e) = append_acknowledgments(&log_path, &acknowledgments) {
+        return Err(format!(
+            "Cannot write acknowledgment log {}: {}",
+            log_path.display(),
+            e
+        ));
+    }
+
+    Ok(acknowledgments)
+}
+
+pub fn apply_acknowledgment(approved_baseline: &mut Vec<FileScanResult>, result: &FileScanResult) {
+    match result.change_type {
+        ChangeType::Unchanged => {}
+        ChangeType::Deleted => {
+            approved_baseline.retain(|approved| approved.path != result.path);
+        }
+        ChangeType::Moved => {
+            if let Some(previous_path) = &result.previous_path {
+                approved_baseline.retain(|approved| &approved.path != previous_path);
+            }
+            upsert_approved_result(approved_baseline, result);
+        }
+        ChangeType::Added | ChangeType::Modified | ChangeType::Unreadable => {
+            upsert_approved_result(approved_baseline, result);
+        }
+    }
+}
+
+fn upsert_approved_result(approved_baseline: &mut Vec<FileScanResult>, result: &FileScanResult) {
+    let approved_result = get_approved_result(result.clone());
+    match approved_baseline
+        .iter_mut()
+        .find(|approved| approved.path == result.path)
+    {
+        Some(approved) => *approved = approved_result,
+        None => approved_baseline.push(approved_result),
+    }
+}
+
+fn get_approved_result(mut result: FileScanResult) -> FileScanResult {
+    // The baseline holds what the path should look like, not how it got there
+    result.set_changes(vec![]);
+    result.previous_path = None;
+    result.content_diff = "".to_string();
+    result.content_diff_hunks = vec![];
+    result.suppressed = false;
+    result.suppressed_by = None;
+    result.binary_diff = None;
+    result.similarity = None;
+    result.directory_changes = vec![];
+    result.changed_block_offsets = vec![];
+    result
+}
+
+pub fn append_acknowledgments(
+    log_path: &Path,
+    acknowledgments: &Vec<Acknowledgment>,
+) -> std::io::Result<()> {
+    if let Some(log_dir) = log_path.parent() {
+        create_dir_all(log_dir)?;
+    }
+    // The log is only ever appended to. One JSON object per line keeps it easy to grep and to ship
+    // to whatever collects the rest of the audit trail.
+    let mut log_file = OpenOptions::new()
+        .append(true)
+        .create(true)
+        .open(log_path)?;
+    for acknowledgment in acknowledgments {
+        let line = serde_json::to_string(acknowledgment)?;
+        writeln!(log_file, "{}", line)?;
+    }
+    Ok(())
+}
@@ -0,0 +1,494 @@
+use blake2s_simd::Params;
+use log::{debug, warn};
+use serde::{Deserialize, Serialize};
+use std::fs::File;
+use std::io::{Read, Seek, SeekFrom};
+use std::path::Path;
+
+// Section hashes only need to tell two versions of the same file apart, so a short digest keeps
+// the results file from ballooning on binaries with lots of sections.
+const SECTION_HASH_LENGTH: usize = 16;
+// Same heuristic git uses: a NUL byte near the start of the file means it isn't text
+const BINARY_SNIFF_LENGTH: u64 = 8_000;
+const ELF_MAGIC: [u8; 4] = [0x7f, b'E', b'L', b'F'];
+const ELF_SECTION_NOBITS: u32 = 8;
+const MAX_ELF_STRING_TABLE_SIZE: u64 = 1_048_576;
+
+#[allow(unused)]
+#[derive(Debug, Default, Clone, PartialEq, Serialize, Deserialize)]
+pub struct BinarySummary {
+    #[serde(default)]
+    pub(crate) elf_sections: Vec<ElfSection>,
+}
+
+#[allow(unused)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ElfSection {
+    pub(crate) name: String,
+    pub(crate) offset: u64,
+    pub(crate) size: u64,
+    pub(crate) hash: String,
+}
+
+#[allow(unused)]
+#[derive(Debug, Default, Clone, PartialEq, Serialize, Deserialize)]
+pub struct BinaryDiff {
+    pub(crate) size_delta: i64,
+    pub(crate) changed_bytes: u64,
+    pub(crate) percent_changed: f64,
+    pub(crate) changed_ranges: Vec<ByteRange>,
+    pub(crate) changed_sections: Vec<String>,
+    pub(crate) added_sections: Vec<String>,
+    pub(crate) removed_sections: Vec<String>,
+}
+
+#[allow(unused)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ByteRange {
+    pub(crate) offset: u64,
+    pub(crate) length: u64,
+}
+
+#[allow(unused)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct BlockHashList {
+    pub(crate) block_size: u64,
+    pub(crate) hashes: Vec<String>,
+}
+
+// Binary diffs, changed block offsets and fast mode spot checks all work from the same block
+// hashes. They are collected as the file streams past for the whole-file hashes, so a large
+// binary is only read once.
+pub struct BlockHasher {
+    block_size: u64,
+    block_length: u64,
+    hasher: blake3::Hasher,
+    hashes: Vec<String>,
+}
+
+impl BlockHasher {
+    pub fn new(block_size: u64) -> Self {
+        Self {
+            block_size: block_size.max(1),
+            block_length: 0,
+            hasher: blake3::Hasher::new(),
+            hashes: Vec::new(),
+        }
+    }
+
+    pub fn update(&mut self, mut data: &[u8]) {
+        // Reads don't line up with blocks, so a read can finish one block and start the next
+        while !data.is_empty() {
+            let remaining = (self.block_size - self.block_length).min(data.len() as u64) as usize;
+            self.hasher.update(&data[..remaining]);
+            self.block_length += remaining as u64;
+            data = &data[remaining..];
+            if self.block_length == self.block_size {
+                self.finish_block();
+            }
+        }
+    }
+
+    fn finish_block(&mut self) {
+        self.hashes
+            .push(hex::encode_upper(self.hasher.finalize().as_bytes()));
+        self.hasher.reset();
+        self.block_length = 0;
+    }
+
+    pub fn finalize(mut self) -> BlockHashList {
+        // The last block is usually short
+        if self.block_length > 0 {
+            self.finish_block();
+        }
+        BlockHashList {
+            block_size: self.block_size,
+            hashes: self.hashes,
+        }
+    }
+}
+
+pub fn is_binary_file(path: &Path) -> bool {
+    let file = match File::open(path) {
+        Ok(file) => file,
+        Err(_) => return false,
+    };
+    let mut start: Vec<u8> = Vec::new();
+    if file
+        .take(BINARY_SNIFF_LENGTH)
+        .read_to_end(&mut start)
+        .is_err()
+    {
+        return false;
+    }
+    start.contains(&0)
+}
+
+pub fn get_block_hash_size(
+    is_binary: bool,
+    block_hash_threshold: u64,
+    block_size: u64,
+    file_size: u64,
+) -> u64 {
+    // Binary files always get block hashes for the binary diff. Anything else only gets them
+    // once it reaches the threshold, and a zero threshold turns that off.
+    if is_binary || (block_hash_threshold > 0 && file_size >= block_hash_threshold) {
+        return block_size;
+    }
+    0
+}
+
+pub fn get_changed_block_offsets(
+    old_blocks: &BlockHashList,
+    new_blocks: &BlockHashList,
+) -> Vec<u64> {
+    // Different block sizes cut the file in different places, so nothing lines up to compare
+    if old_blocks.block_size != new_blocks.block_size {
+        return Vec::new();
+    }
+    let block_count = old_blocks.hashes.len().max(new_blocks.hashes.len());
+    // A block only one side has was appended or truncated, which is a change too
+    (0..block_count)
+        .filter(|index| old_blocks.hashes.get(*index) != new_blocks.hashes.get(*index))
+        .map(|index| index as u64 * new_blocks.block_size)
+        .collect()
+}
+
+// Rereads just the given blocks and returns the offsets of those that no longer match. This is
+// how a large file gets spot checked without reading all of it.
+pub fn verify_blocks(
+    path: &Path,
+    block_hashes: &BlockHashList,
+    block_indexes: &[usize],
+) -> std::io::Result<Vec<u64>> {
+    let mut file = File::open(path)?;
+    let mut block: Vec<u8> = Vec::with_capacity(block_hashes.block_size as usize);
+    let mut changed_offsets: Vec<u64> = Vec::new();
+    for block_index in block_indexes {
+        let Some(expected_hash) = block_hashes.hashes.get(*block_index) else {
+            continue;
+        };
+        let offset = *block_index as u64 * block_hashes.block_size;
+        file.seek(SeekFrom::Start(offset))?;
+        block.clear();
+        (&mut file)
+            .take(block_hashes.block_size)
+            .read_to_end(&mut block)?;
+        let mut block_hasher = BlockHasher::new(block_hashes.block_size);
+        block_hasher.update(&block);
+        if block_hasher.finalize().hashes.first() != Some(expected_hash) {
+            changed_offsets.push(offset);
+        }
+    }
+    Ok(changed_offsets)
+}
+
+pub fn get_binary_summary(path: &Path) -> Option<BinarySummary> {
+    let mut file = match File::open(path) {
+        Ok(file) => file,
+        Err(e) => {
+            warn!(
+                "Cannot open file for binary analysis: {}: {}",
+                path.display(),
+                e
+            );
+            return None;
+        }
+    };
+
+    // Most binaries on a Windows host or in a Linux data directory aren't ELF. That's fine, they
+    // just won't have sections to report.
+    let elf_sections = match get_elf_sections(&mut file) {
+        Ok(elf_sections) => elf_sections,
+        Err(e) => {
+            debug!("No ELF sections for {}: {}", path.display(), e);
+            vec![]
+        }
+    };
+
+    Some(BinarySummary { elf_sections })
+}
+
+pub fn get_elf_sections<R: Read + Seek>(reader: &mut R) -> Result<Vec<ElfSection>, String> {
+    let mut header = [0u8; 64];
+    reader.seek(SeekFrom::Start(0)).map_err(|e| e.to_string())?;
+    reader
+        .read_exact(&mut header[..52])
+        .map_err(|_| "File is too small for an ELF header".to_string())?;
+    if header[..4] != ELF_MAGIC {
+        return Err("Missing ELF magic".to_string());
+    }
+
+    let is_64_bit = match header[4] {
+        1 => false,
+        2 => true,
+        class => return Err(format!("Unknown ELF class {}", class)),
+    };
+    let is_little_endian = match header[5] {
+        1 => true,
+        2 => false,
+        data => return Err(format!("Unknown ELF data encoding {}", data)),
+    };
+    if is_64_bit {
+        reader
+            .read_exact(&mut header[52..])
+            .map_err(|_| "File is too small for an ELF header".to_string())?;
+    }
+    let elf = ElfLayout {
+        is_64_bit,
+        is_little_endian,
+    };
+
+    // The section header table is the only part needed. Offsets differ between 32 and 64 bit.
+    let (section_table_offset, section_header_size, section_count, string_table_index) =
+        if is_64_bit {
+            (
+                elf.read_u64(&header, 0x28),
+                elf.read_u16(&header, 0x3A) as u64,
+                elf.read_u16(&header, 0x3C) as u64,
+                elf.read_u16(&header, 0x3E) as u64,
+            )
+        } else {
+            (
+                elf.read_u32(&header, 0x20) as u64,
+                elf.read_u16(&header, 0x2E) as u64,
+                elf.read_u16(&header, 0x30) as u64,
+                elf.read_u16(&header, 0x32) as u64,
+            )
+        };
+    let minimum_header_size = if is_64_bit { 0x40 } else { 0x28 };
+    if section_table_offset == 0 || section_count == 0 {
+        return Ok(vec![]);
+    }
+    if section_header_size < minimum_header_size || string_table_index >= section_count {
+        return Err("Invalid ELF section header table".to_string());
+    }
+
+    // Both header fields are 16 bits, so a corrupt header could ask for up to 4GB. The table has
+    // to fit in the file before anything gets allocated for it.
+    let section_table_size = section_header_size * section_count;
+    let file_length = reader.seek(SeekFrom::End(0)).map_err(|e| e.to_string())?;
+    match section_table_offset.checked_add(section_table_size) {
+        Some(section_table_end) if section_table_end <= file_length => {}
+        _ => return Err("ELF section header table is truncated".to_string()),
+    }
+    let mut section_table = vec![0u8; section_table_size as usize];
+    reader
+        .seek(SeekFrom::Start(section_table_offset))
+        .map_err(|e| e.to_string())?;
+    reader
+        .read_exact(&mut section_table)
+        .map_err(|_| "ELF section header table is truncated".to_string())?;
+
+    let section_headers: Vec<ElfSectionHeader> = section_table
+        .chunks(section_header_size as usize)
+        .map(|section_header| elf.read_section_header(section_header))
+        .collect();
+
+    let string_table_header = &section_headers[string_table_index as usize];
+    if string_table_header.size > MAX_ELF_STRING_TABLE_SIZE {
+        return Err("ELF section name table is too large".to_string());
+    }
+    let mut string_table = vec![0u8; string_table_header.size as usize];
+    reader
+        .seek(SeekFrom::Start(string_table_header.offset))
+        .map_err(|e| e.to_string())?;
+    reader
+        .read_exact(&mut string_table)
+        .map_err(|_| "ELF section name table is truncated".to_string())?;
+
+    let mut elf_sections: Vec<ElfSection> = Vec::new();
+    // Section 0 is always the reserved null section
+    for section_header in section_headers.iter().skip(1) {
+        // .bss and friends take no space in the file, so there's nothing to hash
+        if section_header.section_type == ELF_SECTION_NOBITS {
+            continue;
+        }
+        let name = get_section_name(&string_table, section_header.name_offset as usize);
+        reader
+            .seek(SeekFrom::Start(section_header.offset))
+            .map_err(|e| e.to_string())?;
+        let mut hasher = Params::new().hash_length(SECTION_HASH_LENGTH).to_state();
+        let mut section_reader = (&mut *reader).take(section_header.size);
+        let mut read_buffer = [0u8; 4_096];
+        let mut hashed_length: u64 = 0;
+        loop {
+            let part_len = section_reader
+                .read(&mut read_buffer)
+                .map_err(|e| e.to_string())?;
+            if part_len == 0 {
+                break;
+            }
+            hasher.update(&read_buffer[..part_len]);
+            hashed_length += part_len as u64;
+        }
+        if hashed_length != section_header.size {
+            return Err(format!("ELF section {} is truncated", name));
+        }
+        elf_sections.push(ElfSection {
+            name,
+            offset: section_header.offset,
+            size: section_header.size,
+            hash: hasher.finalize().to_hex().to_ascii_uppercase(),
+        });
+    }
+
+    Ok(elf_sections)
+}
+
+struct ElfLayout {
+    is_64_bit: bool,
+    is_little_endian: bool,
+}
+
+struct ElfSectionHeader {
+    name_offset: u32,
+    section_type: u32,
+    offset: u64,
+    size: u64,
+}
+
+impl ElfLayout {
+    fn read_u16(&self, data: &[u8], offset: usize) -> u16 {
+        let bytes: [u8; 2] = data[offset..offset + 2].try_into().unwrap();
+        if self.is_little_endian {
+            u16::from_le_bytes(bytes)
+        } else {
+            u16::from_be_bytes(bytes)
+        }
+    }
+
+    fn read_u32(&self, data: &[u8], offset: usize) -> u32 {
+        let bytes: [u8; 4] = data[offset..offset + 4].try_into().unwrap();
+        if self.is_little_endian {
+            u32::from_le_bytes(bytes)
+        } else {
+            u32::from_be_bytes(bytes)
+        }
+    }
+
+    fn read_u64(&self, data: &[u8], offset: usize) -> u64 {
+        let bytes: [u8; 8] = data[offset..offset + 8].try_into().unwrap();
+        if self.is_little_endian {
+            u64::from_le_bytes(bytes)
+        } else {
+            u64::from_be_bytes(bytes)
+        }
+    }
+
+    fn read_section_header(&self, data: &[u8]) -> ElfSectionHeader {
+        if self.is_64_bit {
+            ElfSectionHeader {
+                name_offset: self.read_u32(data, 0x00),
+                section_type: self.read_u32(data, 0x04),
+                offset: self.read_u64(data, 0x18),
+                size: self.read_u64(data, 0x20),
+            }
+        } else {
+            ElfSectionHeader {
+                name_offset: self.read_u32(data, 0x00),
+                section_type: self.read_u32(data, 0x04),
+                offset: self.read_u32(data, 0x10) as u64,
+                size: self.read_u32(data, 0x14) as u64,
+            }
+        }
+    }
+}
+
+fn get_section_name(string_table: &[u8], name_offset: usize) -> String {
+    if name_offset >= string_table.len() {
+        return "".to_string();
+    }
+    let name = &string_table[name_offset..];
+    let name_length = name
+        .iter()
+        .position(|byte| *byte == 0)
+        .unwrap_or(name.len());
+    String::from_utf8_lossy(&name[..name_length]).to_string()
+}
+
+pub fn get_binary_diff(
+    old_summary: &BinarySummary,
+    new_summary: &BinarySummary,
+    old_blocks: Option<&BlockHashList>,
+    new_blocks: Option<&BlockHashList>,
+    old_size: u64,
+    new_size: u64,
+) -> BinaryDiff {
+    let largest_size = old_size.max(new_size);
+    let mut changed_ranges: Vec<ByteRange> = Vec::new();
+
+    match (old_blocks, new_blocks) {
+        (Some(old_blocks), Some(new_blocks))
+            if old_blocks.block_size == new_blocks.block_size && new_blocks.block_size > 0 =>
+        {
+            let block_size = new_blocks.block_size;
+            for offset in get_changed_block_offsets(old_blocks, new_blocks) {
+                let length = block_size.min(largest_size.saturating_sub(offset));
+                // Neighbouring blocks are merged so a rewrite shows up as one range
+                match changed_ranges.last_mut() {
+                    Some(last_range) if last_range.offset + last_range.length == offset => {
+                        last_range.length += length
+                    }
+                    _ => changed_ranges.push(ByteRange { offset, length }),
+                }
+            }
+        }
+        _ => {
+            // Blocks of different sizes can't be lined up, so all that can be said is that the
+            // file changed somewhere.
+            warn!("Block hashes don't line up between scans: Reporting the whole file as changed");
+            if largest_size > 0 {
+                changed_ranges.push(ByteRange {
+                    offset: 0,
+                    length: largest_size,
+                });
+            }
+        }
+    }
+
+    let changed_bytes: u64 = changed_ranges.iter().map(|range| range.length).sum();
+    let percent_changed = if largest_size == 0 {
+        0.0
+    } else {
+        changed_bytes as f64 * 100.0 / largest_size as f64
+    };
+
+    let mut changed_sections: Vec<String> = Vec::new();
+    let mut removed_sections: Vec<String> = Vec::new();
+    for old_section in &old_summary.elf_sections {
+        match new_summary
+            .elf_sections
+            .iter()
+            .find(|new_section| new_section.name == old_section.name)
+        {
+            Some(new_section) => {
+                if new_section.hash != old_section.hash || new_section.size != old_section.size {
+                    changed_sections.push(old_section.name.clone());
+                }
+            }
+            None => removed_sections.push(old_section.name.clone()),
+        }
+    }
+    let added_sections: Vec<String> = new_summary
+        .elf_sections
+        .iter()
+        .filter(|new_section| {
+            !old_summary
+                .elf_sections
+                .iter()
+                .any(|old_section| old_section.name == new_section.name)
+        })
+        .map(|new_section| new_section.name.clone())
+        .collect();
+
+    BinaryDiff {
+        size_delta: new_size as i64 - old_size as i64,
+        changed_bytes,
+        percent_changed,
+        changed_ranges,
+        changed_sections,
+        added_sections,
+        removed_sections,
+    }
+}
@@ -0,0 +1,231 @@
+use crate::file::FileScanResult;
+use crate::hashing::HashAlgorithm;
+use crate::package::PackageStatus;
+use crate::scan_settings::ChangeRule;
+use crate::severity::Severity;
+use serde::{Deserialize, Serialize};
+use std::fmt;
+
+#[allow(unused)]
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ChangeType {
+    #[default]
+    Unchanged,
+    Added,
+    Modified,
+    Deleted,
+    Moved,
+    Unreadable,
+}
+
+#[allow(unused)]
+#[derive(Debug, Default, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ChangeSummary {
+    pub(crate) added: u64,
+    pub(crate) modified: u64,
+    pub(crate) deleted: u64,
+    pub(crate) moved: u64,
+    pub(crate) unchanged: u64,
+    #[serde(default)]
+    pub(crate) unreadable: u64,
+    #[serde(default)]
+    pub(crate) suppressed: u64,
+    #[serde(default)]
+    pub(crate) critical: u64,
+    #[serde(default)]
+    pub(crate) high: u64,
+    #[serde(default)]
+    pub(crate) medium: u64,
+    #[serde(default)]
+    pub(crate) low: u64,
+    #[serde(default)]
+    pub(crate) verified: u64,
+    #[serde(default)]
+    pub(crate) cached: u64,
+    #[serde(default)]
+    pub(crate) ioc_matches: u64,
+    #[serde(default)]
+    pub(crate) package_intact: u64,
+    #[serde(default)]
+    pub(crate) package_modified: u64,
+    #[serde(default)]
+    pub(crate) package_unowned: u64,
+}
+
+impl ChangeSummary {
+    pub fn add(&mut self, change_type: ChangeType) {
+        match change_type {
+            ChangeType::Unchanged => self.unchanged += 1,
+            ChangeType::Added => self.added += 1,
+            ChangeType::Modified => self.modified += 1,
+            ChangeType::Deleted => self.deleted += 1,
+            ChangeType::Moved => self.moved += 1,
+            ChangeType::Unreadable => self.unreadable += 1,
+        }
+    }
+
+    pub fn add_hash_source(&mut self, result: &FileScanResult) {
+        // Only files with hashes count. Directories and unreadable paths were never verified.
+        if result.hashes.is_empty() {
+            return;
+        }
+        if result.hashes_cached {
+            self.cached += 1;
+        } else {
+            self.verified += 1;
+        }
+    }
+
+    pub fn add_ioc_matches(&mut self, result: &FileScanResult) {
+        if !result.ioc_matches.is_empty() {
+            self.ioc_matches += 1;
+        }
+    }
+
+    pub fn add_package_status(&mut self, result: &FileScanResult) {
+        match result.package.as_ref().map(|package| package.status) {
+            Some(PackageStatus::Intact) => self.package_intact += 1,
+            Some(PackageStatus::Modified) => self.package_modified += 1,
+            Some(PackageStatus::Unowned) => self.package_unowned += 1,
+            None => {}
+        }
+    }
+
+    pub fn add_severity(&mut self, severity: Severity) {
+        match severity {
+            Severity::None => {}
+            Severity::Low => self.low += 1,
+            Severity::Medium => self.medium += 1,
+            Severity::High => self.high += 1,
+            Severity::Critical => self.critical += 1,
+        }
+    }
+}
+
+impl fmt::Display for ChangeSummary {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "Added:{}, Modified:{}, Deleted:{}, Moved:{}, Unchanged:{}, Unreadable:{}, \
+            Suppressed:{}, Critical:{}, High:{}, Medium:{}, Low:{}, Verified:{}, Cached:{}, \
+            IOC:{}, PackageIntact:{}, PackageModified:{}, Unowned:{}",
+            self.added,
+            self.modified,
+            self.deleted,
+            self.moved,
+            self.unchanged,
+            self.unreadable,
+            self.suppressed,
+            self.critical,
+            self.high,
+            self.medium,
+            self.low,
+            self.verified,
+            self.cached,
+            self.ioc_matches,
+            self.package_intact,
+            self.package_modified,
+            self.package_unowned
+        )
+    }
+}
+
+#[allow(unused)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct FieldChange {
+    pub(crate) field: String,
+    pub(crate) old_value: String,
+    pub(crate) new_value: String,
+    #[serde(default)]
+    pub(crate) severity: Severity,
+}
+
+impl FieldChange {
+    pub fn new<T: ToString>(field: &str, old_value: T, new_value: T) -> Self {
+        Self {
+            field: field.to_string(),
+            old_value: old_value.to_string(),
+            new_value: new_value.to_string(),
+            severity: Severity::None,
+        }
+    }
+}
+
+pub fn get_changed_fields(changes: &Vec<FieldChange>) -> Vec<String> {
+    // A field may show up more than once in the change records (e.g. multiple ACL entries), but
+    // the summary should only name it once and in the order it was first detected.
+    let mut changed_fields: Vec<String> = Vec::new();
+    for change in changes {
+        if !changed_fields.contains(&change.field) {
+            changed_fields.push(change.field.clone());
+        }
+    }
+    changed_fields
+}
+
+pub fn get_change_type(changes: &Vec<FieldChange>) -> ChangeType {
+    if changes.is_empty() {
+        return ChangeType::Unchanged;
+    }
+    // A path that stopped or started existing is reported as a deletion or addition rather than
+    // a modification. Content found under a new path is reported as a move, and a path that can
+    // no longer be read is reported as unreadable.
+    for change in changes {
+        if change.field == "path" {
+            return ChangeType::Moved;
+        }
+        if change.field == "readable" && change.new_value == "false" {
+            return ChangeType::Unreadable;
+        }
+        if change.field != "exists" {
+            continue;
+        }
+        if change.old_value == "true" && change.new_value == "false" {
+            return ChangeType::Deleted;
+        }
+        if change.old_value == "false" && change.new_value == "true" {
+            return ChangeType::Added;
+        }
+    }
+    ChangeType::Modified
+}
+
+pub fn apply_change_rule(changes: Vec<FieldChange>, change_rule: &ChangeRule) -> Vec<FieldChange> {
+    changes
+        .into_iter()
+        .filter(|change| is_change_monitored(change, change_rule))
+        .collect()
+}
+
+pub fn is_change_monitored(change: &FieldChange, change_rule: &ChangeRule) -> bool {
+    if HashAlgorithm::from_name(&change.field).is_some() || change.field == "directory_hash" {
+        return change_rule.hashes;
+    }
+    match change.field.as_str() {
+        "size" => {
+            if !change_rule.size {
+                return false;
+            }
+            if !change_rule.allow_size_growth {
+                return true;
+            }
+            // A growing file is expected, so only a shrinking file is reported
+            match (
+                change.old_value.parse::<u64>(),
+                change.new_value.parse::<u64>(),
+            ) {
+                (Ok(old_size), Ok(new_size)) => new_size < old_size,
+                _ => true,
+            }
+        }
+        "attributes" | "is_readonly" => change_rule.attributes,
+        "owner" => change_rule.owner,
+        "is_symbolic_link" => change_rule.symbolic_link,
+        "modified_time" => change_rule.modified_time,
+        "creation_time" => change_rule.creation_time,
+        "discretionary_acl" | "system_acl" => change_rule.acl,
+        // Existence, readability and path changes are always reported
+        _ => true,
+    }
+}
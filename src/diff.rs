@@ -0,0 +1,264 @@
+use crate::binary::BinaryDiff;
+use crate::changes::{ChangeSummary, ChangeType};
+use crate::file::{
+    correlate_moved_results, find_deleted_results, get_binary_change, get_changed_blocks,
+    get_content_diff, get_directory_entry_changes, get_file_changes, get_similarity,
+    FileScanResult,
+};
+use crate::helpers::load_results_from_file;
+use crate::osfig_state::load_osfig_settings;
+use crate::registry::{compare_registry_scans, RegistryResult};
+use crate::scan_index::ScanIndex;
+use crate::scan_settings::{get_default_change_rule, SeverityRule, DEFAULT_CONTENT_DIFF_CONTEXT};
+use crate::severity::{classify_changes, classify_registry_changes, Severity};
+use log::{error, info};
+use serde::{Deserialize, Serialize};
+use std::fs::File;
+use std::io::{BufWriter, Error, ErrorKind};
+use std::path::Path;
+
+#[allow(unused)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DiffReport {
+    pub(crate) older: String,
+    pub(crate) newer: String,
+    pub(crate) summary: ChangeSummary,
+    pub(crate) files: Vec<FileScanResult>,
+    pub(crate) registry: Vec<RegistryResult>,
+}
+
+pub fn run_diff(options: &[String]) -> std::io::Result<()> {
+    let mut paths: Vec<&String> = Vec::new();
+    let mut output_path: Option<&String> = None;
+    let mut options_iter = options.iter();
+    while let Some(option) = options_iter.next() {
+        if option == "--output" {
+            output_path = options_iter.next();
+            if output_path.is_none() {
+                return Err(diff_usage_error("Missing path after --output"));
+            }
+        } else {
+            paths.push(option);
+        }
+    }
+    if paths.len() != 2 {
+        return Err(diff_usage_error("Expected exactly two results files"));
+    }
+
+    // Changes are classified with the severity rules from the settings file, the same as a scan
+    let osfig_settings = load_osfig_settings();
+    info!("Comparing results {} to {}", paths[0], paths[1]);
+    let report = match get_diff_report(
+        paths[0],
+        paths[1],
+        &osfig_settings.scan_settings.severity_rules,
+    ) {
+        Ok(report) => report,
+        Err(e) => {
+            error!("{}", e);
+            return Err(Error::new(ErrorKind::InvalidData, e));
+        }
+    };
+    info!("Change summary: {}", report.summary);
+
+    match output_path {
+        Some(output_path) => {
+            let json_file = File::create(output_path)?;
+            let file_writer = BufWriter::new(json_file);
+            serde_json::to_writer_pretty(file_writer, &report)?;
+            info!("Diff report saved to file {}", output_path);
+            println!("Diff report saved to file {}", output_path);
+        }
+        None => print_diff_report(&report),
+    }
+
+    Ok(())
+}
+
+fn diff_usage_error(message: &str) -> Error {
+    error!("Invalid diff options: {}", message);
+    println!(
+        "Usage: osfig diff <older results.json> <newer results.json> [--output <report.json>]"
+    );
+    Error::new(ErrorKind::InvalidInput, message)
+}
+
+pub fn get_diff_report(
+    older_path: &str,
+    newer_path: &str,
+    severity_rules: &[SeverityRule],
+) -> Result<DiffReport, String> {
+    let older_results = load_results_from_file(older_path)?;
+    let newer_results = load_results_from_file(newer_path)?;
+
+    let mut diff_results = diff_file_results(&older_results.files, &newer_results.files);
+    let mut registry_results =
+        compare_registry_scans(&older_results.registry, newer_results.registry);
+    classify_changes(&mut diff_results, severity_rules);
+    classify_registry_changes(&mut registry_results, severity_rules);
+    // IOC matches come from the newer scan and are critical whether or not anything changed
+    for result in diff_results.iter_mut() {
+        if !result.ioc_matches.is_empty() {
+            result.severity = Severity::Critical;
+        }
+    }
+
+    let mut summary = ChangeSummary::default();
+    for result in &diff_results {
+        summary.add(result.change_type);
+        summary.add_severity(result.severity);
+        summary.add_ioc_matches(result);
+    }
+    for result in &registry_results {
+        summary.add(result.change_type);
+        summary.add_severity(result.severity);
+    }
+
+    // The report only needs to carry what changed. The summary still accounts for the rest.
+    Ok(DiffReport {
+        older: older_path.to_string(),
+        newer: newer_path.to_string(),
+        summary,
+        files: diff_results
+            .into_iter()
+            .filter(|result| {
+                result.change_type != ChangeType::Unchanged || !result.ioc_matches.is_empty()
+            })
+            .collect(),
+        registry: registry_results
+            .into_iter()
+            .filter(|result| result.change_type != ChangeType::Unchanged)
+            .collect(),
+    })
+}
+
+pub fn diff_file_results(
+    older: &Vec<FileScanResult>,
+    newer: &Vec<FileScanResult>,
+) -> Vec<FileScanResult> {
+    // This is the same comparison a scan runs against its prior results. The change data stored
+    // in the newer results was relative to whatever scan preceded it, so it is all replaced.
+    let older_index = ScanIndex::new(older);
+    let mut results: Vec<FileScanResult> = Vec::with_capacity(newer.len());
+    for newer_entry in newer {
+        let mut result = newer_entry.clone();
+        result.previous_path = None;
+        result.content_diff = "".to_string();
+        result.content_diff_hunks = vec![];
+        result.suppressed = false;
+        result.suppressed_by = None;
+        result.binary_diff = None;
+        result.similarity = None;
+        result.directory_changes = vec![];
+        result.changed_block_offsets = vec![];
+        result.set_changes(get_file_changes(&older_index, &result));
+
+        if result.is_modified && !result.contents.is_empty() {
+            let (content_diff, content_diff_hunks) =
+                get_content_diff(&result, &older_index, DEFAULT_CONTENT_DIFF_CONTEXT);
+            result.content_diff = content_diff;
+            result.content_diff_hunks = content_diff_hunks;
+        }
+        if result.is_modified && result.binary_summary.is_some() {
+            result.binary_diff = get_binary_change(&result, &older_index);
+        }
+        if result.is_modified {
+            result.changed_block_offsets = get_changed_blocks(&result, &older_index);
+            result.similarity = get_similarity(&result, &older_index);
+            result.directory_changes = get_directory_entry_changes(&result, &older_index);
+        }
+        results.push(result);
+    }
+
+    // Both results sets were already scoped by the settings of their own scans, so every path
+    // missing from the newer results is a deletion. Those settings aren't known here, so every
+    // change is kept.
+    let change_rule_for = |_: &Path| Some(get_default_change_rule());
+    results.append(&mut find_deleted_results(older, &results, change_rule_for));
+
+    correlate_moved_results(&older_index, results, change_rule_for)
+}
+
+pub fn print_diff_report(report: &DiffReport) {
+    println!("Comparing {} to {}", report.older, report.newer);
+    for result in &report.files {
+        let change_type = format!("{:?}", result.change_type).to_uppercase();
+        println!("[{}] {}", change_type, result.path.display());
+        println!(
+            "    severity: {}",
+            format!("{:?}", result.severity).to_lowercase()
+        );
+        for ioc_match in &result.ioc_matches {
+            println!(
+                "    ioc: {} {} from {} ({})",
+                ioc_match.algorithm, ioc_match.hash, ioc_match.source, ioc_match.name
+            );
+        }
+        for change in &result.changes {
+            println!(
+                "    {}: {} -> {}",
+                change.field, change.old_value, change.new_value
+            );
+        }
+        for line in result.content_diff.lines() {
+            println!("    {}", line);
+        }
+        for directory_change in &result.directory_changes {
+            let change_type = format!("{:?}", directory_change.change_type).to_lowercase();
+            println!("    {}: {}", change_type, directory_change.path.display());
+        }
+        if let Some(similarity) = result.similarity {
+            println!("    similarity: {}%", similarity);
+        }
+        if let Some(block_hashes) = &result.block_hashes {
+            for offset in &result.changed_block_offsets {
+                println!(
+                    "    block {}..{} changed",
+                    offset,
+                    offset + block_hashes.block_size
+                );
+            }
+        }
+        if let Some(binary_diff) = &result.binary_diff {
+            print_binary_diff(binary_diff);
+        }
+    }
+    for result in &report.registry {
+        let change_type = format!("{:?}", result.change_type).to_uppercase();
+        println!("[{}] {}", change_type, result.path);
+        println!(
+            "    severity: {}",
+            format!("{:?}", result.severity).to_lowercase()
+        );
+        for change in &result.changes {
+            println!(
+                "    {}: {} -> {}",
+                change.field, change.old_value, change.new_value
+            );
+        }
+    }
+    println!("{}", report.summary);
+}
+
+fn print_binary_diff(binary_diff: &BinaryDiff) {
+    println!(
+        "    binary: {} bytes changed ({:.2}%), size delta {}",
+        binary_diff.changed_bytes, binary_diff.percent_changed, binary_diff.size_delta
+    );
+    for range in &binary_diff.changed_ranges {
+        println!(
+            "    bytes {}..{} changed",
+            range.offset,
+            range.offset + range.length
+        );
+    }
+    for (label, sections) in [
+        ("changed", &binary_diff.changed_sections),
+        ("added", &binary_diff.added_sections),
+        ("removed", &binary_diff.removed_sections),
+    ] {
+        if !sections.is_empty() {
+            println!("    sections {}: {}", label, sections.join(", "));
+        }
+    }
+}
@@ -1,22 +1,43 @@
+use crate::acknowledge::get_approved_baseline_path;
+use crate::binary::{
+    get_binary_diff, get_binary_summary, get_block_hash_size, get_changed_block_offsets,
+    is_binary_file, verify_blocks, BinaryDiff, BinarySummary, BlockHashList,
+};
+use crate::changes::{
+    apply_change_rule, get_change_type, get_changed_fields, ChangeType, FieldChange,
+};
+use crate::fuzzy::compare_ssdeep;
+use crate::hash_cache::{get_cached_hashes, FileStat, FullRehashState};
 use crate::hashing;
+use crate::hashing::{HashAlgorithm, HashValues};
+use crate::helpers::{load_results_from_file, parse_results, ScanResults};
+use crate::ioc::IocEntry;
+use crate::known_good::KnownGoodStatus;
+use crate::merkle::{
+    get_directory_changes, get_directory_hash, DirectoryEntryChange, DirectoryHash,
+};
+use crate::mmap::get_read_mode;
 use crate::osfig_state::OsfigSettings;
+use crate::package::PackageCheck;
+use crate::scan_error::{has_metadata, is_unreadable, ScanError, ScanOperation};
+use crate::scan_index::ScanIndex;
 use chrono::DateTime;
 use chrono::Utc;
 use filetime::FileTime;
-use glob::{glob, GlobResult};
+use glob::{glob, GlobResult, Pattern};
 use log::{debug, error, info, trace, warn};
-use prettydiff::diff_lines;
 use serde::{Deserialize, Serialize};
-use serde_json;
-use serde_json::from_str;
+use similar::{ChangeTag, TextDiff};
+use std::collections::{HashMap, HashSet};
 use std::fmt::Debug;
 use std::fs::File;
-use std::io::{BufWriter, Read};
+use std::io::Read;
 use std::path::Path;
 use std::path::PathBuf;
 use std::process::exit;
+use std::sync::atomic::{AtomicUsize, Ordering};
 use std::time::SystemTime;
-use std::{fs, thread, time};
+use std::{fs, panic, thread, time};
 
 #[cfg(windows)]
 use {
@@ -27,7 +48,10 @@ use {
 #[cfg(target_os = "linux")]
 use {std::os::unix::fs::MetadataExt, std::os::unix::fs::PermissionsExt};
 
-use crate::scan_settings::FileScanSetting;
+use crate::scan_settings::{
+    get_change_rule, ChangeRule, FileScanSetting, ScanSettings, Suppression,
+};
+use crate::severity::{get_highest_severity, Severity};
 
 #[allow(unused)]
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -39,24 +63,78 @@ pub struct FileScanResult {
     pub(crate) is_symbolic_link: bool,
     pub(crate) is_readonly: bool,
     pub(crate) exists: bool,
-    pub(crate) md5: String,
-    pub(crate) sha256: String,
-    pub(crate) blake2s: String,
+    #[serde(default)]
+    pub(crate) hashes: HashValues,
     pub(crate) creation_time: String,
     pub(crate) modified_time: String,
     pub(crate) access_time: String,
     pub(crate) size: u64,
     pub(crate) attributes: u32,
+    #[serde(default)]
+    pub(crate) owner: String,
     pub(crate) contents: String,
     pub(crate) is_modified: bool,
+    #[serde(default)]
+    pub(crate) change_type: ChangeType,
+    #[serde(default)]
+    pub(crate) changes: Vec<FieldChange>,
+    #[serde(default)]
+    pub(crate) changed_fields: Vec<String>,
+    #[serde(default)]
+    pub(crate) severity: Severity,
+    #[serde(default)]
+    pub(crate) previous_path: Option<Box<PathBuf>>,
     pub(crate) content_diff: String,
-    pub(crate) content_diff_readable: String,
+    #[serde(default)]
+    pub(crate) content_diff_hunks: Vec<DiffHunk>,
+    #[serde(default)]
+    pub(crate) suppressed: bool,
+    #[serde(default)]
+    pub(crate) suppressed_by: Option<Suppression>,
+    #[serde(default)]
+    pub(crate) binary_summary: Option<BinarySummary>,
+    #[serde(default)]
+    pub(crate) binary_diff: Option<BinaryDiff>,
+    #[serde(default)]
+    pub(crate) similarity: Option<u32>,
+    #[serde(default)]
+    pub(crate) directory_hash: Option<DirectoryHash>,
+    #[serde(default)]
+    pub(crate) directory_changes: Vec<DirectoryEntryChange>,
+    #[serde(default)]
+    pub(crate) errors: Vec<ScanError>,
+    #[serde(default)]
+    pub(crate) inode: u64,
+    #[serde(default)]
+    pub(crate) change_time: String,
+    #[serde(default)]
+    pub(crate) hashes_cached: bool,
+    #[serde(default)]
+    pub(crate) block_hashes: Option<BlockHashList>,
+    #[serde(default)]
+    pub(crate) changed_block_offsets: Vec<u64>,
+    #[serde(default)]
+    pub(crate) known_good: KnownGoodStatus,
+    #[serde(default)]
+    pub(crate) ioc_matches: Vec<IocEntry>,
+    #[serde(default)]
+    pub(crate) package: Option<PackageCheck>,
     #[cfg(windows)]
     pub(crate) discretionary_acl: WinAcl,
     #[cfg(windows)]
     pub(crate) system_acl: WinAcl,
 }
 
+#[allow(unused)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct DiffHunk {
+    pub(crate) old_start: usize,
+    pub(crate) old_lines: usize,
+    pub(crate) new_start: usize,
+    pub(crate) new_lines: usize,
+    pub(crate) lines: Vec<String>,
+}
+
 impl FileScanResult {
     fn default_time() -> String {
         DateTime::<Utc>::default().to_string()
@@ -85,18 +163,38 @@ impl Default for FileScanResult {
             is_symbolic_link: false,
             is_readonly: false,
             exists: false,
-            md5: "".to_string(),
-            sha256: "".to_string(),
-            blake2s: "".to_string(),
+            hashes: HashValues::new(),
             creation_time: Self::default_time(),
             modified_time: Self::default_time(),
             access_time: Self::default_time(),
             size: 0,
             attributes: 0,
+            owner: "".to_string(),
             contents: "".to_string(),
             is_modified: false,
+            change_type: ChangeType::Unchanged,
+            changes: vec![],
+            changed_fields: vec![],
+            severity: Severity::None,
+            previous_path: None,
             content_diff: "".to_string(),
-            content_diff_readable: "".to_string(),
+            content_diff_hunks: vec![],
+            suppressed: false,
+            suppressed_by: None,
+            binary_summary: None,
+            binary_diff: None,
+            similarity: None,
+            directory_hash: None,
+            directory_changes: vec![],
+            errors: vec![],
+            inode: 0,
+            change_time: "".to_string(),
+            hashes_cached: false,
+            block_hashes: None,
+            changed_block_offsets: vec![],
+            known_good: KnownGoodStatus::Unknown,
+            ioc_matches: vec![],
+            package: None,
             discretionary_acl: Self::default_acl(),
             system_acl: Self::default_acl(),
         }
@@ -113,18 +211,38 @@ impl Default for FileScanResult {
             is_symbolic_link: false,
             is_readonly: false,
             exists: false,
-            md5: "".to_string(),
-            sha256: "".to_string(),
-            blake2s: "".to_string(),
+            hashes: HashValues::new(),
             creation_time: Self::default_time(),
             modified_time: Self::default_time(),
             access_time: Self::default_time(),
             size: 0,
             attributes: 0,
+            owner: "".to_string(),
             contents: "".to_string(),
             is_modified: false,
+            change_type: ChangeType::Unchanged,
+            changes: vec![],
+            changed_fields: vec![],
+            severity: Severity::None,
+            previous_path: None,
             content_diff: "".to_string(),
-            content_diff_readable: "".to_string(),
+            content_diff_hunks: vec![],
+            suppressed: false,
+            suppressed_by: None,
+            binary_summary: None,
+            binary_diff: None,
+            similarity: None,
+            directory_hash: None,
+            directory_changes: vec![],
+            errors: vec![],
+            inode: 0,
+            change_time: "".to_string(),
+            hashes_cached: false,
+            block_hashes: None,
+            changed_block_offsets: vec![],
+            known_good: KnownGoodStatus::Unknown,
+            ioc_matches: vec![],
+            package: None,
         }
     }
 }
@@ -132,11 +250,34 @@ impl Default for FileScanResult {
 impl FileScanResult {
     #[allow(unused)]
     pub(crate) fn set_path<T: AsRef<Path>>(&mut self, new_path: T) {
-        self.path = Box::new(PathBuf::from(new_path.as_ref()))
+        *self.path = PathBuf::from(new_path.as_ref())
+    }
+
+    pub(crate) fn get_hash(&self, algorithm: HashAlgorithm) -> &str {
+        self.hashes
+            .get(&algorithm)
+            .map(|hash| hash.as_str())
+            .unwrap_or("")
+    }
+
+    pub(crate) fn is_unreadable(&self) -> bool {
+        is_unreadable(&self.errors)
+    }
+
+    pub(crate) fn set_changes(&mut self, changes: Vec<FieldChange>) {
+        self.changed_fields = get_changed_fields(&changes);
+        self.change_type = get_change_type(&changes);
+        self.is_modified = !changes.is_empty();
+        self.severity = get_highest_severity(&changes);
+        self.changes = changes;
     }
 }
 
 pub fn find_newest_file(saved_scans_dir: &str) -> Box<PathBuf> {
+    find_newest_file_excluding(saved_scans_dir, Path::new(""))
+}
+
+pub fn find_newest_file_excluding(saved_scans_dir: &str, excluded_path: &Path) -> Box<PathBuf> {
     let mut newest_path = PathBuf::new();
     let mut newest_timestamp = DateTime::<Utc>::from_timestamp(0i64, 0u32);
 
@@ -144,6 +285,9 @@ pub fn find_newest_file(saved_scans_dir: &str) -> Box<PathBuf> {
         if !result_match.as_mut().unwrap().exists() {
             continue;
         }
+        if result_match.as_mut().unwrap().as_path() == excluded_path {
+            continue;
+        }
         if result_match.as_mut().unwrap().is_dir() {
             continue;
         }
@@ -170,11 +314,50 @@ pub fn find_newest_file(saved_scans_dir: &str) -> Box<PathBuf> {
     Box::new(newest_path)
 }
 
-pub fn get_latest_results(osfig_settings: &OsfigSettings) -> Vec<FileScanResult> {
+// newest_files are the files of the newest results file, which main loads once per run
+pub fn get_latest_results(
+    osfig_settings: &OsfigSettings,
+    newest_files: Vec<FileScanResult>,
+) -> Vec<FileScanResult> {
+    // Once changes have been acknowledged, scans are compared against the approved baseline so
+    // unacknowledged changes keep being reported until someone approves them.
+    let approved_baseline_path = get_approved_baseline_path(osfig_settings);
+    if approved_baseline_path.is_file() {
+        info!(
+            "Comparing against approved baseline: {}",
+            approved_baseline_path.display()
+        );
+        let mut approved_files =
+            match load_results_from_file(approved_baseline_path.to_str().unwrap()) {
+                Ok(approved_baseline) => approved_baseline.files,
+                Err(e) => {
+                    error!("Encountered error reading approved baseline: Error: {}", e);
+                    Vec::new()
+                }
+            };
+        // The baseline only holds the paths scanned when it was seeded plus those acknowledged
+        // since. Anything else, like the paths of a file_scan_settings object added later, is
+        // compared against the newest results instead of being reported as added on every scan.
+        let approved_paths: HashSet<PathBuf> = approved_files
+            .iter()
+            .map(|result| (*result.path).clone())
+            .collect();
+        approved_files.extend(
+            newest_files
+                .into_iter()
+                .filter(|result| !approved_paths.contains(&*result.path)),
+        );
+        return approved_files;
+    }
+
+    newest_files
+}
+
+pub fn get_newest_results(osfig_settings: &OsfigSettings) -> ScanResults {
     let modified_scan_result_path = format!("{}/*.json", osfig_settings.scan_result_path.as_str());
     let results_path = find_newest_file(modified_scan_result_path.as_str());
     if !results_path.exists() & !results_path.is_file() {
-        return Vec::new();
+        return ScanResults::default();
     }
 
     // Attempt to load latest results from json
@@ -193,19 +376,26 @@ pub fn get_latest_results(osfig_settings: &OsfigSettings) -> Vec<FileScanResult>
         }
     };
 
-    let latest_results = match from_str(data.as_ref()) {
+    let latest_results = match parse_results(data.as_ref()) {
         Ok(json_data) => json_data,
         Err(e) => {
             error!("Encountered error reading prior results: Error: {}", e);
-            return Vec::new();
+            return ScanResults::default();
         }
     };
 
-    return latest_results;
+    latest_results
 }
 
-pub fn scan_files(osfig_settings: &OsfigSettings) -> Vec<FileScanResult> {
-    let last_scan_results = get_latest_results(&osfig_settings);
+pub fn scan_files(
+    osfig_settings: &OsfigSettings,
+    newest_files: Vec<FileScanResult>,
+    full_rehash: &FullRehashState,
+) -> Vec<FileScanResult> {
+    // Zero runs since a full rehash means this run is one, so nothing comes from the cache
+    let cache_run = Some(full_rehash.runs_since_full_rehash).filter(|runs| *runs > 0);
+    let last_scan_results = get_latest_results(osfig_settings, newest_files);
+    let last_scan_index = ScanIndex::new(&last_scan_results);
 
     let file_scan_settings = &osfig_settings.scan_settings.file_scan_settings;
     let mut results: Vec<FileScanResult> = Vec::new();
@@ -213,7 +403,15 @@ pub fn scan_files(osfig_settings: &OsfigSettings) -> Vec<FileScanResult> {
     for file_scan_setting in file_scan_settings {
         let patterns: &Vec<String> = &file_scan_setting.file_patterns;
         info!("Using file pattern: {:?}", patterns);
+        let change_rule = get_change_rule(
+            &osfig_settings.scan_settings,
+            &file_scan_setting.change_rule,
+        );
+        info!("Using change rule: {}", change_rule.name);
 
+        // Paths are gathered first so the workers can share them out. Their order here is the
+        // order of the results.
+        let mut entries: Vec<GlobResult> = Vec::new();
         for pattern in patterns {
             // Validate there is no PatternError being returned. Fail fast if so by creating a new glob
             // match that will be empty.
@@ -277,28 +475,309 @@ pub fn scan_files(osfig_settings: &OsfigSettings) -> Vec<FileScanResult> {
                     continue;
                 }
 
-                results.push(scan_file(&file_scan_setting, &entry, &last_scan_results));
-
-                // This is quick and dirty for testing, but quite effective at reducing CPU and Disk
-                // utilization figures. I may keep it for awhile given the simplicity to implement and
-                // how predictable it is in execution for a less knowledgeable end user. It is, after
-                // all, deterministic, albeit crude.
-                debug!("Sleeping thread before next scan");
-                let delay_millis = time::Duration::from_millis(
-                    u64::from(osfig_settings.scan_settings.file_scan_delay).clone(),
-                );
-                thread::sleep(delay_millis);
+                entries.push(entry);
+            }
+        }
+
+        results.append(&mut scan_entries(
+            file_scan_setting,
+            &change_rule,
+            &entries,
+            &last_scan_index,
+            cache_run.filter(|_| file_scan_setting.file_fast_mode),
+            &osfig_settings.scan_settings,
+        ));
+    }
+
+    // Glob only finds paths that currently exist, so anything removed since the last scan has to
+    // be found by reconciling against the prior results.
+    debug!("Checking for paths deleted since the last scan");
+    let mut deleted_results =
+        get_deleted_results(&last_scan_results, &results, &osfig_settings.scan_settings);
+    if !deleted_results.is_empty() {
+        info!(
+            "Found {} paths deleted since the last scan",
+            deleted_results.len()
+        );
+    }
+    results.append(&mut deleted_results);
+
+    // A deleted path and an added path holding the same content are reported as a single move
+    debug!("Checking for paths moved since the last scan");
+    correlate_moved_results(&last_scan_index, results, |path| {
+        get_path_change_rule(path, &osfig_settings.scan_settings)
+    })
+}
+
+pub fn scan_entries(
+    settings: &FileScanSetting,
+    change_rule: &ChangeRule,
+    entries: &[GlobResult],
+    last_scan: &ScanIndex,
+    cache_run: Option<u32>,
+    scan_settings: &ScanSettings,
+) -> Vec<FileScanResult> {
+    // This is quick and dirty for testing, but quite effective at reducing CPU and Disk
+    // utilization figures. I may keep it for awhile given the simplicity to implement and
+    // how predictable it is in execution for a less knowledgeable end user. It is, after
+    // all, deterministic, albeit crude. Each worker pauses after its own scans.
+    let delay_millis = time::Duration::from_millis(u64::from(scan_settings.file_scan_delay));
+    let scan_entry = |entry: &GlobResult| {
+        let result = scan_file(settings, change_rule, entry, last_scan, cache_run);
+        debug!("Sleeping thread before next scan");
+        thread::sleep(delay_millis);
+        result
+    };
+
+    let workers = usize::from(scan_settings.file_scan_workers).clamp(1, entries.len().max(1));
+    if workers == 1 {
+        return entries.iter().map(scan_entry).collect();
+    }
+
+    // Each worker takes the next path as soon as it's done with its last one, so a worker only
+    // ever has one file open. Read buffer memory tops out at file_read_buffer_size per worker.
+    debug!("Scanning {} paths with {} workers", entries.len(), workers);
+    let next_entry = AtomicUsize::new(0);
+    let mut scanned: Vec<Option<FileScanResult>> = (0..entries.len()).map(|_| None).collect();
+    thread::scope(|scope| {
+        let handles: Vec<_> = (0..workers)
+            .map(|_| {
+                scope.spawn(|| {
+                    let mut worker_results: Vec<(usize, FileScanResult)> = Vec::new();
+                    loop {
+                        let index = next_entry.fetch_add(1, Ordering::Relaxed);
+                        let Some(entry) = entries.get(index) else {
+                            break;
+                        };
+                        worker_results.push((index, scan_entry(entry)));
+                    }
+                    worker_results
+                })
+            })
+            .collect();
+        for handle in handles {
+            match handle.join() {
+                Ok(worker_results) => {
+                    for (index, result) in worker_results {
+                        scanned[index] = Some(result);
+                    }
+                }
+                // The worker's finished results went down with it. Carrying on would report
+                // every path it had scanned as deleted, so the panic is passed on instead.
+                Err(panic) => {
+                    error!("Contact Developer! - A scan worker panicked");
+                    panic::resume_unwind(panic)
+                }
+            }
+        }
+    });
+
+    // Slotting results back in by index keeps the output in glob order, however the workers
+    // happened to finish
+    scanned.into_iter().flatten().collect()
+}
+
+pub fn correlate_moved_results<F: Fn(&Path) -> Option<ChangeRule>>(
+    last_scan: &ScanIndex,
+    results: Vec<FileScanResult>,
+    change_rule_for: F,
+) -> Vec<FileScanResult> {
+    // Index the deletions of this run by path so prior entries holding the same content as an
+    // added path can be checked without walking every result
+    let deleted_indexes: HashMap<&PathBuf, usize> = results
+        .iter()
+        .enumerate()
+        .filter(|(_, result)| result.change_type == ChangeType::Deleted)
+        .map(|(index, result)| (&*result.path, index))
+        .collect();
+
+    // Map the index of each added result to the prior entry it was moved from
+    let mut moved_from: HashMap<usize, &FileScanResult> = HashMap::new();
+    let mut moved_away: HashSet<usize> = HashSet::new();
+    if !deleted_indexes.is_empty() {
+        for (index, result) in results.iter().enumerate() {
+            if result.change_type != ChangeType::Added {
+                continue;
+            }
+            for prior_entry in last_scan.get_by_content(result) {
+                let deleted_index = match deleted_indexes.get(&*prior_entry.path) {
+                    Some(deleted_index) => *deleted_index,
+                    None => continue,
+                };
+                if moved_away.contains(&deleted_index) {
+                    continue;
+                }
+                if is_same_content(prior_entry, result) {
+                    info!("Path moved: {:?} -> {:?}", prior_entry.path, result.path);
+                    moved_from.insert(index, prior_entry);
+                    moved_away.insert(deleted_index);
+                    break;
+                }
+            }
+        }
+    }
+
+    let mut correlated_results: Vec<FileScanResult> = Vec::with_capacity(results.len());
+    for (index, mut result) in results.into_iter().enumerate() {
+        if moved_away.contains(&index) {
+            continue;
+        }
+        if let Some(prior_entry) = moved_from.get(&index) {
+            let mut changes = vec![FieldChange::new(
+                "path",
+                prior_entry.path.display(),
+                result.path.display(),
+            )];
+            changes.append(&mut compare_file_results(prior_entry, &result));
+            // The rule of the path it moved to decides which of its other changes are reported
+            if let Some(change_rule) = change_rule_for(&result.path) {
+                changes = apply_change_rule(changes, &change_rule);
+            }
+            result.previous_path = Some(prior_entry.path.clone());
+            result.set_changes(changes);
+        }
+        correlated_results.push(result);
+    }
+
+    correlated_results
+}
+
+pub fn is_same_content(scan_entry: &FileScanResult, this_scan: &FileScanResult) -> bool {
+    // Every empty file hashes the same, so an empty file is never treated as the same content
+    if !scan_entry.is_file || !this_scan.is_file || scan_entry.size == 0 {
+        return false;
+    }
+    if !scan_entry.size.eq(&this_scan.size) {
+        return false;
+    }
+    for algorithm in HashAlgorithm::CONTENT_ALGORITHMS {
+        let (old_hash, new_hash) = (
+            scan_entry.get_hash(algorithm),
+            this_scan.get_hash(algorithm),
+        );
+        if !old_hash.is_empty() && !new_hash.is_empty() {
+            return old_hash.eq(new_hash);
+        }
+    }
+    // Without a strong hash on both sides we can't claim the content is the same
+    false
+}
+
+pub fn get_deleted_results(
+    last_scan: &[FileScanResult],
+    this_scan: &[FileScanResult],
+    scan_settings: &ScanSettings,
+) -> Vec<FileScanResult> {
+    find_deleted_results(last_scan, this_scan, |path| {
+        get_path_change_rule(path, scan_settings)
+    })
+}
+
+// change_rule_for gives the change rule of a path, or None when the path is out of scope
+pub fn find_deleted_results<F: Fn(&Path) -> Option<ChangeRule>>(
+    last_scan: &[FileScanResult],
+    this_scan: &[FileScanResult],
+    change_rule_for: F,
+) -> Vec<FileScanResult> {
+    let scanned_paths: HashSet<&PathBuf> = this_scan.iter().map(|result| &*result.path).collect();
+    let mut deleted_results: Vec<FileScanResult> = Vec::new();
+
+    for scan_entry in last_scan {
+        // Paths that were already missing last time have been reported before
+        if !scan_entry.exists || scanned_paths.contains(&*scan_entry.path) {
+            continue;
+        }
+        let Some(change_rule) = change_rule_for(&scan_entry.path) else {
+            debug!(
+                "Prior path no longer in scope of scan settings: {:?}",
+                scan_entry.path
+            );
+            continue;
+        };
+
+        let mut filescanresult = FileScanResult {
+            scantime: DateTime::<Utc>::from(SystemTime::now()).to_string(),
+            ..Default::default()
+        };
+        filescanresult.set_path(&*scan_entry.path);
+        filescanresult.set_changes(apply_change_rule(
+            compare_file_results(scan_entry, &filescanresult),
+            &change_rule,
+        ));
+        deleted_results.push(filescanresult);
+    }
+
+    deleted_results
+}
+
+pub fn get_path_change_rule(path: &Path, scan_settings: &ScanSettings) -> Option<ChangeRule> {
+    // A path that no longer exists can't be expanded by glob, so the patterns are matched against
+    // the path itself instead. The first file_scan_settings object covering the path supplies its
+    // change rule, and None means no object covers it any more.
+    for file_scan_setting in &scan_settings.file_scan_settings {
+        let mut in_scope = false;
+        for pattern in &file_scan_setting.file_patterns {
+            if path_matches_pattern(path, pattern) {
+                in_scope = true;
+                break;
             }
         }
+        if !in_scope {
+            continue;
+        }
+        let mut ignored = false;
+        for negate_pattern in &file_scan_setting.file_ignore_patterns {
+            if path_matches_pattern(path, negate_pattern) {
+                ignored = true;
+                break;
+            }
+        }
+        if !ignored {
+            return Some(get_change_rule(
+                scan_settings,
+                &file_scan_setting.change_rule,
+            ));
+        }
+    }
+    None
+}
+
+pub fn path_matches_pattern(path: &Path, pattern: &str) -> bool {
+    let glob_pattern = match Pattern::new(pattern) {
+        Ok(glob_pattern) => glob_pattern,
+        Err(e) => {
+            warn!("Invalid Glob Pattern: Error: {}", e);
+            return false;
+        }
+    };
+    if glob_pattern.matches_path(path) {
+        return true;
+    }
+
+    // Glob strips a leading "./" from the paths it returns, so "./file*" finds "file1". Compare
+    // again without it so relative patterns still match the stored paths.
+    let trimmed_pattern = pattern.strip_prefix("./").unwrap_or(pattern);
+    let trimmed_path = path.strip_prefix("./").unwrap_or(path);
+    match Pattern::new(trimmed_pattern) {
+        Ok(glob_pattern) => glob_pattern.matches_path(trimmed_path),
+        Err(_) => false,
     }
+}
 
-    results
+pub fn get_timestamp(seconds: i64, nanoseconds: u32) -> String {
+    // A timestamp chrono can't represent (a corrupt or deliberately forged one) is left empty
+    // rather than taking the whole scan down with it
+    DateTime::from_timestamp(seconds, nanoseconds)
+        .map(|time| time.to_string())
+        .unwrap_or_default()
 }
 
 pub fn scan_file(
     settings: &FileScanSetting,
+    change_rule: &ChangeRule,
     glob_match: &GlobResult,
-    last_scan: &Vec<FileScanResult>,
+    last_scan: &ScanIndex,
+    cache_run: Option<u32>,
 ) -> FileScanResult {
     let path = match glob_match.as_ref() {
         Ok(path) => path,
@@ -313,35 +792,106 @@ pub fn scan_file(
     // This handles the TOCTOU case where a path exists during glob validation, but not once we
     // are scanning. The glob matching ensures we only have real paths to scan. If it's gone by
     // this time, it's likely to be an ephemeral file and not one we would have wanted results
-    // about anyway.
+    // about anyway. If the last scan did know about it though, it still needs to be reported as
+    // a deletion.
     if !path_result.exists() {
         let mut filescanresult: FileScanResult = FileScanResult::default();
         filescanresult.set_path(path);
+        filescanresult.set_changes(get_file_changes(last_scan, &filescanresult));
 
         return filescanresult;
     }
 
-    // At this point, we have a living result for a path. Collect the associated data.
-    debug!("File path confirmed: Collecting hashes");
-    let hashes =
-        hashing::get_all_hashes(&settings.file_hashes, settings.file_read_buffer_size, path);
+    // At this point, we have a living result for a path. Collect the associated data. Anything
+    // that can't be read is recorded in errors and left empty, so it's never mistaken for data.
+    let mut errors: Vec<ScanError> = Vec::new();
 
     debug!("Collecting metadata");
-    let md = fs::metadata(path).unwrap();
+    let md = match fs::metadata(path) {
+        Ok(md) => md,
+        Err(e) => {
+            warn!("Cannot read metadata: {:?}: {}", path.to_str(), e);
+            let mut filescanresult: FileScanResult = FileScanResult::default();
+            filescanresult.set_path(path);
+            filescanresult.exists = true;
+            filescanresult.errors = vec![ScanError::new(ScanOperation::Metadata, &e)];
+            filescanresult.set_changes(apply_change_rule(
+                get_file_changes(last_scan, &filescanresult),
+                change_rule,
+            ));
+            return filescanresult;
+        }
+    };
 
     debug!("Collecting timestamps");
     #[cfg(windows)]
-    let file_time = FileTime::from_creation_time(&md);
-    #[cfg(windows)]
-    let created_time = DateTime::from_timestamp(
-        file_time.unwrap().unix_seconds(),
-        file_time.unwrap().nanoseconds(),
-    );
+    let created_time = FileTime::from_creation_time(&md)
+        .map(|file_time| get_timestamp(file_time.unix_seconds(), file_time.nanoseconds()))
+        .unwrap_or_default();
     #[cfg(target_os = "linux")]
-    let created_time = DateTime::from_timestamp(md.ctime(), 0);
+    let created_time = get_timestamp(md.ctime(), 0);
 
     let file_time = FileTime::from_last_modification_time(&md);
-    let mod_time = DateTime::from_timestamp(file_time.unix_seconds(), file_time.nanoseconds());
+    let mod_time = get_timestamp(file_time.unix_seconds(), file_time.nanoseconds());
+
+    // Windows has no inode or change time in std, so its stat tuple leans on the creation time
+    let stat = FileStat {
+        #[cfg(windows)]
+        inode: 0,
+        #[cfg(target_os = "linux")]
+        inode: md.ino(),
+        #[cfg(windows)]
+        size: md.file_size(),
+        #[cfg(target_os = "linux")]
+        size: md.size(),
+        modified_time: mod_time,
+        #[cfg(windows)]
+        change_time: created_time.clone(),
+        #[cfg(target_os = "linux")]
+        change_time: get_timestamp(md.ctime(), md.ctime_nsec() as u32),
+    };
+
+    // Binary files need block hashes for the binary diff, so they get them during the hashing
+    // read below instead of a second pass over the file
+    let is_binary = md.is_file() && settings.file_binary_analysis && is_binary_file(path);
+    let block_size = get_block_hash_size(
+        is_binary,
+        settings.file_block_hash_threshold,
+        settings.file_binary_block_size,
+        stat.size,
+    );
+    let cached_hashes = match (last_scan.get(path), cache_run) {
+        (Some(prior_result), Some(run)) if md.is_file() => {
+            get_cached_hashes(&settings.file_hashes, prior_result, &stat)
+                .filter(|_| is_spot_check_clean(path, prior_result, block_size, run))
+                .map(|hashes| (hashes, prior_result.block_hashes.clone()))
+        }
+        _ => None,
+    };
+    let hashes_cached = cached_hashes.is_some();
+    let (hashes, block_hashes) = match cached_hashes {
+        Some(cached_hashes) => {
+            debug!("Stat unchanged since the last scan: Reusing hashes");
+            cached_hashes
+        }
+        None => {
+            debug!("File path confirmed: Collecting hashes");
+            match hashing::get_all_hashes_with_blocks(
+                &settings.file_hashes,
+                settings.file_read_buffer_size,
+                path,
+                block_size,
+                get_read_mode(settings.file_mmap, settings.file_mmap_threshold, stat.size),
+            ) {
+                Ok(hashes) => hashes,
+                Err(e) => {
+                    warn!("Cannot open file for hashing: {:?}: {}", path.to_str(), e);
+                    errors.push(ScanError::new(ScanOperation::Hash, &e));
+                    (HashValues::new(), None)
+                }
+            }
+        }
+    };
 
     // Todo research how to avoid update access_time when reading file in Windows
     trace!("access_time not yet implemented");
@@ -350,31 +900,31 @@ pub fn scan_file(
 
     // File contents
     let mut utf8_contents: String = "".to_string();
-    if path.is_file() {
-        if settings.file_content {
-            debug!("Collecting file contents");
-            if File::open(path).is_err() {
-                info!("Cannot open file: {:?}", path.to_str());
-                utf8_contents = String::from("Cannot open file");
-            } else {
-                // Todo Consider using crate simdutf8 in the future for performance enhancements
-                let mut myfile = File::open(path).unwrap();
-                let mut file_contents: Vec<u8> = Vec::new();
-                // Collect file contents but intentionally fail on non-UTF8 content. There's no value in
-                // storing content from other-encoded files.
-                myfile
-                    .read_to_end(&mut file_contents)
-                    .expect("Not valid UTF8");
-                utf8_contents = match std::str::from_utf8(file_contents.as_slice()) {
-                    Ok(contents) => contents,
-                    Err(_e) => "Not valid UTF8",
-                }
-                .parse()
-                .unwrap();
+    if path.is_file() && settings.file_content {
+        debug!("Collecting file contents");
+        match read_utf8_contents(path) {
+            Ok(contents) => utf8_contents = contents,
+            Err(e) => {
+                info!("Cannot collect file contents: {:?}: {}", path.to_str(), e);
+                errors.push(ScanError::new(ScanOperation::Content, &e));
             }
         }
     }
 
+    // Binary analysis
+    let mut binary_summary: Option<BinarySummary> = None;
+    if is_binary {
+        debug!("Collecting binary sections");
+        binary_summary = get_binary_summary(path);
+    }
+
+    // Directory hashing
+    let directory_hash = get_directory_hash(
+        path,
+        settings.directory_hash,
+        settings.file_read_buffer_size,
+    );
+
     #[cfg(windows)]
     let mut dacl_result = WinAcl {
         object_type: "".to_string(),
@@ -396,10 +946,7 @@ pub fn scan_file(
         }
     }
 
-    debug!(
-        "File scan results complete: {}",
-        format!("{:?}", &path.to_str().unwrap())
-    );
+    debug!("File scan results complete: {}", path.display());
 
     // We have our scan data--save into the FileScanResult. Note that I have intentionally placed
     // the scantime value as now() instead of when we first checked the file. It takes only a few
@@ -416,33 +963,57 @@ pub fn scan_file(
         is_symbolic_link: md.is_symlink(),
         is_readonly: md.permissions().readonly(),
         exists: md.is_file() || md.is_dir(),
-        md5: hashes.md5.to_owned(),
-        sha256: hashes.sha256.to_owned(),
-        blake2s: hashes.blake2s.to_owned(),
-        creation_time: created_time.unwrap().to_string(),
-        modified_time: mod_time.unwrap().to_string(),
+        hashes,
+        creation_time: created_time,
+        modified_time: stat.modified_time.clone(),
         access_time: DateTime::<Utc>::default().to_string(),
-        #[cfg(windows)]
-        size: md.file_size(),
-        #[cfg(target_os = "linux")]
-        size: md.size(),
+        size: stat.size,
         #[cfg(windows)]
         attributes: md.file_attributes(),
         #[cfg(target_os = "linux")]
         attributes: md.permissions().mode(),
+        // Windows ownership is already covered by the ACLs
+        #[cfg(windows)]
+        owner: "".to_string(),
+        #[cfg(target_os = "linux")]
+        owner: format!("{}:{}", md.uid(), md.gid()),
         contents: utf8_contents,
         is_modified: false,
+        change_type: ChangeType::Unchanged,
+        changes: vec![],
+        changed_fields: vec![],
+        severity: Severity::None,
+        previous_path: None,
         content_diff: "".to_string(),
-        content_diff_readable: "".to_string(),
+        content_diff_hunks: vec![],
+        suppressed: false,
+        suppressed_by: None,
+        binary_summary,
+        binary_diff: None,
+        similarity: None,
+        directory_hash,
+        directory_changes: vec![],
+        errors,
+        inode: stat.inode,
+        change_time: stat.change_time,
+        hashes_cached,
+        block_hashes,
+        changed_block_offsets: vec![],
+        known_good: KnownGoodStatus::Unknown,
+        ioc_matches: vec![],
+        package: None,
         #[cfg(windows)]
         discretionary_acl: dacl_result,
         #[cfg(windows)]
         system_acl: sacl_result,
     };
 
-    // Check if the file was modified and update flag
+    // Check if the file was modified and record which fields changed
     debug!("checking if file is_modified");
-    filescanresult.is_modified = check_file_modified(last_scan, &filescanresult);
+    filescanresult.set_changes(apply_change_rule(
+        get_file_changes(last_scan, &filescanresult),
+        change_rule,
+    ));
 
     /*
     Since there's no way (with our current data structure) to tell if a file had all lines added
@@ -469,130 +1040,375 @@ pub fn scan_file(
     */
     if settings.file_content && filescanresult.is_modified {
         debug!("File is_modified: Checking content diffs");
-        let (content_diff, content_diff_readable) = get_content_diff(&filescanresult, last_scan);
+        let (content_diff, content_diff_hunks) = get_content_diff(
+            &filescanresult,
+            last_scan,
+            settings.file_content_diff_context,
+        );
 
         filescanresult.content_diff = content_diff;
-        filescanresult.content_diff_readable = content_diff_readable;
+        filescanresult.content_diff_hunks = content_diff_hunks;
+    }
+
+    // Same idea as the content diff, but for files where a line diff means nothing. Both scans
+    // need block hashes for there to be anything to compare.
+    if filescanresult.binary_summary.is_some() && filescanresult.is_modified {
+        debug!("File is_modified: Checking binary diffs");
+        filescanresult.binary_diff = get_binary_change(&filescanresult, last_scan);
+    }
+
+    if filescanresult.is_modified {
+        filescanresult.changed_block_offsets = get_changed_blocks(&filescanresult, last_scan);
+        filescanresult.similarity = get_similarity(&filescanresult, last_scan);
+        filescanresult.directory_changes = get_directory_entry_changes(&filescanresult, last_scan);
     }
 
     filescanresult
 }
 
-pub fn get_content_diff(
+fn is_spot_check_clean(
+    path: &Path,
+    prior_result: &FileScanResult,
+    block_size: u64,
+    run: u32,
+) -> bool {
+    // Reusing hashes trusts the stat tuple. For files with block hashes, one block is reread each
+    // run, a different one every time, so tampering that hides from stat still turns up before
+    // the next full rehash.
+    let Some(block_hashes) = &prior_result.block_hashes else {
+        return block_size == 0;
+    };
+    if block_hashes.block_size != block_size || block_hashes.hashes.is_empty() {
+        return false;
+    }
+    let block_index = run as usize % block_hashes.hashes.len();
+    match verify_blocks(path, block_hashes, &[block_index]) {
+        Ok(changed_offsets) if changed_offsets.is_empty() => true,
+        Ok(changed_offsets) => {
+            warn!(
+                "Block at offset {} changed without a stat change: {:?}",
+                changed_offsets[0],
+                path.to_str()
+            );
+            false
+        }
+        Err(_) => false,
+    }
+}
+
+fn read_utf8_contents(path: &Path) -> std::io::Result<String> {
+    // Todo Consider using crate simdutf8 in the future for performance enhancements
+    let mut file_contents: Vec<u8> = Vec::new();
+    File::open(path)?.read_to_end(&mut file_contents)?;
+    // There's no value in storing content from other-encoded files, so they come back as
+    // InvalidData and the contents are left empty
+    String::from_utf8(file_contents).map_err(|_| {
+        std::io::Error::new(
+            std::io::ErrorKind::InvalidData,
+            "Contents are not valid UTF-8",
+        )
+    })
+}
+
+pub fn get_changed_blocks(new_scan: &FileScanResult, old_scan_results: &ScanIndex) -> Vec<u64> {
+    let scan_entry = match old_scan_results.get(&new_scan.path) {
+        Some(scan_entry) => scan_entry,
+        None => return Vec::new(),
+    };
+    match (&scan_entry.block_hashes, &new_scan.block_hashes) {
+        (Some(old_blocks), Some(new_blocks)) => get_changed_block_offsets(old_blocks, new_blocks),
+        _ => Vec::new(),
+    }
+}
+
+pub fn get_directory_entry_changes(
     new_scan: &FileScanResult,
-    old_scan_results: &Vec<FileScanResult>,
-) -> (String, String) {
-    for scan_entry in old_scan_results {
-        if !scan_entry.path.eq(&new_scan.path) {
-            continue;
+    old_scan_results: &ScanIndex,
+) -> Vec<DirectoryEntryChange> {
+    let scan_entry = match old_scan_results.get(&new_scan.path) {
+        Some(scan_entry) => scan_entry,
+        None => return Vec::new(),
+    };
+    match (&scan_entry.directory_hash, &new_scan.directory_hash) {
+        (Some(old_hash), Some(new_hash)) => {
+            get_directory_changes(old_hash, new_hash, &new_scan.path)
         }
-        debug!("Found matching prior scan entry");
-        let diff_result = diff_lines(&scan_entry.contents, &new_scan.contents);
-        let result = diff_result
-            .to_string()
-            .replace(" [9;31m", "--[[") // Replace RED
-            .replace(" [32m", "++[[") // Replace GREEN
-            .replace("[0m", "]]"); // Replace RESET
-
-        let mut readable_output = String::new();
-        let mut line_counter = 0;
-        for line in result.lines() {
-            line_counter += 1;
-
-            if line.contains("--[[") {
-                readable_output.push_str(
-                    String::from(format!("Line {}: {}\n", line_counter.to_string(), line)).as_str(),
-                );
-                // When dealing with a removed line, we need to decrement the line counter so our
-                // final output line numbers match the file in the file system
-                line_counter -= 1;
-                continue;
-            } else {
-                readable_output.push_str(
-                    String::from(format!("Line {}: {}\n", line_counter.to_string(), line)).as_str(),
-                );
-            }
+        _ => Vec::new(),
+    }
+}
+
+pub fn get_similarity(new_scan: &FileScanResult, old_scan_results: &ScanIndex) -> Option<u32> {
+    // Exact hashes only say the content changed. The ssdeep digests say by how much.
+    let scan_entry = old_scan_results.get(&new_scan.path)?;
+    let old_digest = scan_entry.get_hash(HashAlgorithm::Ssdeep);
+    let new_digest = new_scan.get_hash(HashAlgorithm::Ssdeep);
+    if old_digest.is_empty() || new_digest.is_empty() {
+        return None;
+    }
+    compare_ssdeep(old_digest, new_digest)
+}
+
+pub fn get_binary_change(
+    new_scan: &FileScanResult,
+    old_scan_results: &ScanIndex,
+) -> Option<BinaryDiff> {
+    let scan_entry = old_scan_results.get(&new_scan.path)?;
+    match (&scan_entry.binary_summary, &new_scan.binary_summary) {
+        (Some(old_summary), Some(new_summary)) => Some(get_binary_diff(
+            old_summary,
+            new_summary,
+            scan_entry.block_hashes.as_ref(),
+            new_scan.block_hashes.as_ref(),
+            scan_entry.size,
+            new_scan.size,
+        )),
+        _ => {
+            debug!("Found no binary summary to compare against: Skipping binary diff");
+            None
         }
+    }
+}
 
-        // This removes the final line break that we unnecessarily added.
-        readable_output.pop();
-        return (result.to_string(), readable_output);
+pub fn get_content_diff(
+    new_scan: &FileScanResult,
+    old_scan_results: &ScanIndex,
+    context_lines: usize,
+) -> (String, Vec<DiffHunk>) {
+    if let Some(scan_entry) = old_scan_results.get(&new_scan.path) {
+        debug!("Found matching prior scan entry");
+        return get_unified_diff(
+            &scan_entry.contents,
+            &new_scan.contents,
+            &new_scan.path,
+            context_lines,
+        );
     }
     debug!("Found no matching prior scan entry: Returning empty diffs");
-    return ("".to_string(), "".to_string());
+    ("".to_string(), Vec::new())
 }
 
-pub fn check_file_modified(last_scan: &Vec<FileScanResult>, this_scan: &FileScanResult) -> bool {
-    for scan_entry in last_scan {
-        if &scan_entry.path != &this_scan.path {
-            continue;
+pub fn get_unified_diff(
+    old_contents: &str,
+    new_contents: &str,
+    path: &Path,
+    context_lines: usize,
+) -> (String, Vec<DiffHunk>) {
+    let text_diff = TextDiff::from_lines(old_contents, new_contents);
+
+    // Use the a/ and b/ prefixes git uses so the patch applies with `git apply` or `patch -p1`
+    // from the root of the file system (or the scan directory for relative paths).
+    let patch_path = path.to_string_lossy().trim_start_matches('/').to_string();
+    let mut unified_diff = text_diff.unified_diff();
+    unified_diff.context_radius(context_lines).header(
+        format!("a/{}", patch_path).as_str(),
+        format!("b/{}", patch_path).as_str(),
+    );
+
+    let mut hunks: Vec<DiffHunk> = Vec::new();
+    for unified_hunk in unified_diff.iter_hunks() {
+        let ops = unified_hunk.ops();
+        let (first_op, last_op) = match (ops.first(), ops.last()) {
+            (Some(first_op), Some(last_op)) => (first_op, last_op),
+            _ => continue,
+        };
+        let old_lines = last_op.old_range().end - first_op.old_range().start;
+        let new_lines = last_op.new_range().end - first_op.new_range().start;
+
+        let mut lines: Vec<String> = Vec::new();
+        for change in unified_hunk.iter_changes() {
+            let sign = match change.tag() {
+                ChangeTag::Equal => " ",
+                ChangeTag::Delete => "-",
+                ChangeTag::Insert => "+",
+            };
+            lines.push(format!(
+                "{}{}",
+                sign,
+                change.value().trim_end_matches(&['\r', '\n'][..])
+            ));
         }
 
+        hunks.push(DiffHunk {
+            old_start: get_hunk_start(first_op.old_range().start, old_lines),
+            old_lines,
+            new_start: get_hunk_start(first_op.new_range().start, new_lines),
+            new_lines,
+            lines,
+        });
+    }
+
+    (unified_diff.to_string(), hunks)
+}
+
+fn get_hunk_start(start_index: usize, line_count: usize) -> usize {
+    // Unified diffs count lines from 1, except an empty range names the line before it
+    if line_count == 0 {
+        start_index
+    } else {
+        start_index + 1
+    }
+}
+
+pub fn get_file_changes(last_scan: &ScanIndex, this_scan: &FileScanResult) -> Vec<FieldChange> {
+    if let Some(scan_entry) = last_scan.get(&this_scan.path) {
         // We should have a matching path now
-        // Missing files/dirs don't have hashes, so check existence first
-        if !&scan_entry.exists.eq(&this_scan.exists) {
-            return true;
-        }
+        return compare_file_results(scan_entry, this_scan);
+    }
+    // Without any prior results this is the first scan, so there is nothing to compare against
+    if last_scan.is_empty() {
+        return Vec::new();
+    }
+    // A path that is missing now and wasn't known before (a dangling symlink, or a file that
+    // went away between glob and scan) hasn't changed at all
+    if !this_scan.exists {
+        return Vec::new();
+    }
+    // No prior entry exists for this path, so it is new since the last scan
+    debug!("Found no matching prior scan entry: Path is added");
+    vec![FieldChange::new("exists", false, this_scan.exists)]
+}
 
-        // Check that we have hashes on both results, then compare for changes
-        if &scan_entry.blake2s != ""
-            && this_scan.blake2s != ""
-            && !&scan_entry.blake2s.eq(&this_scan.blake2s)
-            || &scan_entry.sha256 != ""
-                && this_scan.sha256 != ""
-                && !&scan_entry.sha256.eq(&this_scan.sha256)
-            || &scan_entry.md5 != "" && this_scan.md5 != "" && !&scan_entry.md5.eq(&this_scan.md5)
-        {
-            // Results have a different hash. File is modified
-            debug!("File hashes differ: Path is_modified");
-            return true;
-        }
+pub fn compare_file_results(
+    scan_entry: &FileScanResult,
+    this_scan: &FileScanResult,
+) -> Vec<FieldChange> {
+    let mut changes: Vec<FieldChange> = Vec::new();
 
-        debug!("File hashes do not differ: Checking metadata");
-        // Hash data is not available for comparison. Check other parameters
-        if !&scan_entry.attributes.eq(&this_scan.attributes) {
-            return true;
-        }
-        // Compare sizes
-        if !&scan_entry.size.eq(&this_scan.size) {
-            return true;
-        }
-        // If RO settings were swapped, this is a permissions change
-        if !&scan_entry.is_readonly.eq(&this_scan.is_readonly) {
-            return true;
-        }
-        // If symlink status has changed, then technically we are looking at a symlink instead of
-        // a file/dir, even if the resulting object of the symlink is the same file.
-        // It's important to fail here before getting to ACLs since ultimately, ACLs in Windows
-        // are a slower comparison. Given a lot of file ACLs are based on inheritance, doing any
-        // symlink is likely to corrupt our permissions.
-        if !&scan_entry.is_symbolic_link.eq(&this_scan.is_symbolic_link) {
-            return true;
-        }
-        // T
-        if !&scan_entry.modified_time.eq(&this_scan.modified_time) {
-            return true;
+    // Missing files/dirs don't have hashes, so check existence first. Every other field of a
+    // missing path is a default value, so comparing them would only add noise to the record.
+    if !&scan_entry.exists.eq(&this_scan.exists) {
+        changes.push(FieldChange::new(
+            "exists",
+            scan_entry.exists,
+            this_scan.exists,
+        ));
+        return changes;
+    }
+
+    // Not being able to read a path is its own state rather than a change in every field. An
+    // unreadable result has no hashes, so they are only compared once both sides were read.
+    if scan_entry.is_unreadable() != this_scan.is_unreadable() {
+        changes.push(FieldChange::new(
+            "readable",
+            !scan_entry.is_unreadable(),
+            !this_scan.is_unreadable(),
+        ));
+    }
+    // Without metadata every other field is a default value, same as a missing path
+    if !has_metadata(&scan_entry.errors) || !has_metadata(&this_scan.errors) {
+        return changes;
+    }
+
+    // Check that we have hashes on both results, then compare for changes
+    for algorithm in HashAlgorithm::ALL {
+        let (old_hash, new_hash) = (
+            scan_entry.get_hash(algorithm),
+            this_scan.get_hash(algorithm),
+        );
+        if !old_hash.is_empty() && !new_hash.is_empty() && !old_hash.eq(new_hash) {
+            changes.push(FieldChange::new(algorithm.name(), old_hash, new_hash));
         }
-        // T
-        if !&scan_entry.creation_time.eq(&this_scan.creation_time) {
-            return true;
+    }
+    // A single digest covers everything below a directory. The entries say where to look when it
+    // doesn't match.
+    if let (Some(old_hash), Some(new_hash)) =
+        (&scan_entry.directory_hash, &this_scan.directory_hash)
+    {
+        if !old_hash.digest.eq(&new_hash.digest) {
+            changes.push(FieldChange::new(
+                "directory_hash",
+                &old_hash.digest,
+                &new_hash.digest,
+            ));
         }
+    }
 
-        // Validate DACLs match
-        #[cfg(windows)]
-        if check_acl_modified(&scan_entry.discretionary_acl, &this_scan.discretionary_acl) {
-            return true;
-        }
+    if !&scan_entry.attributes.eq(&this_scan.attributes) {
+        changes.push(FieldChange::new(
+            "attributes",
+            scan_entry.attributes,
+            this_scan.attributes,
+        ));
+    }
+    // Ownership is only collected on some platforms, so both sides need a value to compare
+    if !scan_entry.owner.is_empty()
+        && !this_scan.owner.is_empty()
+        && !&scan_entry.owner.eq(&this_scan.owner)
+    {
+        changes.push(FieldChange::new(
+            "owner",
+            &scan_entry.owner,
+            &this_scan.owner,
+        ));
+    }
+    // Compare sizes
+    if !&scan_entry.size.eq(&this_scan.size) {
+        changes.push(FieldChange::new("size", scan_entry.size, this_scan.size));
+    }
+    // If RO settings were swapped, this is a permissions change
+    if !&scan_entry.is_readonly.eq(&this_scan.is_readonly) {
+        changes.push(FieldChange::new(
+            "is_readonly",
+            scan_entry.is_readonly,
+            this_scan.is_readonly,
+        ));
+    }
+    // If symlink status has changed, then technically we are looking at a symlink instead of
+    // a file/dir, even if the resulting object of the symlink is the same file.
+    if !&scan_entry.is_symbolic_link.eq(&this_scan.is_symbolic_link) {
+        changes.push(FieldChange::new(
+            "is_symbolic_link",
+            scan_entry.is_symbolic_link,
+            this_scan.is_symbolic_link,
+        ));
+    }
+    if !&scan_entry.modified_time.eq(&this_scan.modified_time) {
+        changes.push(FieldChange::new(
+            "modified_time",
+            &scan_entry.modified_time,
+            &this_scan.modified_time,
+        ));
+    }
+    if !&scan_entry.creation_time.eq(&this_scan.creation_time) {
+        changes.push(FieldChange::new(
+            "creation_time",
+            &scan_entry.creation_time,
+            &this_scan.creation_time,
+        ));
+    }
 
-        // Validate SACLs match
-        #[cfg(windows)]
-        if check_acl_modified(&scan_entry.system_acl, &this_scan.system_acl) {
-            return true;
-        }
+    // Validate DACLs match. ACLs in Windows are a slower comparison, so they are left for last.
+    #[cfg(windows)]
+    if check_acl_modified(&scan_entry.discretionary_acl, &this_scan.discretionary_acl) {
+        changes.push(FieldChange::new(
+            "discretionary_acl",
+            get_acl_summary(&scan_entry.discretionary_acl),
+            get_acl_summary(&this_scan.discretionary_acl),
+        ));
     }
-    // We've checked all metadata aspects and found no changes for our matching path
-    debug!("File metadata matches");
-    return false;
+
+    // Validate SACLs match
+    #[cfg(windows)]
+    if check_acl_modified(&scan_entry.system_acl, &this_scan.system_acl) {
+        changes.push(FieldChange::new(
+            "system_acl",
+            get_acl_summary(&scan_entry.system_acl),
+            get_acl_summary(&this_scan.system_acl),
+        ));
+    }
+
+    if changes.is_empty() {
+        debug!("File metadata matches");
+    } else {
+        debug!("File changes detected: Path is_modified");
+    }
+    changes
+}
+
+#[cfg(windows)]
+fn get_acl_summary(acl: &WinAcl) -> String {
+    // The change record only holds strings, so flatten the ACL into its json representation
+    serde_json::to_string(acl).unwrap_or_default()
 }
 
 #[cfg(windows)]
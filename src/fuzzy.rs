@@ -0,0 +1,331 @@
+// Context triggered piecewise hashing, compatible with ssdeep. A rolling hash over the last few
+// bytes picks the points where the input is cut into pieces, so an edit only changes the
+// digest characters for the pieces it touches. That is what makes two digests comparable.
+const ROLLING_WINDOW: usize = 7;
+const MIN_BLOCKSIZE: u64 = 3;
+const HASH_PRIME: u32 = 0x01000193;
+const HASH_INIT: u32 = 0x28021967;
+const NUM_BLOCKHASHES: usize = 31;
+const SPAMSUM_LENGTH: usize = 64;
+const BASE64_CHARS: &[u8; 64] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+
+fn get_block_size(index: usize) -> u64 {
+    MIN_BLOCKSIZE << index
+}
+
+fn sum_hash(byte: u8, hash: u32) -> u32 {
+    hash.wrapping_mul(HASH_PRIME) ^ byte as u32
+}
+
+fn get_base64_char(hash: u32) -> u8 {
+    BASE64_CHARS[(hash % 64) as usize]
+}
+
+#[derive(Default)]
+struct RollingHash {
+    window: [u8; ROLLING_WINDOW],
+    h1: u32,
+    h2: u32,
+    h3: u32,
+    n: usize,
+}
+
+impl RollingHash {
+    fn update(&mut self, byte: u8) {
+        self.h2 = self.h2.wrapping_sub(self.h1);
+        self.h2 = self
+            .h2
+            .wrapping_add((ROLLING_WINDOW as u32).wrapping_mul(byte as u32));
+        self.h1 = self.h1.wrapping_add(byte as u32);
+        self.h1 = self.h1.wrapping_sub(self.window[self.n] as u32);
+        self.window[self.n] = byte;
+        self.n = (self.n + 1) % ROLLING_WINDOW;
+        self.h3 = (self.h3 << 5) ^ byte as u32;
+    }
+
+    fn sum(&self) -> u32 {
+        self.h1.wrapping_add(self.h2).wrapping_add(self.h3)
+    }
+}
+
+#[derive(Clone)]
+struct BlockHash {
+    h: u32,
+    half_h: u32,
+    digest: Vec<u8>,
+    // Once the digest is full, the last character keeps being replaced instead of appended
+    tail: Option<u8>,
+    half_tail: Option<u8>,
+}
+
+impl BlockHash {
+    fn new() -> BlockHash {
+        BlockHash {
+            h: HASH_INIT,
+            half_h: HASH_INIT,
+            digest: Vec::with_capacity(SPAMSUM_LENGTH),
+            tail: None,
+            half_tail: None,
+        }
+    }
+}
+
+// Every block size that could end up in the digest is tracked at once, so the input only has
+// to be read a single time. Block sizes that can no longer be chosen are dropped as the input
+// grows.
+pub struct SsdeepHasher {
+    roll: RollingHash,
+    block_hashes: Vec<BlockHash>,
+    start: usize,
+    total_size: u64,
+}
+
+impl Default for SsdeepHasher {
+    fn default() -> Self {
+        SsdeepHasher {
+            roll: RollingHash::default(),
+            block_hashes: vec![BlockHash::new()],
+            start: 0,
+            total_size: 0,
+        }
+    }
+}
+
+impl SsdeepHasher {
+    pub fn update(&mut self, data: &[u8]) {
+        self.total_size += data.len() as u64;
+        for byte in data {
+            self.update_byte(*byte);
+        }
+    }
+
+    fn update_byte(&mut self, byte: u8) {
+        self.roll.update(byte);
+        let roll_sum = self.roll.sum() as u64;
+        for block_hash in self.block_hashes[self.start..].iter_mut() {
+            block_hash.h = sum_hash(byte, block_hash.h);
+            block_hash.half_h = sum_hash(byte, block_hash.half_h);
+        }
+
+        let mut index = self.start;
+        while index < self.block_hashes.len() {
+            let block_size = get_block_size(index);
+            // Block sizes double, so once one misses the trigger point all larger ones do too
+            if roll_sum % block_size != block_size - 1 {
+                break;
+            }
+            if self.block_hashes[index].digest.is_empty() {
+                self.try_fork_block_hash();
+            }
+            let block_hash = &mut self.block_hashes[index];
+            let digest_char = get_base64_char(block_hash.h);
+            block_hash.half_tail = Some(get_base64_char(block_hash.half_h));
+            if block_hash.digest.len() < SPAMSUM_LENGTH - 1 {
+                block_hash.digest.push(digest_char);
+                block_hash.tail = None;
+                block_hash.h = HASH_INIT;
+                if block_hash.digest.len() < SPAMSUM_LENGTH / 2 {
+                    block_hash.half_h = HASH_INIT;
+                    block_hash.half_tail = None;
+                }
+            } else {
+                block_hash.tail = Some(digest_char);
+                self.try_reduce_block_hashes();
+            }
+            index += 1;
+        }
+    }
+
+    fn try_fork_block_hash(&mut self) {
+        if self.block_hashes.len() >= NUM_BLOCKHASHES {
+            return;
+        }
+        let last_block_hash = self.block_hashes.last().unwrap();
+        let mut block_hash = BlockHash::new();
+        block_hash.h = last_block_hash.h;
+        block_hash.half_h = last_block_hash.half_h;
+        self.block_hashes.push(block_hash);
+    }
+
+    fn try_reduce_block_hashes(&mut self) {
+        if self.block_hashes.len() - self.start < 2 {
+            return;
+        }
+        // The smallest block size is only useless once the input is too large for it and the
+        // next block size has produced enough of a digest to be picked instead
+        if get_block_size(self.start) * (SPAMSUM_LENGTH as u64) >= self.total_size {
+            return;
+        }
+        if self.block_hashes[self.start + 1].digest.len() < SPAMSUM_LENGTH / 2 {
+            return;
+        }
+        self.start += 1;
+    }
+
+    pub fn finalize(self) -> String {
+        let roll_sum = self.roll.sum();
+        let mut index = self.start;
+        while get_block_size(index) * (SPAMSUM_LENGTH as u64) < self.total_size
+            && index < NUM_BLOCKHASHES - 1
+        {
+            index += 1;
+        }
+        while index >= self.block_hashes.len() {
+            index -= 1;
+        }
+        while index > self.start && self.block_hashes[index].digest.len() < SPAMSUM_LENGTH / 2 {
+            index -= 1;
+        }
+
+        let block_hash = &self.block_hashes[index];
+        let mut first_part = block_hash.digest.clone();
+        if roll_sum != 0 {
+            first_part.push(get_base64_char(block_hash.h));
+        } else if let Some(tail) = block_hash.tail {
+            first_part.push(tail);
+        }
+
+        let mut second_part: Vec<u8>;
+        if index < self.block_hashes.len() - 1 {
+            let next_block_hash = &self.block_hashes[index + 1];
+            let half_length = next_block_hash.digest.len().min(SPAMSUM_LENGTH / 2 - 1);
+            second_part = next_block_hash.digest[..half_length].to_vec();
+            if roll_sum != 0 {
+                second_part.push(get_base64_char(next_block_hash.half_h));
+            } else if let Some(half_tail) = next_block_hash.half_tail {
+                second_part.push(half_tail);
+            }
+        } else {
+            second_part = Vec::new();
+            if roll_sum != 0 {
+                second_part.push(get_base64_char(block_hash.h));
+            }
+        }
+
+        format!(
+            "{}:{}:{}",
+            get_block_size(index),
+            String::from_utf8_lossy(&first_part),
+            String::from_utf8_lossy(&second_part)
+        )
+    }
+}
+
+#[allow(unused)]
+pub fn get_ssdeep(data: &[u8]) -> String {
+    let mut hasher = SsdeepHasher::default();
+    hasher.update(data);
+    hasher.finalize()
+}
+
+// Returns a score from 0 (nothing in common) to 100 (the same or nearly the same), or None if
+// either digest can't be read
+pub fn compare_ssdeep(old_digest: &str, new_digest: &str) -> Option<u32> {
+    let (old_block_size, old_first, old_second) = parse_ssdeep(old_digest)?;
+    let (new_block_size, new_first, new_second) = parse_ssdeep(new_digest)?;
+
+    // Only digests from the same or neighbouring block sizes describe comparable pieces
+    if old_block_size != new_block_size
+        && old_block_size != new_block_size * 2
+        && new_block_size != old_block_size * 2
+    {
+        return Some(0);
+    }
+
+    let old_first = eliminate_sequences(old_first);
+    let old_second = eliminate_sequences(old_second);
+    let new_first = eliminate_sequences(new_first);
+    let new_second = eliminate_sequences(new_second);
+
+    if old_block_size == new_block_size && old_first == new_first {
+        return Some(100);
+    }
+
+    let score = if old_block_size == new_block_size {
+        get_score(&old_first, &new_first, old_block_size).max(get_score(
+            &old_second,
+            &new_second,
+            old_block_size * 2,
+        ))
+    } else if old_block_size == new_block_size * 2 {
+        get_score(&old_first, &new_second, old_block_size)
+    } else {
+        get_score(&old_second, &new_first, new_block_size)
+    };
+    Some(score)
+}
+
+fn parse_ssdeep(digest: &str) -> Option<(u64, &[u8], &[u8])> {
+    let mut parts = digest.splitn(3, ':');
+    let block_size = parts.next()?.parse::<u64>().ok()?;
+    let first_part = parts.next()?;
+    // Digests from files can have a quoted file name after the second part
+    let second_part = parts.next()?.split(',').next()?;
+    Some((block_size, first_part.as_bytes(), second_part.as_bytes()))
+}
+
+fn eliminate_sequences(digest: &[u8]) -> Vec<u8> {
+    // Runs of the same character carry little information and inflate the score, so anything
+    // past three in a row is dropped
+    let mut eliminated: Vec<u8> = Vec::with_capacity(digest.len());
+    for (index, digest_char) in digest.iter().enumerate() {
+        if index >= 3
+            && *digest_char == digest[index - 1]
+            && *digest_char == digest[index - 2]
+            && *digest_char == digest[index - 3]
+        {
+            continue;
+        }
+        eliminated.push(*digest_char);
+    }
+    eliminated
+}
+
+fn has_common_substring(first: &[u8], second: &[u8]) -> bool {
+    if first.len() < ROLLING_WINDOW || second.len() < ROLLING_WINDOW {
+        return false;
+    }
+    first
+        .windows(ROLLING_WINDOW)
+        .any(|window| second.windows(ROLLING_WINDOW).any(|other| other == window))
+}
+
+fn get_edit_distance(first: &[u8], second: &[u8]) -> usize {
+    // Insertions and deletions cost 1, a substitution costs 2
+    let mut previous_row: Vec<usize> = (0..=second.len()).collect();
+    let mut current_row: Vec<usize> = vec![0; second.len() + 1];
+    for (first_index, first_char) in first.iter().enumerate() {
+        current_row[0] = first_index + 1;
+        for (second_index, second_char) in second.iter().enumerate() {
+            let substitution_cost = if first_char == second_char { 0 } else { 2 };
+            current_row[second_index + 1] = (previous_row[second_index + 1] + 1)
+                .min(current_row[second_index] + 1)
+                .min(previous_row[second_index] + substitution_cost);
+        }
+        std::mem::swap(&mut previous_row, &mut current_row);
+    }
+    previous_row[second.len()]
+}
+
+fn get_score(first: &[u8], second: &[u8], block_size: u64) -> u32 {
+    if first.len() > SPAMSUM_LENGTH || second.len() > SPAMSUM_LENGTH {
+        return 0;
+    }
+    // Without a shared run as long as the rolling window any match is coincidence
+    if !has_common_substring(first, second) {
+        return 0;
+    }
+
+    let distance = get_edit_distance(first, second) as u64;
+    let scaled_distance = distance * SPAMSUM_LENGTH as u64 / (first.len() + second.len()) as u64;
+    let mut score = 100 - (100 * scaled_distance / SPAMSUM_LENGTH as u64).min(100);
+
+    // Small block sizes mean short pieces, so a match there shouldn't be exaggerated
+    let small_block_size_limit =
+        (99 + ROLLING_WINDOW as u64) / ROLLING_WINDOW as u64 * MIN_BLOCKSIZE;
+    if block_size < small_block_size_limit {
+        let score_limit = block_size / MIN_BLOCKSIZE * first.len().min(second.len()) as u64;
+        score = score.min(score_limit);
+    }
+    score as u32
+}
@@ -0,0 +1,93 @@
+use crate::file::FileScanResult;
+use crate::hashing::HashValues;
+use crate::scan_settings::{FileHashes, ScanSettings};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+
+#[allow(unused)]
+#[derive(Debug, Default, Clone, PartialEq, Serialize, Deserialize)]
+pub struct FullRehashState {
+    pub(crate) runs_since_full_rehash: u32,
+    // Empty until the first full rehash, which makes the first fast run a full one
+    pub(crate) last_full_rehash: String,
+}
+
+#[allow(unused)]
+#[derive(Debug, Clone, PartialEq)]
+pub struct FileStat {
+    pub(crate) inode: u64,
+    pub(crate) size: u64,
+    pub(crate) modified_time: String,
+    pub(crate) change_time: String,
+}
+
+pub fn is_full_rehash_due(
+    scan_settings: &ScanSettings,
+    last_state: &FullRehashState,
+    now: DateTime<Utc>,
+) -> bool {
+    // Nothing is trusted from the cache unless some setting asks for it, so every run is a full one
+    if !scan_settings
+        .file_scan_settings
+        .iter()
+        .any(|file_scan_setting| file_scan_setting.file_fast_mode)
+    {
+        return true;
+    }
+    let last_full_rehash = match last_state.last_full_rehash.parse::<DateTime<Utc>>() {
+        Ok(last_full_rehash) => last_full_rehash,
+        Err(_) => return true,
+    };
+    // A zero for either schedule turns that one off
+    if scan_settings.full_rehash_runs != 0
+        && last_state.runs_since_full_rehash + 1 >= scan_settings.full_rehash_runs
+    {
+        return true;
+    }
+    scan_settings.full_rehash_days != 0
+        && now - last_full_rehash >= chrono::Duration::days(scan_settings.full_rehash_days.into())
+}
+
+pub fn get_next_full_rehash_state(
+    last_state: &FullRehashState,
+    full_rehash: bool,
+    now: DateTime<Utc>,
+) -> FullRehashState {
+    if full_rehash {
+        return FullRehashState {
+            runs_since_full_rehash: 0,
+            last_full_rehash: now.to_string(),
+        };
+    }
+    FullRehashState {
+        runs_since_full_rehash: last_state.runs_since_full_rehash + 1,
+        last_full_rehash: last_state.last_full_rehash.clone(),
+    }
+}
+
+pub fn get_cached_hashes(
+    file_hashes: &FileHashes,
+    prior_result: &FileScanResult,
+    stat: &FileStat,
+) -> Option<HashValues> {
+    // The hashes are only as good as the stat tuple they were taken with. Anything that touches
+    // the file without putting all four back the way they were means reading it again. Results
+    // from before the change time was recorded never match.
+    if !prior_result.exists
+        || prior_result.is_unreadable()
+        || prior_result.change_time.is_empty()
+        || prior_result.inode != stat.inode
+        || prior_result.size != stat.size
+        || prior_result.modified_time != stat.modified_time
+        || prior_result.change_time != stat.change_time
+    {
+        return None;
+    }
+
+    // A newly enabled algorithm has nothing cached yet
+    let mut hashes = HashValues::new();
+    for algorithm in file_hashes.get_algorithms() {
+        hashes.insert(algorithm, prior_result.hashes.get(&algorithm)?.clone());
+    }
+    Some(hashes)
+}
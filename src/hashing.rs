@@ -1,138 +1,301 @@
+use crate::binary::{BlockHashList, BlockHasher};
+use crate::fuzzy::SsdeepHasher;
+use crate::mmap::{read_mapped, ReadMode};
 use crate::scan_settings::FileHashes;
-use blake2s_simd;
 use blake2s_simd::Params;
-use log::{info, warn};
-use sha256::try_digest;
+use log::{debug, info, warn};
+use serde::{Deserialize, Serialize};
+use sha1::Sha1;
+use sha2::{Digest, Sha256, Sha512};
+use sha3::Sha3_256;
+use std::collections::BTreeMap;
 use std::fmt;
 use std::fs::File;
 use std::io::{BufRead, BufReader};
 use std::path::Path;
+use xxhash_rust::xxh3::Xxh3;
 
-pub struct HashValues {
-    pub md5: String,
-    pub sha256: String,
-    pub blake2s: String,
+#[allow(unused)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum HashAlgorithm {
+    Md5,
+    Sha1,
+    Sha256,
+    Sha512,
+    Sha3_256,
+    Blake2s,
+    Blake3,
+    Xxh3,
+    Ssdeep,
 }
-impl fmt::Display for HashValues {
+
+impl HashAlgorithm {
+    pub const ALL: [HashAlgorithm; 9] = [
+        HashAlgorithm::Md5,
+        HashAlgorithm::Sha1,
+        HashAlgorithm::Sha256,
+        HashAlgorithm::Sha512,
+        HashAlgorithm::Sha3_256,
+        HashAlgorithm::Blake2s,
+        HashAlgorithm::Blake3,
+        HashAlgorithm::Xxh3,
+        HashAlgorithm::Ssdeep,
+    ];
+
+    // Only these are trusted to say two files hold the same content. md5 and sha1 have practical
+    // collisions and xxh3 was never meant to resist them. sha256 stays first since that is what
+    // most users enable.
+    pub const CONTENT_ALGORITHMS: [HashAlgorithm; 5] = [
+        HashAlgorithm::Sha256,
+        HashAlgorithm::Sha512,
+        HashAlgorithm::Sha3_256,
+        HashAlgorithm::Blake3,
+        HashAlgorithm::Blake2s,
+    ];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            HashAlgorithm::Md5 => "md5",
+            HashAlgorithm::Sha1 => "sha1",
+            HashAlgorithm::Sha256 => "sha256",
+            HashAlgorithm::Sha512 => "sha512",
+            HashAlgorithm::Sha3_256 => "sha3_256",
+            HashAlgorithm::Blake2s => "blake2s",
+            HashAlgorithm::Blake3 => "blake3",
+            HashAlgorithm::Xxh3 => "xxh3",
+            HashAlgorithm::Ssdeep => "ssdeep",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<HashAlgorithm> {
+        HashAlgorithm::ALL
+            .into_iter()
+            .find(|algorithm| algorithm.name() == name)
+    }
+
+    // Hash lists from other tools rarely say which algorithm they used. The common ones all have
+    // a different length, and 64 characters is taken to be sha256 since that is what sha256sum
+    // and most feeds publish.
+    pub fn from_hex_length(length: usize) -> Option<HashAlgorithm> {
+        match length {
+            32 => Some(HashAlgorithm::Md5),
+            40 => Some(HashAlgorithm::Sha1),
+            64 => Some(HashAlgorithm::Sha256),
+            128 => Some(HashAlgorithm::Sha512),
+            _ => None,
+        }
+    }
+}
+
+impl fmt::Display for HashAlgorithm {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
-        write!(
-            f,
-            "MD5:{}, SHA256:{}, BLAKE2:{}",
-            self.md5, self.sha256, self.blake2s
-        )
+        write!(f, "{}", self.name())
     }
 }
 
-pub fn get_all_hashes(hash_values: &FileHashes, read_buffer_size: u64, path: &Path) -> HashValues {
-    let mut hashes: HashValues = HashValues {
-        md5: "".to_string(),
-        sha256: "".to_string(),
-        blake2s: "".to_string(),
-    };
+// Keyed by algorithm so a new algorithm is just a new key in the results file. A BTreeMap keeps
+// the keys in the same order every time the results are saved.
+pub type HashValues = BTreeMap<HashAlgorithm, String>;
+
+// A file that can't be read returns the error instead of any hashes, so the caller can record why
+pub fn get_all_hashes_with_blocks(
+    hash_values: &FileHashes,
+    read_buffer_size: u64,
+    path: &Path,
+    block_size: u64,
+    read_mode: ReadMode,
+) -> std::io::Result<(HashValues, Option<BlockHashList>)> {
+    let mut hashes: HashValues = HashValues::new();
+    let mut block_hashes: Option<BlockHashList> = None;
 
     if path.is_file() {
-        if hash_values.md5 {
-            hashes.md5 = get_md5(path, read_buffer_size);
-        }
-        if hash_values.sha256 {
-            hashes.sha256 = get_sha256(path, read_buffer_size);
-        }
-        if hash_values.blake2s {
-            hashes.blake2s = get_blake2s(path, read_buffer_size);
-        }
+        (hashes, block_hashes) =
+            get_file_and_block_hashes(hash_values, read_buffer_size, path, block_size, read_mode)?;
         info!("Hashing complete");
     } else {
-        // While I have no intention of supporting directory hashing at this time, I'm curious how
-        // many users would have configurations that would attempt it. Putting this log here just
-        // to potentially collect some insight in case I'm later asked to add dir hashing and I
-        // agree to build it.
-        info!("Hashing attempted on a directory: Unsupported functionality at this time");
+        // Directories have no content of their own. Their digest is built from their children
+        // when the directory_hash setting is on.
+        debug!("Skipping content hashes for a directory: See directory_hash");
     }
 
-    hashes
+    Ok((hashes, block_hashes))
 }
 
-pub fn get_md5(path: &Path, read_buffer_size: u64) -> String {
-    // MD5 does have collissions, but it's still widely employed with commercial software
-    // that users may need to compare with.
-    if File::open(path).is_err() {
-        warn!(
-            "Cannot open file for md5 hashing: {:?}",
-            path.to_str().unwrap()
-        );
-        return String::from("Cannot open file");
+// blake3, xxh3 and ssdeep carry large internal buffers, so they are boxed to keep the other variants small
+enum AlgorithmHasher {
+    Md5(md5::Context),
+    Sha1(Sha1),
+    Sha256(Sha256),
+    Sha512(Sha512),
+    Sha3_256(Sha3_256),
+    Blake2s(blake2s_simd::State),
+    Blake3(Box<blake3::Hasher>),
+    Xxh3(Box<Xxh3>),
+    Ssdeep(Box<SsdeepHasher>),
+}
+
+impl AlgorithmHasher {
+    fn new(algorithm: HashAlgorithm) -> AlgorithmHasher {
+        match algorithm {
+            HashAlgorithm::Md5 => AlgorithmHasher::Md5(md5::Context::new()),
+            HashAlgorithm::Sha1 => AlgorithmHasher::Sha1(Sha1::new()),
+            HashAlgorithm::Sha256 => AlgorithmHasher::Sha256(Sha256::new()),
+            HashAlgorithm::Sha512 => AlgorithmHasher::Sha512(Sha512::new()),
+            HashAlgorithm::Sha3_256 => AlgorithmHasher::Sha3_256(Sha3_256::new()),
+            HashAlgorithm::Blake2s => AlgorithmHasher::Blake2s(Params::new().to_state()),
+            HashAlgorithm::Blake3 => AlgorithmHasher::Blake3(Box::default()),
+            HashAlgorithm::Xxh3 => AlgorithmHasher::Xxh3(Box::default()),
+            HashAlgorithm::Ssdeep => AlgorithmHasher::Ssdeep(Box::default()),
+        }
     }
 
-    let file = File::open(path).expect("Cannot open file");
-    let file_length = file.metadata().unwrap().len();
+    fn update(&mut self, data: &[u8]) {
+        match self {
+            AlgorithmHasher::Md5(hasher) => hasher.consume(data),
+            AlgorithmHasher::Sha1(hasher) => hasher.update(data),
+            AlgorithmHasher::Sha256(hasher) => hasher.update(data),
+            AlgorithmHasher::Sha512(hasher) => hasher.update(data),
+            AlgorithmHasher::Sha3_256(hasher) => hasher.update(data),
+            AlgorithmHasher::Blake2s(hasher) => {
+                hasher.update(data);
+            }
+            AlgorithmHasher::Blake3(hasher) => {
+                hasher.update(data);
+            }
+            AlgorithmHasher::Xxh3(hasher) => hasher.update(data),
+            AlgorithmHasher::Ssdeep(hasher) => hasher.update(data),
+        }
+    }
 
-    // This buffer size will directly correlate to RAM usage. OSFIG is currently single threaded,
-    // but need to keep this in mind for the distant future.
-    let read_buffer_size = file_length.min(read_buffer_size) as usize;
-    let mut read_buffer = BufReader::with_capacity(read_buffer_size, file);
-    let mut md5_context = md5::Context::new();
-    loop {
-        let file_chunk = read_buffer.fill_buf().unwrap();
-        // If the part is empty then we have reached EOF
-        if file_chunk.is_empty() {
-            break;
+    fn finalize(self) -> String {
+        match self {
+            AlgorithmHasher::Md5(hasher) => format!("{:x}", hasher.compute()).to_ascii_uppercase(),
+            AlgorithmHasher::Sha1(hasher) => hex::encode_upper(hasher.finalize()),
+            AlgorithmHasher::Sha256(hasher) => hex::encode_upper(hasher.finalize()),
+            AlgorithmHasher::Sha512(hasher) => hex::encode_upper(hasher.finalize()),
+            AlgorithmHasher::Sha3_256(hasher) => hex::encode_upper(hasher.finalize()),
+            AlgorithmHasher::Blake2s(hasher) => hasher.finalize().to_hex().to_ascii_uppercase(),
+            AlgorithmHasher::Blake3(hasher) => hasher.finalize().to_hex().to_ascii_uppercase(),
+            AlgorithmHasher::Xxh3(hasher) => format!("{:016X}", hasher.digest()),
+            // ssdeep digests are base64 and case sensitive, so they are left as they are
+            AlgorithmHasher::Ssdeep(hasher) => hasher.finalize(),
         }
-        // Hasher needs to consume data before the buffer does
-        md5_context.consume(file_chunk);
-        let part_len = file_chunk.len();
-        // Buffer consuming the data moves us forward in the file
-        read_buffer.consume(part_len);
     }
-    let md5_hash = format!("{:x}", md5_context.compute()).to_ascii_uppercase();
-    md5_hash
 }
 
-pub fn get_sha256(path: &Path, _read_buffer_size: u64) -> String {
-    // Todo if possible, implement a read buffer with size read_buffer_size
-    if File::open(path).is_err() {
-        warn!(
-            "Cannot open file for sha256 hashing: {:?}",
-            path.to_str().unwrap()
-        );
-        return String::from("Cannot open file");
+// Each enabled algorithm gets its own hasher and they are all fed from the same read buffer, so
+// the file is only read once no matter how many hashes are collected.
+struct FileHasher {
+    hashers: Vec<(HashAlgorithm, AlgorithmHasher)>,
+}
+
+impl FileHasher {
+    fn new(hash_values: &FileHashes) -> FileHasher {
+        FileHasher {
+            hashers: hash_values
+                .get_algorithms()
+                .into_iter()
+                .map(|algorithm| (algorithm, AlgorithmHasher::new(algorithm)))
+                .collect(),
+        }
+    }
+
+    fn is_empty(&self) -> bool {
+        self.hashers.is_empty()
     }
 
-    let sha256_hash = try_digest(Path::new(path)).unwrap().to_ascii_uppercase();
-    sha256_hash
+    fn update(&mut self, data: &[u8]) {
+        for (_, hasher) in self.hashers.iter_mut() {
+            hasher.update(data);
+        }
+    }
+
+    fn finalize(self) -> HashValues {
+        self.hashers
+            .into_iter()
+            .map(|(algorithm, hasher)| (algorithm, hasher.finalize()))
+            .collect()
+    }
+}
+
+pub fn get_file_hashes(
+    hash_values: &FileHashes,
+    read_buffer_size: u64,
+    path: &Path,
+) -> std::io::Result<HashValues> {
+    Ok(get_file_and_block_hashes(hash_values, read_buffer_size, path, 0, ReadMode::Buffered)?.0)
 }
 
-pub fn get_blake2s(path: &Path, read_buffer_size: u64) -> String {
-    if File::open(path).is_err() {
-        warn!(
-            "Cannot open file for blake2s hashing: {:?}",
-            path.to_str().unwrap()
-        );
-        return String::from("Cannot open file");
+// Block hashes come from the same read as the file hashes. A block size of 0 skips them.
+pub fn get_file_and_block_hashes(
+    hash_values: &FileHashes,
+    read_buffer_size: u64,
+    path: &Path,
+    block_size: u64,
+    read_mode: ReadMode,
+) -> std::io::Result<(HashValues, Option<BlockHashList>)> {
+    let mut hasher = FileHasher::new(hash_values);
+    let mut block_hasher = (block_size > 0).then(|| BlockHasher::new(block_size));
+    if hasher.is_empty() && block_hasher.is_none() {
+        return Ok((hasher.finalize(), None));
     }
 
-    let file = File::open(path).expect("Cannot open file");
-    let file_length = file.metadata().unwrap().len();
+    let file = File::open(path)?;
+    let file_length = file.metadata()?.len();
 
-    // This buffer size will directly correlate to RAM usage. OSFIG is currently single threaded,
-    // but need to keep this in mind for the distant future.
+    // An empty file can't be mapped, and there's nothing to save by mapping it anyway
+    if read_mode == ReadMode::Mmap && file_length > 0 {
+        let mapped_result = read_mapped(&file, file_length, |data| {
+            hasher.update(data);
+            if let Some(block_hasher) = block_hasher.as_mut() {
+                block_hasher.update(data);
+            }
+        });
+        match mapped_result {
+            Ok(true) => {
+                return Ok((
+                    hasher.finalize(),
+                    block_hasher.map(|block_hasher| block_hasher.finalize()),
+                ))
+            }
+            Ok(false) => warn!(
+                "File was truncated while mapped: Falling back to buffered reads: {:?}",
+                path.to_str()
+            ),
+            Err(e) => warn!(
+                "Cannot map file: Falling back to buffered reads: {:?}: {}",
+                path.to_str(),
+                e
+            ),
+        }
+        // Start over, since the hashers already saw part of the mapping
+        hasher = FileHasher::new(hash_values);
+        block_hasher = (block_size > 0).then(|| BlockHasher::new(block_size));
+    }
+
+    // This buffer size will directly correlate to RAM usage. Every scan worker holds one, so the
+    // total is file_read_buffer_size x file_scan_workers.
     let read_buffer_size = file_length.min(read_buffer_size) as usize;
     let mut read_buffer = BufReader::with_capacity(read_buffer_size, file);
-    let mut hasher = Params::new().to_state();
-
     loop {
-        let part = read_buffer.fill_buf().unwrap();
+        let part = read_buffer.fill_buf()?;
         // If the part is empty then we have reached EOF
         if part.is_empty() {
             break;
         }
-        // Hasher needs to consume data before the buffer does
+        // Hashers need to consume data before the buffer does
         hasher.update(part);
+        if let Some(block_hasher) = block_hasher.as_mut() {
+            block_hasher.update(part);
+        }
         let part_len = part.len();
         // Buffer consuming the data moves us forward in the file
         read_buffer.consume(part_len);
     }
-    let result = hasher.finalize().to_hex();
-    let blake2_hash = result.to_ascii_uppercase();
 
-    blake2_hash
+    Ok((
+        hasher.finalize(),
+        block_hasher.map(|block_hasher| block_hasher.finalize()),
+    ))
 }
@@ -1,9 +1,10 @@
 use chrono::{DateTime, Utc};
 use log::{error, info, warn};
 use serde::{Deserialize, Serialize};
+use serde_json::{from_str, json, Value};
 use std::fs;
 use std::fs::File;
-use std::io::BufWriter;
+use std::io::{BufWriter, Read};
 use std::path::Path;
 use std::time::SystemTime;
 #[cfg(windows)]
@@ -22,11 +23,18 @@ pub fn get_cur_sid() -> Vec<BYTE> {
     cur_sid.unwrap()
 }
 
+use crate::changes::ChangeSummary;
 use crate::file::FileScanResult;
+use crate::hash_cache::FullRehashState;
+use crate::hashing::HashAlgorithm;
+use crate::ioc::{apply_ioc_matches, IocDatabase};
+use crate::known_good::{apply_known_good, KnownGoodDatabase};
 use crate::osfig_state::OsfigSettings;
+use crate::package::{verify_packages, PackageDatabase};
 use crate::registry::RegistryResult;
-#[cfg(target_os = "linux")]
-use users;
+use crate::scan_settings::{SeverityRule, Suppression};
+use crate::severity::{classify_changes, classify_registry_changes, Severity};
+use crate::suppression::{apply_registry_suppressions, apply_suppressions};
 
 #[allow(unused)]
 pub fn get_cur_username() -> String {
@@ -54,22 +62,69 @@ pub fn sid_to_username(sid: &String) -> (String, String) {
     (system_name.to_string(), user_name.to_string())
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, Default)]
 pub struct ScanResults {
+    #[serde(default)]
+    pub summary: ChangeSummary,
     pub files: Vec<FileScanResult>,
     pub registry: Vec<RegistryResult>,
+    #[serde(default)]
+    pub expired_suppressions: Vec<Suppression>,
+    #[serde(default)]
+    pub full_rehash: FullRehashState,
 }
 
-impl Default for ScanResults {
-    fn default() -> Self {
-        Self {
-            files: vec![],
-            registry: vec![],
+#[allow(unused)]
+impl ScanResults {
+    pub fn summarize_changes(&mut self) {
+        let mut summary = ChangeSummary::default();
+        for file in &self.files {
+            summary.add(file.change_type);
+            if file.suppressed {
+                summary.suppressed += 1;
+            }
+            summary.add_severity(file.severity);
+            summary.add_hash_source(file);
+            summary.add_ioc_matches(file);
+            summary.add_package_status(file);
         }
+        for registry in &self.registry {
+            summary.add(registry.change_type);
+            if registry.suppressed {
+                summary.suppressed += 1;
+            }
+            summary.add_severity(registry.severity);
+        }
+        self.summary = summary;
+    }
+
+    pub fn classify_changes(&mut self, severity_rules: &[SeverityRule]) {
+        classify_changes(&mut self.files, severity_rules);
+        classify_registry_changes(&mut self.registry, severity_rules);
+    }
+
+    pub fn apply_known_good(
+        &mut self,
+        database: &KnownGoodDatabase,
+        known_good_severity: Option<Severity>,
+    ) {
+        apply_known_good(&mut self.files, database, known_good_severity);
+    }
+
+    pub fn apply_suppressions(&mut self, suppressions: &[Suppression]) {
+        let now = DateTime::<Utc>::from(SystemTime::now());
+        self.expired_suppressions = apply_suppressions(&mut self.files, suppressions, now);
+        apply_registry_suppressions(&mut self.registry, suppressions, now);
+    }
+
+    pub fn apply_ioc_matches(&mut self, database: &IocDatabase) {
+        apply_ioc_matches(&mut self.files, database);
+    }
+
+    pub fn verify_packages(&mut self, database: &PackageDatabase) {
+        verify_packages(&mut self.files, database);
     }
-}
 
-impl ScanResults {
     pub fn add_file(&mut self, file: FileScanResult) {
         self.files.push(file)
     }
@@ -93,13 +148,35 @@ impl ScanResults {
     }
 }
 
+pub fn split_csv_line(line: &str) -> Vec<String> {
+    // Just enough CSV for hash lists. Fields can be quoted, and a doubled quote inside quotes is
+    // a literal quote, so file names with commas in them survive.
+    let mut fields: Vec<String> = Vec::new();
+    let mut field = String::new();
+    let mut in_quotes = false;
+    let mut chars = line.trim_end_matches(['\r', '\n']).chars().peekable();
+    while let Some(c) = chars.next() {
+        match c {
+            '"' if in_quotes && chars.peek() == Some(&'"') => {
+                field.push('"');
+                chars.next();
+            }
+            '"' => in_quotes = !in_quotes,
+            ',' if !in_quotes => fields.push(std::mem::take(&mut field)),
+            _ => field.push(c),
+        }
+    }
+    fields.push(field);
+    fields
+}
+
 pub fn save_results_to_file(results: ScanResults, osfig_settings: &OsfigSettings) {
     // I'm torn on this and may change it later. If the results are 0 it just saved "[]" into the
     // json. Currently we're skipping the save operation and putting a message in the log. For
     // integrity purposes, it may be valuable to store the empty json result instead. Will need to
     // reconsider this later.
 
-    if results.files.len() == 0 && results.registry.len() == 0 {
+    if results.files.is_empty() && results.registry.is_empty() {
         warn!("Found no results to save. Validate scan settings, access/permissions, and errors in the log");
         return;
     }
@@ -134,11 +211,96 @@ pub fn store_json(results: &ScanResults, path: &str) -> Result<(), std::io::Erro
         };
     }
 
-    let json_file = File::create(&path)?;
+    let json_file = File::create(path)?;
     let file_writer = BufWriter::new(json_file);
-    let save_result = match serde_json::to_writer_pretty(file_writer, &results) {
-        Ok(_) => {}
-        Err(_) => {}
+    serde_json::to_writer_pretty(file_writer, &results)?;
+    Ok(())
+}
+
+pub fn load_results_from_file(path: &str) -> Result<ScanResults, String> {
+    let mut results_file = match File::open(path) {
+        Ok(file) => file,
+        Err(e) => return Err(format!("Cannot open results file {}: {}", path, e)),
+    };
+
+    let mut data: String = "".to_string();
+    if let Err(e) = results_file.read_to_string(&mut data) {
+        return Err(format!("Unable to read results file {}: {}", path, e));
+    }
+
+    match parse_results(data.as_ref()) {
+        Ok(results) => Ok(results),
+        Err(e) => Err(format!("Cannot parse results file {}: {}", path, e)),
+    }
+}
+
+pub fn parse_results(data: &str) -> Result<ScanResults, serde_json::Error> {
+    let mut results: Value = from_str(data)?;
+    // Results are saved as a ScanResults object, but older results files only held the list of
+    // file results. Accept either so a prior scan isn't silently ignored.
+    if results.is_array() {
+        results = json!({ "files": results, "registry": [] });
+    }
+    if let Some(Value::Array(files)) = results.get_mut("files") {
+        for file in files.iter_mut() {
+            move_legacy_hashes(file);
+            clear_legacy_contents(file);
+        }
+    }
+    serde_json::from_value(results)
+}
+
+fn move_legacy_hashes(file: &mut Value) {
+    // Hashes used to be their own md5/sha256/blake2s fields. Move them into the hashes map so
+    // results from before the change still compare against new scans instead of every file
+    // looking modified.
+    let Value::Object(file) = file else {
+        return;
+    };
+    for algorithm in [
+        HashAlgorithm::Md5,
+        HashAlgorithm::Sha256,
+        HashAlgorithm::Blake2s,
+    ] {
+        if let Some(Value::String(hash)) = file.remove(algorithm.name()) {
+            if !is_legacy_digest(algorithm, &hash) {
+                continue;
+            }
+            if let Value::Object(hashes) = file.entry("hashes").or_insert_with(|| json!({})) {
+                hashes
+                    .entry(algorithm.name())
+                    .or_insert(Value::String(hash));
+            }
+        }
+    }
+}
+
+fn clear_legacy_contents(file: &mut Value) {
+    // Before scan errors were recorded, a file that couldn't be opened or wasn't UTF-8 kept one
+    // of these messages as its contents. New scans leave the contents empty and record an error
+    // instead, so the message would otherwise show up as a content change on the next scan.
+    let Value::Object(file) = file else {
+        return;
+    };
+    if file.contains_key("errors") {
+        return;
+    }
+    if let Some(Value::String(contents)) = file.get_mut("contents") {
+        if contents == "Cannot open file" || contents == "Not valid UTF8" {
+            contents.clear();
+        }
+    }
+}
+
+fn is_legacy_digest(algorithm: HashAlgorithm, hash: &str) -> bool {
+    // The old hasher wrote "Cannot open file" in place of a digest when it couldn't read the file.
+    // Anything that isn't hex of the right length is one of those and is dropped rather than
+    // compared against. blake2s digests are as long as sha256 ones, which is what
+    // from_hex_length reports for them.
+    let length_algorithm = match algorithm {
+        HashAlgorithm::Blake2s => HashAlgorithm::Sha256,
+        _ => algorithm,
     };
-    Ok(save_result)
+    HashAlgorithm::from_hex_length(hash.len()) == Some(length_algorithm)
+        && hash.chars().all(|c| c.is_ascii_hexdigit())
 }
@@ -0,0 +1,197 @@
+use crate::file::FileScanResult;
+use crate::hashing::HashAlgorithm;
+use crate::helpers::split_csv_line;
+use crate::scan_settings::ScanSettings;
+use crate::severity::Severity;
+use glob::glob;
+use log::{error, info, warn};
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeSet, HashMap};
+use std::fs::read_to_string;
+
+#[allow(unused)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct IocEntry {
+    pub(crate) algorithm: HashAlgorithm,
+    pub(crate) hash: String,
+    // Whatever the feed says about the hash, e.g. a malware family or report ID
+    pub(crate) name: String,
+    // The feed file the hash was loaded from
+    pub(crate) source: String,
+}
+
+#[allow(unused)]
+#[derive(Debug, Default, Clone)]
+pub struct IocDatabase {
+    index: HashMap<(HashAlgorithm, String), IocEntry>,
+}
+
+impl IocDatabase {
+    #[allow(unused)]
+    pub fn new(entries: Vec<IocEntry>) -> Self {
+        let mut database = IocDatabase::default();
+        database.add_entries(entries);
+        database
+    }
+
+    pub fn add_entries(&mut self, entries: Vec<IocEntry>) {
+        // The first feed to list a hash is the one that gets credited for it
+        for entry in entries {
+            self.index
+                .entry((entry.algorithm, entry.hash.clone()))
+                .or_insert(entry);
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.index.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.index.is_empty()
+    }
+
+    pub fn get_algorithms(&self) -> BTreeSet<HashAlgorithm> {
+        self.index.keys().map(|(algorithm, _)| *algorithm).collect()
+    }
+
+    pub fn get_matches(&self, result: &FileScanResult) -> Vec<IocEntry> {
+        result
+            .hashes
+            .iter()
+            .filter_map(|(algorithm, hash)| self.index.get(&(*algorithm, hash.to_uppercase())))
+            .cloned()
+            .collect()
+    }
+}
+
+pub fn get_ioc_hash(field: &str) -> Option<(HashAlgorithm, String)> {
+    // Feeds publish md5, sha1 and sha256. Anything else in a row is a name, a date or a header.
+    let algorithm = match HashAlgorithm::from_hex_length(field.len())? {
+        HashAlgorithm::Sha512 => return None,
+        algorithm => algorithm,
+    };
+    if hex::decode(field).is_err() {
+        return None;
+    }
+    Some((algorithm, field.to_uppercase()))
+}
+
+pub fn parse_ioc_feed(data: &str, source: &str) -> Vec<IocEntry> {
+    // Feeds are messy and every vendor lays them out differently. A line without a hash in it
+    // (comments, headers, blank lines) is skipped rather than failing the whole feed.
+    let mut entries: Vec<IocEntry> = Vec::new();
+    for line in data.lines() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+        let (hashes, name) = if line.contains(',') {
+            // CSV rows often carry the md5, sha1 and sha256 of the same sample side by side
+            let fields = split_csv_line(line);
+            let hashes: Vec<(HashAlgorithm, String)> = fields
+                .iter()
+                .filter_map(|field| get_ioc_hash(field.trim()))
+                .collect();
+            let name = fields
+                .iter()
+                .map(|field| field.trim())
+                .find(|field| !field.is_empty() && get_ioc_hash(field).is_none())
+                .unwrap_or("")
+                .to_string();
+            (hashes, name)
+        } else {
+            // Plain text is one hash per line, optionally followed by a name
+            let (hash, name) = line.split_once(char::is_whitespace).unwrap_or((line, ""));
+            (
+                get_ioc_hash(hash).into_iter().collect(),
+                name.trim().to_string(),
+            )
+        };
+        for (algorithm, hash) in hashes {
+            entries.push(IocEntry {
+                algorithm,
+                hash,
+                name: name.clone(),
+                source: source.to_string(),
+            });
+        }
+    }
+    entries
+}
+
+pub fn load_ioc_feeds(ioc_feeds: &[String]) -> IocDatabase {
+    let mut database = IocDatabase::default();
+    for feed_pattern in ioc_feeds {
+        let feed_paths = match glob(feed_pattern) {
+            Ok(feed_paths) => feed_paths,
+            Err(e) => {
+                error!("Invalid IOC feed pattern {}: {}", feed_pattern, e);
+                continue;
+            }
+        };
+        let mut found = false;
+        for feed_path in feed_paths.flatten() {
+            found = true;
+            // One broken feed shouldn't stop the others from being checked
+            match read_to_string(&feed_path) {
+                Ok(data) => {
+                    let entries = parse_ioc_feed(&data, feed_path.to_string_lossy().as_ref());
+                    info!(
+                        "Loaded {} IOC hash(es) from {}",
+                        entries.len(),
+                        feed_path.display()
+                    );
+                    database.add_entries(entries);
+                }
+                Err(e) => error!("Cannot read IOC feed {}: {}", feed_path.display(), e),
+            }
+        }
+        if !found {
+            warn!("No IOC feed found matching {}", feed_pattern);
+        }
+    }
+    database
+}
+
+pub fn warn_missing_ioc_algorithms(database: &IocDatabase, scan_settings: &ScanSettings) {
+    // A file can only match the algorithms it was hashed with. An md5 feed does nothing when
+    // only sha256 is enabled, which is easy to miss.
+    let enabled_algorithms: BTreeSet<HashAlgorithm> = scan_settings
+        .file_scan_settings
+        .iter()
+        .flat_map(|file_scan_setting| file_scan_setting.file_hashes.get_algorithms())
+        .collect();
+    for algorithm in database.get_algorithms() {
+        if !enabled_algorithms.contains(&algorithm) {
+            warn!(
+                "IOC feeds have {} hashes, but no file_scan_settings entry has {} enabled",
+                algorithm, algorithm
+            );
+        }
+    }
+}
+
+pub fn apply_ioc_matches(results: &mut [FileScanResult], database: &IocDatabase) {
+    for result in results.iter_mut() {
+        result.ioc_matches = database.get_matches(result);
+        if result.ioc_matches.is_empty() {
+            continue;
+        }
+        for ioc_match in &result.ioc_matches {
+            error!(
+                "IOC match: {} has {} {} from {} ({})",
+                result.path.display(),
+                ioc_match.algorithm,
+                ioc_match.hash,
+                ioc_match.source,
+                ioc_match.name
+            );
+        }
+        // It doesn't matter whether the file changed. Known malware on disk is the finding, and
+        // suppressions are for expected changes, which this never is.
+        result.severity = Severity::Critical;
+        result.suppressed = false;
+        result.suppressed_by = None;
+    }
+}
@@ -0,0 +1,740 @@
+use crate::changes::{ChangeType, FieldChange};
+use crate::file::FileScanResult;
+use crate::hashing::HashAlgorithm;
+use crate::helpers::split_csv_line;
+use crate::osfig_state::{load_osfig_settings, OsfigSettings};
+use crate::severity::{get_highest_severity, Severity};
+use log::{error, info};
+use memmap2::Mmap;
+use serde::{Deserialize, Serialize};
+use std::cmp::{Ordering, Reverse};
+use std::collections::BinaryHeap;
+use std::fs::{create_dir_all, remove_file, rename, File};
+use std::io::{BufRead, BufReader, BufWriter, Error, ErrorKind, Write};
+use std::path::{Path, PathBuf};
+
+const KNOWN_GOOD_DIR: &str = "known_good";
+const KNOWN_GOOD_FILE: &str = "hashes.txt";
+// Entries held in memory before a sorted run is written out. An entry takes a few hundred bytes,
+// so an import stays within a few hundred MB whatever the size of the manifest.
+const IMPORT_RUN_ENTRIES: usize = 500_000;
+
+#[allow(unused)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ManifestFormat {
+    // "<hash>  <path>" lines from sha256sum, md5sum, sha1sum or sha512sum
+    Sha256sum,
+    // hashdeep output, with the column order given by its "%%%% size,md5,..." header
+    Hashdeep,
+    // NSRL RDS style CSV with quoted "SHA-1","MD5",...,"FileName" columns
+    Nsrl,
+}
+
+impl ManifestFormat {
+    pub fn from_name(name: &str) -> Option<ManifestFormat> {
+        match name {
+            "sha256sum" => Some(ManifestFormat::Sha256sum),
+            "hashdeep" => Some(ManifestFormat::Hashdeep),
+            "nsrl" => Some(ManifestFormat::Nsrl),
+            _ => None,
+        }
+    }
+}
+
+#[allow(unused)]
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum KnownGoodStatus {
+    #[default]
+    Unknown,
+    KnownGood,
+    // The content is known, just not at this path. A known binary copied somewhere it has no
+    // business being is worth a look.
+    KnownGoodOtherPath,
+    // Only an md5 or sha1 matched. That's worth recording, but it never lowers a severity.
+    KnownGoodWeakHash,
+}
+
+#[allow(unused)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct KnownGoodEntry {
+    pub(crate) algorithm: HashAlgorithm,
+    pub(crate) hash: String,
+    // A bare file name (all NSRL gives us) matches that name in any directory
+    pub(crate) path: String,
+    // The manifest the entry was imported from
+    pub(crate) source: String,
+}
+
+// Entries are kept one per line as "<algorithm>\t<hash>\t<path>\t<source>", sorted. A scan maps
+// the file and binary searches it, so a full NSRL set costs nothing to open and only the pages a
+// lookup touches are ever read.
+#[allow(unused)]
+#[derive(Debug, Default)]
+pub struct KnownGoodDatabase {
+    data: KnownGoodData,
+}
+
+#[derive(Debug, Default)]
+enum KnownGoodData {
+    #[default]
+    Empty,
+    Memory(Vec<u8>),
+    Mapped(Mmap),
+}
+
+impl KnownGoodDatabase {
+    #[allow(unused)]
+    pub fn new(entries: Vec<KnownGoodEntry>) -> Self {
+        let mut data: Vec<u8> = Vec::new();
+        // Writing to a Vec can't fail
+        let _ = merge_lines(
+            &mut data,
+            vec![Box::new(get_sorted_lines(entries).into_iter().map(Ok))],
+        );
+        KnownGoodDatabase {
+            data: KnownGoodData::Memory(data),
+        }
+    }
+
+    fn get_data(&self) -> &[u8] {
+        match &self.data {
+            KnownGoodData::Empty => &[],
+            KnownGoodData::Memory(data) => data,
+            KnownGoodData::Mapped(mmap) => mmap,
+        }
+    }
+
+    fn get_lines(&self) -> impl Iterator<Item = &str> {
+        self.get_data()
+            .split(|byte| *byte == b'\n')
+            .filter_map(|line| std::str::from_utf8(line).ok())
+            .filter(|line| !line.is_empty())
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.get_data().is_empty()
+    }
+
+    pub fn get_entries(&self, algorithm: HashAlgorithm, hash: &str) -> Vec<KnownGoodEntry> {
+        let data = self.get_data();
+        let hash = hash.to_uppercase();
+        let key = (algorithm.name(), hash.as_str());
+
+        // Find the first line that doesn't sort before the key. lo always sits at a line start.
+        let mut lo = 0;
+        let mut hi = data.len();
+        while lo < hi {
+            let mid = lo + (hi - lo) / 2;
+            let line_start = data[lo..mid]
+                .iter()
+                .rposition(|byte| *byte == b'\n')
+                .map_or(lo, |position| lo + position + 1);
+            let line_end = get_line_end(data, line_start);
+            if get_line_key(&data[line_start..line_end]) < Some(key) {
+                lo = line_end + 1;
+            } else {
+                hi = line_start;
+            }
+        }
+
+        let mut entries: Vec<KnownGoodEntry> = Vec::new();
+        while lo < data.len() {
+            let line_end = get_line_end(data, lo);
+            let line = &data[lo..line_end];
+            if get_line_key(line) != Some(key) {
+                break;
+            }
+            if let Some(entry) = std::str::from_utf8(line).ok().and_then(parse_entry_line) {
+                entries.push(entry);
+            }
+            lo = line_end + 1;
+        }
+        entries
+    }
+
+    pub fn get_status(&self, result: &FileScanResult) -> KnownGoodStatus {
+        let mut status = KnownGoodStatus::Unknown;
+        for (algorithm, hash) in &result.hashes {
+            let entries = self.get_entries(*algorithm, hash);
+            if entries.is_empty() {
+                continue;
+            }
+            // md5 and sha1 collisions can be made on purpose, so a file could be built to match
+            // an NSRL entry. Only a collision resistant hash gets to vouch for the content.
+            if !HashAlgorithm::CONTENT_ALGORITHMS.contains(algorithm) {
+                if status == KnownGoodStatus::Unknown {
+                    status = KnownGoodStatus::KnownGoodWeakHash;
+                }
+                continue;
+            }
+            if entries
+                .iter()
+                .any(|entry| is_entry_path_match(&entry.path, &result.path))
+            {
+                return KnownGoodStatus::KnownGood;
+            }
+            status = KnownGoodStatus::KnownGoodOtherPath;
+        }
+        status
+    }
+}
+
+fn get_line_end(data: &[u8], line_start: usize) -> usize {
+    data[line_start..]
+        .iter()
+        .position(|byte| *byte == b'\n')
+        .map_or(data.len(), |position| line_start + position)
+}
+
+fn get_line_key(line: &[u8]) -> Option<(&str, &str)> {
+    let line = std::str::from_utf8(line).ok()?;
+    let mut fields = line.splitn(3, '\t');
+    Some((fields.next()?, fields.next()?))
+}
+
+fn parse_entry_line(line: &str) -> Option<KnownGoodEntry> {
+    let mut fields = line.splitn(4, '\t');
+    Some(KnownGoodEntry {
+        algorithm: HashAlgorithm::from_name(fields.next()?)?,
+        hash: fields.next()?.to_string(),
+        path: fields.next()?.to_string(),
+        source: fields.next()?.to_string(),
+    })
+}
+
+// A line source yields database lines in sorted order
+type LineSource<'a> = Box<dyn Iterator<Item = std::io::Result<String>> + 'a>;
+
+fn get_entry_key(entry: &KnownGoodEntry) -> (&str, &str, &str) {
+    (entry.algorithm.name(), &entry.hash, &entry.path)
+}
+
+fn get_line_entry_key(line: &str) -> Option<(&str, &str, &str)> {
+    let mut fields = line.splitn(4, '\t');
+    Some((fields.next()?, fields.next()?, fields.next()?))
+}
+
+fn get_sorted_lines(mut entries: Vec<KnownGoodEntry>) -> Vec<String> {
+    entries.sort_by(|a, b| get_entry_key(a).cmp(&get_entry_key(b)));
+    entries.dedup_by(|a, b| get_entry_key(a) == get_entry_key(b));
+    entries
+        .iter()
+        .map(|entry| {
+            format!(
+                "{}\t{}\t{}\t{}",
+                entry.algorithm.name(),
+                entry.hash,
+                entry.path,
+                entry.source
+            )
+        })
+        .collect()
+}
+
+struct MergeLine {
+    line: String,
+    source: usize,
+}
+
+impl Ord for MergeLine {
+    fn cmp(&self, other: &Self) -> Ordering {
+        get_line_entry_key(&self.line)
+            .cmp(&get_line_entry_key(&other.line))
+            .then(self.source.cmp(&other.source))
+    }
+}
+
+impl PartialOrd for MergeLine {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl PartialEq for MergeLine {
+    fn eq(&self, other: &Self) -> bool {
+        self.cmp(other) == Ordering::Equal
+    }
+}
+
+impl Eq for MergeLine {}
+
+// Skips anything that isn't an entry line
+fn get_next_line(source: &mut LineSource) -> std::io::Result<Option<String>> {
+    for line in source.by_ref() {
+        let line = line?;
+        if get_line_entry_key(&line).is_some() {
+            return Ok(Some(line));
+        }
+    }
+    Ok(None)
+}
+
+// Merges sorted line sources into out, in order and without duplicates. Only the first source's
+// line is kept for an entry, so the existing database goes first and the same manifest imported
+// twice adds nothing. Returns how many lines came from the other sources.
+fn merge_lines<W: Write>(out: &mut W, mut sources: Vec<LineSource>) -> std::io::Result<usize> {
+    let mut next_lines: BinaryHeap<Reverse<MergeLine>> = BinaryHeap::new();
+    for (source, lines) in sources.iter_mut().enumerate() {
+        if let Some(line) = get_next_line(lines)? {
+            next_lines.push(Reverse(MergeLine { line, source }));
+        }
+    }
+    let mut added = 0;
+    let mut last_line: Option<String> = None;
+    while let Some(Reverse(merge_line)) = next_lines.pop() {
+        if let Some(line) = get_next_line(&mut sources[merge_line.source])? {
+            next_lines.push(Reverse(MergeLine {
+                line,
+                source: merge_line.source,
+            }));
+        }
+        if last_line.as_deref().and_then(get_line_entry_key) == get_line_entry_key(&merge_line.line)
+        {
+            continue;
+        }
+        writeln!(out, "{}", merge_line.line)?;
+        if merge_line.source > 0 {
+            added += 1;
+        }
+        last_line = Some(merge_line.line);
+    }
+    Ok(added)
+}
+
+pub fn is_entry_path_match(entry_path: &str, path: &Path) -> bool {
+    if !entry_path.contains(['/', '\\']) {
+        return path
+            .file_name()
+            .is_some_and(|file_name| file_name == entry_path);
+    }
+    Path::new(entry_path) == path
+}
+
+pub fn get_known_good_path(osfig_settings: &OsfigSettings) -> PathBuf {
+    // Kept in its own directory so it never gets picked up as the newest results
+    Path::new(&osfig_settings.scan_result_path)
+        .join(KNOWN_GOOD_DIR)
+        .join(KNOWN_GOOD_FILE)
+}
+
+pub fn load_known_good_database(path: &Path) -> Result<KnownGoodDatabase, String> {
+    // Nothing imported yet just means nothing is known good
+    if !path.exists() {
+        return Ok(KnownGoodDatabase::default());
+    }
+    let database_file = File::open(path)
+        .map_err(|e| format!("Cannot open known good hashes {}: {}", path.display(), e))?;
+    let length = database_file
+        .metadata()
+        .map_err(|e| format!("Cannot open known good hashes {}: {}", path.display(), e))?
+        .len();
+    // An empty file can't be mapped
+    if length == 0 {
+        return Ok(KnownGoodDatabase::default());
+    }
+    // Safety: only OSFIG writes this file, and an import always replaces it with a rename rather
+    // than rewriting it in place, so the mapped file never shrinks under a running scan.
+    let mmap = unsafe { Mmap::map(&database_file) }
+        .map_err(|e| format!("Cannot map known good hashes {}: {}", path.display(), e))?;
+    Ok(KnownGoodDatabase {
+        data: KnownGoodData::Mapped(mmap),
+    })
+}
+
+// Collects imported entries in sorted runs. A full run is written to its own file next to the
+// database, so an import only ever holds one run in memory however large the manifest is.
+pub struct ImportRuns {
+    database_path: PathBuf,
+    run_entries: usize,
+    entries: Vec<KnownGoodEntry>,
+    run_paths: Vec<PathBuf>,
+}
+
+impl ImportRuns {
+    pub fn new(database_path: &Path, run_entries: usize) -> std::io::Result<Self> {
+        if let Some(parent) = database_path.parent() {
+            create_dir_all(parent)?;
+        }
+        Ok(ImportRuns {
+            database_path: database_path.to_path_buf(),
+            run_entries: run_entries.max(1),
+            entries: Vec::new(),
+            run_paths: Vec::new(),
+        })
+    }
+
+    pub fn add(&mut self, entry: KnownGoodEntry) -> std::io::Result<()> {
+        self.entries.push(entry);
+        if self.entries.len() >= self.run_entries {
+            self.write_run()?;
+        }
+        Ok(())
+    }
+
+    fn write_run(&mut self) -> std::io::Result<()> {
+        let run_path = self
+            .database_path
+            .with_extension(format!("run{}", self.run_paths.len()));
+        // Tracked before it's written so a half written run is cleaned up too
+        self.run_paths.push(run_path.clone());
+        let mut run_file = BufWriter::new(File::create(&run_path)?);
+        for line in get_sorted_lines(std::mem::take(&mut self.entries)) {
+            writeln!(run_file, "{}", line)?;
+        }
+        run_file.flush()
+    }
+
+    // Merges the runs with the database into a new database file. Returns how many entries were
+    // new.
+    pub fn save(mut self, database: &KnownGoodDatabase) -> std::io::Result<usize> {
+        let mut sources: Vec<LineSource> = vec![Box::new(
+            database.get_lines().map(|line| Ok(line.to_string())),
+        )];
+        for run_path in &self.run_paths {
+            sources.push(Box::new(BufReader::new(File::open(run_path)?).lines()));
+        }
+        // The last, partial run never needs to touch the disk
+        let entries = std::mem::take(&mut self.entries);
+        sources.push(Box::new(get_sorted_lines(entries).into_iter().map(Ok)));
+
+        // Written next to the database and renamed over it, so a scan that has the old one
+        // mapped keeps reading the old one
+        let temp_path = self.database_path.with_extension("tmp");
+        let mut temp_file = BufWriter::new(File::create(&temp_path)?);
+        let added = merge_lines(&mut temp_file, sources)?;
+        temp_file.flush()?;
+        drop(temp_file);
+        rename(&temp_path, &self.database_path)?;
+        Ok(added)
+    }
+}
+
+impl Drop for ImportRuns {
+    fn drop(&mut self) {
+        for run_path in &self.run_paths {
+            let _ = remove_file(run_path);
+        }
+    }
+}
+
+#[allow(unused)]
+pub fn parse_manifest<R: BufRead>(
+    format: ManifestFormat,
+    reader: R,
+    root: Option<&Path>,
+    source: &str,
+) -> Result<Vec<KnownGoodEntry>, String> {
+    let mut entries: Vec<KnownGoodEntry> = Vec::new();
+    read_manifest(format, reader, root, source, |entry| {
+        entries.push(entry);
+        Ok(())
+    })?;
+    Ok(entries)
+}
+
+pub fn read_manifest<R: BufRead, F: FnMut(KnownGoodEntry) -> Result<(), String>>(
+    format: ManifestFormat,
+    reader: R,
+    root: Option<&Path>,
+    source: &str,
+    mut add_entry: F,
+) -> Result<(), String> {
+    // Manifests are read a line at a time and each entry is handed on as it's parsed. An NSRL
+    // set runs to several GB.
+    let lines = reader
+        .lines()
+        .enumerate()
+        .map(|(line_index, line)| line.map_err(|e| format!("Line {}: {}", line_index + 1, e)));
+    match format {
+        ManifestFormat::Sha256sum => parse_sha256sum(lines, root, source, &mut add_entry),
+        ManifestFormat::Hashdeep => parse_hashdeep(lines, root, source, &mut add_entry),
+        ManifestFormat::Nsrl => parse_nsrl(lines, source, &mut add_entry),
+    }
+}
+
+fn parse_sha256sum(
+    lines: impl Iterator<Item = Result<String, String>>,
+    root: Option<&Path>,
+    source: &str,
+    add_entry: &mut impl FnMut(KnownGoodEntry) -> Result<(), String>,
+) -> Result<(), String> {
+    for (line_index, line) in lines.enumerate() {
+        let line = line?;
+        let line = line.trim_end_matches('\r');
+        if line.trim().is_empty() || line.starts_with('#') {
+            continue;
+        }
+        // Two spaces for text mode, a space and an asterisk for binary mode
+        let path = line
+            .split_once(' ')
+            .and_then(|(hash, rest)| Some((hash, rest.strip_prefix([' ', '*'])?)));
+        let Some((hash, path)) = path else {
+            return Err(format!("Line {}: Expected <hash>  <path>", line_index + 1));
+        };
+        add_entry(
+            get_entry(hash, None, path, root, source)
+                .map_err(|e| format!("Line {}: {}", line_index + 1, e))?,
+        )?;
+    }
+    Ok(())
+}
+
+fn parse_hashdeep(
+    lines: impl Iterator<Item = Result<String, String>>,
+    root: Option<&Path>,
+    source: &str,
+    add_entry: &mut impl FnMut(KnownGoodEntry) -> Result<(), String>,
+) -> Result<(), String> {
+    let mut columns: Vec<String> = Vec::new();
+    for (line_index, line) in lines.enumerate() {
+        let line = line?;
+        let line = line.trim_end_matches('\r');
+        if line.trim().is_empty() || line.starts_with("##") {
+            continue;
+        }
+        if let Some(header) = line.strip_prefix("%%%%") {
+            let header = header.trim();
+            if !header.starts_with("HASHDEEP") {
+                columns = header.split(',').map(str::to_string).collect();
+            }
+            continue;
+        }
+        let Some(path_column) = columns.iter().position(|column| column == "filename") else {
+            return Err(format!(
+                "Line {}: Expected a %%%% header with a filename column first",
+                line_index + 1
+            ));
+        };
+        // hashdeep doesn't quote anything, so a comma in the file name only works because the
+        // name is the last column
+        let fields: Vec<&str> = line.splitn(columns.len(), ',').collect();
+        if fields.len() != columns.len() {
+            return Err(format!(
+                "Line {}: Expected {} columns",
+                line_index + 1,
+                columns.len()
+            ));
+        }
+        for (column, hash) in columns.iter().zip(&fields) {
+            // tiger and whirlpool aren't hashes OSFIG can calculate
+            let Some(algorithm) = HashAlgorithm::from_name(column) else {
+                continue;
+            };
+            add_entry(
+                get_entry(hash, Some(algorithm), fields[path_column], root, source)
+                    .map_err(|e| format!("Line {}: {}", line_index + 1, e))?,
+            )?;
+        }
+    }
+    Ok(())
+}
+
+fn parse_nsrl(
+    lines: impl Iterator<Item = Result<String, String>>,
+    source: &str,
+    add_entry: &mut impl FnMut(KnownGoodEntry) -> Result<(), String>,
+) -> Result<(), String> {
+    let mut lines = lines.enumerate();
+    let columns = match lines.next() {
+        Some((_, header)) => split_csv_line(&header?),
+        None => return Ok(()),
+    };
+    let Some(name_column) = columns.iter().position(|column| column == "FileName") else {
+        return Err("Line 1: Expected a header with a FileName column".to_string());
+    };
+    let hash_columns: Vec<(usize, HashAlgorithm)> = columns
+        .iter()
+        .enumerate()
+        .filter_map(|(index, column)| {
+            let algorithm = match column.as_str() {
+                "MD5" => HashAlgorithm::Md5,
+                "SHA-1" => HashAlgorithm::Sha1,
+                "SHA-256" => HashAlgorithm::Sha256,
+                _ => return None,
+            };
+            Some((index, algorithm))
+        })
+        .collect();
+
+    for (line_index, line) in lines {
+        let line = line?;
+        if line.trim().is_empty() {
+            continue;
+        }
+        let fields = split_csv_line(&line);
+        let Some(file_name) = fields.get(name_column) else {
+            return Err(format!("Line {}: Missing FileName", line_index + 1));
+        };
+        for (index, algorithm) in &hash_columns {
+            match fields.get(*index) {
+                Some(hash) if !hash.is_empty() => add_entry(
+                    get_entry(hash, Some(*algorithm), file_name, None, source)
+                        .map_err(|e| format!("Line {}: {}", line_index + 1, e))?,
+                )?,
+                _ => {}
+            }
+        }
+    }
+    Ok(())
+}
+
+fn get_entry(
+    hash: &str,
+    algorithm: Option<HashAlgorithm>,
+    path: &str,
+    root: Option<&Path>,
+    source: &str,
+) -> Result<KnownGoodEntry, String> {
+    let algorithm = match algorithm {
+        Some(algorithm) => algorithm,
+        None => HashAlgorithm::from_hex_length(hash.len())
+            .ok_or_else(|| format!("Unrecognized hash length {}", hash.len()))?,
+    };
+    if hex::decode(hash).is_err() {
+        return Err(format!("Hash {} is not hexadecimal", hash));
+    }
+    // Manifests made with a relative path only mean something next to the directory they were
+    // made in
+    let path = match root {
+        Some(root) if Path::new(path).is_relative() => root
+            .join(path.strip_prefix("./").unwrap_or(path))
+            .to_string_lossy()
+            .to_string(),
+        _ => path.to_string(),
+    };
+    // The database is tab separated, one entry per line
+    if path.contains(['\t', '\n', '\r']) || source.contains(['\t', '\n', '\r']) {
+        return Err(format!("Path {:?} contains a tab or line break", path));
+    }
+    Ok(KnownGoodEntry {
+        algorithm,
+        hash: hash.to_uppercase(),
+        path,
+        source: source.to_string(),
+    })
+}
+
+pub fn apply_known_good(
+    results: &mut [FileScanResult],
+    database: &KnownGoodDatabase,
+    known_good_severity: Option<Severity>,
+) {
+    for result in results.iter_mut() {
+        result.known_good = database.get_status(result);
+        if result.known_good != KnownGoodStatus::KnownGood
+            || !matches!(result.change_type, ChangeType::Added | ChangeType::Modified)
+        {
+            continue;
+        }
+        let Some(known_good_severity) = known_good_severity else {
+            continue;
+        };
+        // The allowlist vouches for the content only. A known good binary that picked up setuid
+        // or a new owner keeps the severity its rules gave it.
+        for change in result.changes.iter_mut() {
+            if is_content_change(change) {
+                change.severity = change.severity.min(known_good_severity);
+            }
+        }
+        result.severity = get_highest_severity(&result.changes);
+    }
+}
+
+pub fn is_content_change(change: &FieldChange) -> bool {
+    HashAlgorithm::from_name(&change.field).is_some()
+        || matches!(
+            change.field.as_str(),
+            "exists" | "path" | "size" | "modified_time" | "creation_time"
+        )
+}
+
+pub fn run_known_good(options: &[String]) -> std::io::Result<()> {
+    if options.first().map(String::as_str) != Some("import") {
+        return Err(known_good_usage_error("Expected import"));
+    }
+    let mut paths: Vec<&String> = Vec::new();
+    let mut root: Option<&String> = None;
+    let mut options_iter = options[1..].iter();
+    while let Some(option) = options_iter.next() {
+        if option == "--root" {
+            root = options_iter.next();
+            if root.is_none() {
+                return Err(known_good_usage_error("Missing directory after --root"));
+            }
+        } else {
+            paths.push(option);
+        }
+    }
+    if paths.len() != 2 {
+        return Err(known_good_usage_error(
+            "Expected a manifest format and one manifest file",
+        ));
+    }
+    let Some(format) = ManifestFormat::from_name(paths[0]) else {
+        return Err(known_good_usage_error(
+            "Manifest format must be sha256sum, hashdeep or nsrl",
+        ));
+    };
+
+    let osfig_settings = load_osfig_settings();
+    let known_good_path = get_known_good_path(&osfig_settings);
+    let added = import_manifest(
+        &known_good_path,
+        format,
+        Path::new(paths[1]),
+        root.map(Path::new),
+    )
+    .map_err(|e| {
+        error!("{}", e);
+        Error::new(ErrorKind::InvalidData, e)
+    })?;
+
+    info!(
+        "Imported {} known good hash(es) from {} into {}",
+        added,
+        paths[1],
+        known_good_path.display()
+    );
+    println!("{} known good hash(es) imported", added);
+
+    Ok(())
+}
+
+fn known_good_usage_error(message: &str) -> Error {
+    error!("Invalid known-good options: {}", message);
+    println!(
+        "Usage: osfig known-good import <sha256sum|hashdeep|nsrl> <manifest> [--root <directory>]"
+    );
+    Error::new(ErrorKind::InvalidInput, message)
+}
+
+pub fn import_manifest(
+    known_good_path: &Path,
+    format: ManifestFormat,
+    manifest_path: &Path,
+    root: Option<&Path>,
+) -> Result<usize, String> {
+    let save_error = |e: Error| {
+        format!(
+            "Cannot save known good hashes {}: {}",
+            known_good_path.display(),
+            e
+        )
+    };
+    let manifest_file = File::open(manifest_path)
+        .map_err(|e| format!("Cannot read manifest {}: {}", manifest_path.display(), e))?;
+    let mut runs = ImportRuns::new(known_good_path, IMPORT_RUN_ENTRIES).map_err(save_error)?;
+    read_manifest(
+        format,
+        BufReader::new(manifest_file),
+        root,
+        manifest_path.to_string_lossy().as_ref(),
+        |entry| runs.add(entry).map_err(save_error),
+    )
+    .map_err(|e| format!("Cannot parse manifest {}: {}", manifest_path.display(), e))?;
+
+    let database = load_known_good_database(known_good_path)?;
+    runs.save(&database).map_err(save_error)
+}
@@ -1,4 +1,3 @@
-use log4rs;
 use std::fs;
 use std::fs::File;
 use std::io::Write;
@@ -1,16 +1,34 @@
 use crate::helpers::get_cur_username;
 use crate::osfig_state::load_osfig_settings;
-use log::info;
+use chrono::{DateTime, Utc};
+use log::{error, info, warn};
+use std::env::args;
+use std::process::exit;
+use std::time::SystemTime;
 
+mod acknowledge;
+mod binary;
+mod changes;
+mod diff;
 mod file;
+mod fuzzy;
+mod hash_cache;
 mod hashing;
 mod helpers;
+mod ioc;
+mod known_good;
 mod logging;
+mod merkle;
+mod mmap;
 mod osfig_state;
+mod package;
+mod registry;
+mod scan_error;
+mod scan_index;
 mod scan_settings;
+mod severity;
+mod suppression;
 
-#[cfg(windows)]
-mod registry;
 mod tests;
 #[cfg(windows)]
 mod win_acl;
@@ -26,18 +44,51 @@ fn main() -> std::io::Result<()> {
     osfig_state::print_usage();
     info!("Current Running User: {}", get_cur_username());
 
-    // Todo implement args
-    // let mut options: Vec<String> = args().collect();
-    // options.remove(0);
-    // if options.len() == 1 {
-    //     pattern = options.get(0).unwrap();
-    // }
-    let osfig_settings = load_osfig_settings();
+    let options: Vec<String> = args().skip(1).collect();
+    match options.first().map(String::as_str) {
+        Some("ack") => return acknowledge::run_acknowledge(&options[1..]),
+        Some("diff") => return diff::run_diff(&options[1..]),
+        Some("known-good") => return known_good::run_known_good(&options[1..]),
+        Some(command) => {
+            error!("Unknown command: {}", command);
+            osfig_state::print_command_usage();
+            exit(1);
+        }
+        None => {}
+    }
+
+    let mut osfig_settings = load_osfig_settings();
+
+    // The package database is loaded before scanning so files get hashed with the algorithms the
+    // package managers used
+    let package_database = if osfig_settings.scan_settings.verify_packages {
+        package::load_package_database(&osfig_settings.scan_settings)
+    } else {
+        package::PackageDatabase::default()
+    };
+    package::add_package_algorithms(&mut osfig_settings.scan_settings, &package_database);
 
     let mut scan_results = helpers::ScanResults::default();
+    // The newest results file is parsed once here, and each scan takes the part it compares against
+    let mut last_results = file::get_newest_results(&osfig_settings);
 
     if osfig_settings.scan_settings.scan_files {
-        scan_results.add_files(file::scan_files(&osfig_settings));
+        // Fast mode reuses hashes from the last run until a full rehash is due
+        let now = DateTime::<Utc>::from(SystemTime::now());
+        let last_full_rehash = &last_results.full_rehash;
+        let full_rehash =
+            hash_cache::is_full_rehash_due(&osfig_settings.scan_settings, last_full_rehash, now);
+        if full_rehash {
+            info!("Full rehash this run: Every file is read from disk");
+        }
+        scan_results.full_rehash =
+            hash_cache::get_next_full_rehash_state(last_full_rehash, full_rehash, now);
+        let full_rehash_state = scan_results.full_rehash.clone();
+        scan_results.add_files(file::scan_files(
+            &osfig_settings,
+            std::mem::take(&mut last_results.files),
+            &full_rehash_state,
+        ));
         info!("File scanning complete");
     } else {
         info!("File scanning disabled this run: Validate settings if this is not intended")
@@ -45,14 +96,44 @@ fn main() -> std::io::Result<()> {
 
     #[cfg(windows)]
     if osfig_settings.scan_settings.scan_registry {
-        scan_results.add_registries(registry::scan_reg_keys(
-            &osfig_settings.scan_settings.registry_patterns,
+        // Acknowledgments only cover files, so registry keys are always compared against the
+        // newest results file.
+        scan_results.add_registries(registry::compare_registry_scans(
+            &last_results.registry,
+            registry::scan_reg_keys(&osfig_settings.scan_settings.registry_patterns),
         ));
         info!("Registry scanning complete");
     } else {
         info!("Registry scanning disabled this run: Validate settings if this is not intended")
     }
 
+    scan_results.classify_changes(&osfig_settings.scan_settings.severity_rules);
+    match known_good::load_known_good_database(&known_good::get_known_good_path(&osfig_settings)) {
+        Ok(known_good_database) if known_good_database.is_empty() => {}
+        Ok(known_good_database) => scan_results.apply_known_good(
+            &known_good_database,
+            osfig_settings.scan_settings.known_good_severity,
+        ),
+        Err(e) => warn!("Known good hashes not checked this run: {}", e),
+    }
+    scan_results.apply_suppressions(&osfig_settings.scan_settings.suppressions);
+    // Feeds are read fresh every run, so a newly published hash is checked on the next scan
+    let ioc_database = ioc::load_ioc_feeds(&osfig_settings.scan_settings.ioc_feeds);
+    if !ioc_database.is_empty() {
+        info!("Checking files against {} IOC hash(es)", ioc_database.len());
+        ioc::warn_missing_ioc_algorithms(&ioc_database, &osfig_settings.scan_settings);
+        scan_results.apply_ioc_matches(&ioc_database);
+    }
+    if !package_database.is_empty() {
+        info!(
+            "Verifying files against {} package file hash(es)",
+            package_database.len()
+        );
+        scan_results.verify_packages(&package_database);
+    }
+    scan_results.summarize_changes();
+    info!("Change summary: {}", scan_results.summary);
+
     helpers::save_results_to_file(scan_results, &osfig_settings);
 
     Ok(())
@@ -60,4 +141,3 @@ fn main() -> std::io::Result<()> {
 
 // Todo Store scans in local db for comparisons
 // Todo Set up a build pipeline that automatically addresses versioning
-// Todo Add commandline options
@@ -0,0 +1,272 @@
+use crate::changes::ChangeType;
+use crate::hashing::{get_file_hashes, HashAlgorithm};
+use crate::scan_settings::FileHashes;
+use log::warn;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::collections::{HashMap, HashSet};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+#[cfg(target_os = "linux")]
+use std::os::unix::fs::PermissionsExt;
+#[cfg(windows)]
+use std::os::windows::fs::MetadataExt;
+
+#[allow(unused)]
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum DirectoryHashMode {
+    #[default]
+    Off,
+    OneLevel,
+    Recursive,
+}
+
+#[allow(unused)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct DirectoryHash {
+    pub(crate) digest: String,
+    pub(crate) mode: DirectoryHashMode,
+    pub(crate) entries: Vec<DirectoryEntry>,
+}
+
+#[allow(unused)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct DirectoryEntry {
+    pub(crate) name: String,
+    pub(crate) entry_type: String,
+    pub(crate) mode: u32,
+    pub(crate) hash: String,
+    // Only filled in for subdirectories when hashing recursively
+    #[serde(default)]
+    pub(crate) entries: Vec<DirectoryEntry>,
+}
+
+#[allow(unused)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct DirectoryEntryChange {
+    pub(crate) path: Box<PathBuf>,
+    pub(crate) change_type: ChangeType,
+}
+
+// Stands in for the digest of a directory that can't be listed. It can't be mistaken for a
+// real digest, so a populated directory that becomes unreadable doesn't look like an empty one.
+pub const UNREADABLE_DIRECTORY_DIGEST: &str = "unreadable";
+
+pub fn get_directory_hash(
+    path: &Path,
+    mode: DirectoryHashMode,
+    read_buffer_size: u64,
+) -> Option<DirectoryHash> {
+    if mode == DirectoryHashMode::Off || !path.is_dir() {
+        return None;
+    }
+    let (digest, entries) =
+        match get_directory_entries(path, mode == DirectoryHashMode::Recursive, read_buffer_size) {
+            Some(entries) => (get_entries_digest(&entries), entries),
+            None => (UNREADABLE_DIRECTORY_DIGEST.to_string(), vec![]),
+        };
+    Some(DirectoryHash {
+        digest,
+        mode,
+        entries,
+    })
+}
+
+// None when the directory can't be listed
+fn get_directory_entries(
+    path: &Path,
+    recursive: bool,
+    read_buffer_size: u64,
+) -> Option<Vec<DirectoryEntry>> {
+    let read_dir = match fs::read_dir(path) {
+        Ok(read_dir) => read_dir,
+        Err(e) => {
+            warn!(
+                "Cannot read directory for hashing: {}: {}",
+                path.display(),
+                e
+            );
+            return None;
+        }
+    };
+
+    let mut child_paths: Vec<PathBuf> = read_dir
+        .filter_map(|dir_entry| dir_entry.ok())
+        .map(|dir_entry| dir_entry.path())
+        .collect();
+    // read_dir order depends on the file system, so sort to get the same digest every time
+    child_paths.sort_by(|first, second| first.file_name().cmp(&second.file_name()));
+
+    Some(
+        child_paths
+            .iter()
+            .filter_map(|child_path| get_directory_entry(child_path, recursive, read_buffer_size))
+            .collect(),
+    )
+}
+
+fn get_directory_entry(
+    path: &Path,
+    recursive: bool,
+    read_buffer_size: u64,
+) -> Option<DirectoryEntry> {
+    // Links are never followed. A link back up the tree would otherwise never finish.
+    let md = match fs::symlink_metadata(path) {
+        Ok(md) => md,
+        Err(e) => {
+            warn!(
+                "Cannot read metadata for directory hashing: {}: {}",
+                path.display(),
+                e
+            );
+            return None;
+        }
+    };
+    let name = path.file_name()?.to_string_lossy().to_string();
+    #[cfg(windows)]
+    let mode = md.file_attributes();
+    #[cfg(target_os = "linux")]
+    let mode = md.permissions().mode();
+
+    let mut entries: Vec<DirectoryEntry> = vec![];
+    let (entry_type, hash) = if md.is_symlink() {
+        let target = fs::read_link(path).unwrap_or_default();
+        ("symlink", target.to_string_lossy().to_string())
+    } else if md.is_dir() {
+        if recursive {
+            match get_directory_entries(path, recursive, read_buffer_size) {
+                Some(dir_entries) => {
+                    entries = dir_entries;
+                    ("dir", get_entries_digest(&entries))
+                }
+                None => ("dir", UNREADABLE_DIRECTORY_DIGEST.to_string()),
+            }
+        } else {
+            ("dir", "".to_string())
+        }
+    } else if md.is_file() {
+        ("file", get_content_hash(path, read_buffer_size))
+    } else {
+        ("other", "".to_string())
+    };
+
+    Some(DirectoryEntry {
+        name,
+        entry_type: entry_type.to_string(),
+        mode,
+        hash,
+        entries,
+    })
+}
+
+fn get_content_hash(path: &Path, read_buffer_size: u64) -> String {
+    let hash_values = FileHashes {
+        sha256: true,
+        ..Default::default()
+    };
+    match get_file_hashes(&hash_values, read_buffer_size, path) {
+        Ok(mut hashes) => hashes.remove(&HashAlgorithm::Sha256).unwrap_or_default(),
+        Err(e) => {
+            warn!(
+                "Cannot open file for directory hashing: {}: {}",
+                path.display(),
+                e
+            );
+            "".to_string()
+        }
+    }
+}
+
+pub fn get_entries_digest(entries: &[DirectoryEntry]) -> String {
+    // The name length is included so a name can't run into the fields after it
+    let mut hasher = Sha256::new();
+    for entry in entries {
+        hasher.update(
+            format!(
+                "{}:{}:{}:{:o}:{}\n",
+                entry.entry_type,
+                entry.name.len(),
+                entry.name,
+                entry.mode,
+                entry.hash
+            )
+            .as_bytes(),
+        );
+    }
+    hex::encode_upper(hasher.finalize())
+}
+
+pub fn get_directory_changes(
+    old_hash: &DirectoryHash,
+    new_hash: &DirectoryHash,
+    path: &Path,
+) -> Vec<DirectoryEntryChange> {
+    let mut changes: Vec<DirectoryEntryChange> = Vec::new();
+    if old_hash.digest != new_hash.digest {
+        find_entry_changes(&old_hash.entries, &new_hash.entries, path, &mut changes);
+    }
+    changes
+}
+
+fn find_entry_changes(
+    old_entries: &[DirectoryEntry],
+    new_entries: &[DirectoryEntry],
+    path: &Path,
+    changes: &mut Vec<DirectoryEntryChange>,
+) {
+    // Big directories like /usr/lib have thousands of entries, so look them up by name instead
+    // of searching the list for each one
+    let mut new_entries_by_name: HashMap<&str, &DirectoryEntry> = HashMap::new();
+    for new_entry in new_entries {
+        new_entries_by_name
+            .entry(new_entry.name.as_str())
+            .or_insert(new_entry);
+    }
+    let old_names: HashSet<&str> = old_entries
+        .iter()
+        .map(|old_entry| old_entry.name.as_str())
+        .collect();
+
+    for old_entry in old_entries {
+        let entry_path = path.join(&old_entry.name);
+        match new_entries_by_name.get(old_entry.name.as_str()) {
+            None => changes.push(DirectoryEntryChange {
+                path: Box::new(entry_path),
+                change_type: ChangeType::Deleted,
+            }),
+            Some(new_entry) if *new_entry != old_entry => {
+                // Drill into a subdirectory so the change points at the files that changed
+                // instead of just the top of the tree
+                let is_same_directory = old_entry.entry_type == "dir"
+                    && new_entry.entry_type == "dir"
+                    && old_entry.mode == new_entry.mode
+                    && !old_entry.entries.is_empty()
+                    && !new_entry.entries.is_empty();
+                if is_same_directory {
+                    find_entry_changes(
+                        &old_entry.entries,
+                        &new_entry.entries,
+                        &entry_path,
+                        changes,
+                    );
+                } else {
+                    changes.push(DirectoryEntryChange {
+                        path: Box::new(entry_path),
+                        change_type: ChangeType::Modified,
+                    });
+                }
+            }
+            Some(_) => {}
+        }
+    }
+    for new_entry in new_entries {
+        if !old_names.contains(new_entry.name.as_str()) {
+            changes.push(DirectoryEntryChange {
+                path: Box::new(path.join(&new_entry.name)),
+                change_type: ChangeType::Added,
+            });
+        }
+    }
+}
@@ -0,0 +1,172 @@
+use memmap2::MmapOptions;
+use serde::{Deserialize, Serialize};
+use std::fs::File;
+use std::io;
+
+#[allow(unused)]
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ReadMode {
+    #[default]
+    Buffered,
+    Mmap,
+}
+
+pub fn get_read_mode(file_mmap: bool, file_mmap_threshold: u64, file_size: u64) -> ReadMode {
+    // Mapping has a fixed setup cost, so small files are faster through the read buffer. A zero
+    // threshold leaves it to file_mmap alone.
+    if file_mmap || (file_mmap_threshold != 0 && file_size >= file_mmap_threshold) {
+        return ReadMode::Mmap;
+    }
+    ReadMode::Buffered
+}
+
+// Hands the whole file to update in one slice, with no copies through a read buffer. Returns
+// false if the file was truncated while it was mapped. Whatever was hashed by then is garbage and
+// the caller needs to read the file again the normal way.
+pub fn read_mapped<F: FnMut(&[u8])>(file: &File, length: u64, mut update: F) -> io::Result<bool> {
+    let length = usize::try_from(length).map_err(io::Error::other)?;
+    // Safety: another process can change the file under the mapping. That only ever costs us a
+    // wrong hash, which is the same thing that happens when a file changes mid-read, and
+    // truncation is caught by the guard below.
+    let mmap = unsafe { MmapOptions::new().len(length).map(file)? };
+    #[cfg(unix)]
+    let _ = mmap.advise(memmap2::Advice::Sequential);
+
+    #[cfg(target_os = "linux")]
+    {
+        let guard = sigbus::FaultGuard::new(mmap.as_ptr() as usize, length)?;
+        update(&mmap);
+        Ok(!guard.is_faulted())
+    }
+    // Windows won't truncate a file while it's mapped, so there's nothing to guard against
+    #[cfg(windows)]
+    {
+        update(&mmap);
+        Ok(true)
+    }
+}
+
+#[cfg(target_os = "linux")]
+mod sigbus {
+    // Reading a mapped page past the end of a truncated file raises SIGBUS, which kills the
+    // process by default. The handler swaps zero pages in over the rest of the mapping and marks
+    // it faulted, so the read finishes and the caller throws the result away.
+    use crate::scan_settings::MAX_FILE_SCAN_WORKERS;
+    use std::io;
+    use std::ptr;
+    use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+    use std::sync::OnceLock;
+
+    // One mapping per scan worker is the most there can be at once
+    const GUARD_SLOTS: usize = MAX_FILE_SCAN_WORKERS as usize;
+
+    static SLOT_IN_USE: [AtomicBool; GUARD_SLOTS] = [const { AtomicBool::new(false) }; GUARD_SLOTS];
+    static SLOT_START: [AtomicUsize; GUARD_SLOTS] = [const { AtomicUsize::new(0) }; GUARD_SLOTS];
+    static SLOT_LENGTH: [AtomicUsize; GUARD_SLOTS] = [const { AtomicUsize::new(0) }; GUARD_SLOTS];
+    static SLOT_FAULTED: [AtomicBool; GUARD_SLOTS] =
+        [const { AtomicBool::new(false) }; GUARD_SLOTS];
+    static PAGE_SIZE: AtomicUsize = AtomicUsize::new(4096);
+    static PREVIOUS_ACTION: OnceLock<Result<libc::sigaction, i32>> = OnceLock::new();
+
+    pub struct FaultGuard {
+        slot: usize,
+    }
+
+    impl FaultGuard {
+        pub fn new(start: usize, length: usize) -> io::Result<FaultGuard> {
+            install_handler()?;
+            let slot = (0..GUARD_SLOTS)
+                .find(|slot| {
+                    SLOT_IN_USE[*slot]
+                        .compare_exchange(false, true, Ordering::SeqCst, Ordering::SeqCst)
+                        .is_ok()
+                })
+                .ok_or_else(|| io::Error::other("No free mapping guard"))?;
+            SLOT_FAULTED[slot].store(false, Ordering::SeqCst);
+            SLOT_LENGTH[slot].store(length, Ordering::SeqCst);
+            // The start goes in last. The handler skips slots with no start.
+            SLOT_START[slot].store(start, Ordering::SeqCst);
+            Ok(FaultGuard { slot })
+        }
+
+        pub fn is_faulted(&self) -> bool {
+            SLOT_FAULTED[self.slot].load(Ordering::SeqCst)
+        }
+    }
+
+    impl Drop for FaultGuard {
+        fn drop(&mut self) {
+            SLOT_START[self.slot].store(0, Ordering::SeqCst);
+            SLOT_LENGTH[self.slot].store(0, Ordering::SeqCst);
+            SLOT_IN_USE[self.slot].store(false, Ordering::SeqCst);
+        }
+    }
+
+    fn install_handler() -> io::Result<()> {
+        let previous_action = PREVIOUS_ACTION.get_or_init(|| unsafe {
+            let page_size = libc::sysconf(libc::_SC_PAGESIZE);
+            if page_size > 0 {
+                PAGE_SIZE.store(page_size as usize, Ordering::SeqCst);
+            }
+            let mut action: libc::sigaction = std::mem::zeroed();
+            action.sa_sigaction = handle_sigbus as *const () as usize;
+            action.sa_flags = libc::SA_SIGINFO | libc::SA_NODEFER;
+            libc::sigemptyset(&mut action.sa_mask);
+            let mut previous_action: libc::sigaction = std::mem::zeroed();
+            if libc::sigaction(libc::SIGBUS, &action, &mut previous_action) != 0 {
+                return Err(io::Error::last_os_error().raw_os_error().unwrap_or(0));
+            }
+            Ok(previous_action)
+        });
+        match previous_action {
+            Ok(_) => Ok(()),
+            Err(code) => Err(io::Error::from_raw_os_error(*code)),
+        }
+    }
+
+    extern "C" fn handle_sigbus(
+        _signal: libc::c_int,
+        info: *mut libc::siginfo_t,
+        _context: *mut libc::c_void,
+    ) {
+        // Only async-signal-safe calls from here on: atomics, mmap and sigaction
+        let fault_address = unsafe { (*info).si_addr() } as usize;
+        let page_size = PAGE_SIZE.load(Ordering::SeqCst);
+        for slot in 0..GUARD_SLOTS {
+            let start = SLOT_START[slot].load(Ordering::SeqCst);
+            let end = start + SLOT_LENGTH[slot].load(Ordering::SeqCst);
+            if start == 0 || fault_address < start || fault_address >= end {
+                continue;
+            }
+            // Cover everything from the faulting page on, so a big file doesn't fault once per page
+            let page = fault_address & !(page_size - 1);
+            let mapped = unsafe {
+                libc::mmap(
+                    page as *mut libc::c_void,
+                    end - page,
+                    libc::PROT_READ,
+                    libc::MAP_PRIVATE | libc::MAP_FIXED | libc::MAP_ANONYMOUS,
+                    -1,
+                    0,
+                )
+            };
+            if mapped != libc::MAP_FAILED {
+                SLOT_FAULTED[slot].store(true, Ordering::SeqCst);
+                return;
+            }
+        }
+        // Not one of ours. Put back whatever handled SIGBUS before, so the fault happens again
+        // on return and gets the usual treatment.
+        unsafe {
+            match PREVIOUS_ACTION.get() {
+                Some(Ok(previous_action)) => {
+                    libc::sigaction(libc::SIGBUS, previous_action, ptr::null_mut());
+                }
+                _ => {
+                    libc::signal(libc::SIGBUS, libc::SIG_DFL);
+                }
+            }
+        }
+    }
+}
@@ -1,4 +1,7 @@
-use crate::scan_settings::{get_default_scan_settings, FileScanSetting, ScanSettings};
+use crate::scan_settings::{
+    get_default_scan_settings, FileScanSetting, ScanSettings, DEFAULT_BINARY_BLOCK_SIZE,
+    DEFAULT_FILE_SCAN_WORKERS, MAX_FILE_SCAN_WORKERS,
+};
 use log::{error, info, warn};
 use serde::{Deserialize, Serialize};
 use serde_json::from_str;
@@ -11,7 +14,7 @@ use std::string::ToString;
 
 // Settings defaults
 const MAX_FILE_SCAN_DELAY: u16 = 10000;
-const DEFAULT_SCANS_SAVE_PATH: &'static str = "./scans";
+const DEFAULT_SCANS_SAVE_PATH: &str = "./scans";
 pub const DEFAULT_FILE_READ_BUFFER_SIZE: u64 = 4_096;
 
 #[allow(unused)]
@@ -54,6 +57,19 @@ pub fn print_usage() {
     // )
 }
 
+pub fn print_command_usage() {
+    println!("Usage:");
+    println!("  osfig                                   Run a scan using the settings file");
+    println!("  osfig diff <older> <newer> [--output <report>]");
+    println!("                                          Compare two results files");
+    println!("  osfig ack <path>... --reason <reason> [--ticket <ticket id>]");
+    println!(
+        "                                          Approve detected changes into the baseline"
+    );
+    println!("  osfig known-good import <sha256sum|hashdeep|nsrl> <manifest> [--root <directory>]");
+    println!("                                          Add a manifest to the known good hashes");
+}
+
 fn print_banner() {
     // Todo is it possible to detect when a terminal doesn't support color output?
 
@@ -90,12 +106,10 @@ fn print_banner() {
 }
 
 fn get_default_settings() -> OsfigSettings {
-    let osfig_settings = OsfigSettings {
+    OsfigSettings {
         scan_settings: get_default_scan_settings(),
         scan_result_path: DEFAULT_SCANS_SAVE_PATH.to_string(),
-    };
-
-    osfig_settings
+    }
 }
 const CONFIG_FILE_PATH: &str = "./config/osfig_settings.json";
 
@@ -122,7 +136,7 @@ pub fn save_osfig_settings(settings: OsfigSettings) {
         std::thread::sleep(std::time::Duration::from_millis(50));
     }
 
-    let mut json_file = File::create(&path).unwrap();
+    let mut json_file = File::create(path).unwrap();
     let file_writer = BufWriter::new(&json_file);
     let storage_result = serde_json::to_writer_pretty(file_writer, &settings);
     match storage_result {
@@ -186,6 +200,17 @@ pub fn load_osfig_settings() -> OsfigSettings {
         reset_delay(&mut settings.scan_settings);
     }
 
+    // Zero workers would never scan anything, and past a few dozen the disk is the bottleneck
+    // while every worker still holds its own read buffer
+    let file_scan_workers = settings.scan_settings.file_scan_workers;
+    if file_scan_workers == 0 || file_scan_workers > MAX_FILE_SCAN_WORKERS {
+        warn!(
+            "Found invalid file_scan_workers: Resetting to {}",
+            DEFAULT_FILE_SCAN_WORKERS
+        );
+        settings.scan_settings.file_scan_workers = DEFAULT_FILE_SCAN_WORKERS;
+    }
+
     // If they provide no path, use the default directory.
     if is_bad_scan_save_path(&settings.scan_result_path) {
         warn!(
@@ -200,7 +225,7 @@ pub fn load_osfig_settings() -> OsfigSettings {
     let mut temp_file_scan_settings: Vec<FileScanSetting> = Vec::new();
     for file_scan_setting in settings.scan_settings.file_scan_settings {
         let mut temp_file_scan_setting = file_scan_setting.clone();
-        if file_scan_setting.file_read_buffer_size <= 0
+        if file_scan_setting.file_read_buffer_size == 0
             || file_scan_setting.file_read_buffer_size > 4_294_967_296
         {
             warn!(
@@ -209,6 +234,14 @@ pub fn load_osfig_settings() -> OsfigSettings {
             );
             temp_file_scan_setting.file_read_buffer_size = DEFAULT_FILE_READ_BUFFER_SIZE;
         }
+        // A zero block size would leave nothing to compare
+        if file_scan_setting.file_binary_block_size == 0 {
+            warn!(
+                "Found invalid file_binary_block_size: Resetting to {}",
+                DEFAULT_BINARY_BLOCK_SIZE
+            );
+            temp_file_scan_setting.file_binary_block_size = DEFAULT_BINARY_BLOCK_SIZE;
+        }
         temp_file_scan_settings.push(temp_file_scan_setting);
     }
     settings.scan_settings.file_scan_settings = temp_file_scan_settings;
@@ -221,7 +254,7 @@ fn reset_delay(scan_settings: &mut ScanSettings) {
     scan_settings.file_scan_delay = MAX_FILE_SCAN_DELAY;
 }
 
-fn is_bad_scan_save_path(path: &String) -> bool {
+fn is_bad_scan_save_path(path: &str) -> bool {
     if path.is_empty() || path.ends_with("/") || path.ends_with("\\") {
         warn!("Invalid setting configuration: scan_result_path: See documentation");
         false
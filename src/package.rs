@@ -0,0 +1,373 @@
+use crate::file::FileScanResult;
+use crate::hashing::HashAlgorithm;
+use crate::scan_error::is_unreadable;
+use crate::scan_settings::ScanSettings;
+use glob::glob;
+use log::{debug, error, info, warn};
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeSet, HashMap, HashSet};
+use std::fs::{canonicalize, read_to_string};
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+// Every file with its digest and digest algorithm, one line per file. The = repeats the package
+// level tags on each line.
+const RPM_QUERY_FORMAT: &str =
+    "[%{FILENAMES}\\t%{FILEDIGESTS}\\t%{FILEFLAGS:fflags}\\t%{=FILEDIGESTALGO}\\t%{=NAME}\\n]";
+const RPM_PROGRAM: &str = "rpm";
+
+#[allow(unused)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum PackageStatus {
+    Intact,
+    Modified,
+    Unowned,
+}
+
+#[allow(unused)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct PackageFile {
+    pub(crate) package: String,
+    pub(crate) algorithm: HashAlgorithm,
+    pub(crate) hash: String,
+    // Config files are meant to be edited, so a modified one is far less alarming
+    pub(crate) conffile: bool,
+}
+
+#[allow(unused)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct PackageCheck {
+    pub(crate) status: PackageStatus,
+    // Empty when the file is unowned
+    pub(crate) package: String,
+    pub(crate) algorithm: Option<HashAlgorithm>,
+    pub(crate) expected_hash: String,
+    pub(crate) conffile: bool,
+}
+
+#[allow(unused)]
+#[derive(Debug, Default, Clone)]
+pub struct PackageDatabase {
+    files: HashMap<PathBuf, PackageFile>,
+}
+
+impl PackageDatabase {
+    pub fn add_files(&mut self, files: Vec<(PathBuf, PackageFile)>) {
+        // Packages still list /bin/ls on systems where /bin is a symlink to /usr/bin, so each
+        // file is also added under its resolved directory. Lots of files share a directory.
+        let mut resolved_dirs: HashMap<PathBuf, Option<PathBuf>> = HashMap::new();
+        for (path, package_file) in files {
+            let resolved_path = path.parent().and_then(|parent| {
+                let resolved_dir = resolved_dirs
+                    .entry(parent.to_path_buf())
+                    .or_insert_with(|| canonicalize(parent).ok());
+                Some(resolved_dir.as_ref()?.join(path.file_name()?))
+            });
+            if let Some(resolved_path) = resolved_path.filter(|resolved| *resolved != path) {
+                self.add_file(resolved_path, package_file.clone());
+            }
+            self.add_file(path, package_file);
+        }
+    }
+
+    fn add_file(&mut self, path: PathBuf, package_file: PackageFile) {
+        // Diverted and shared paths are claimed by more than one package. The first one loaded
+        // is the one checked.
+        self.files.entry(path).or_insert(package_file);
+    }
+
+    pub fn len(&self) -> usize {
+        self.files.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.files.is_empty()
+    }
+
+    pub fn get(&self, path: &Path) -> Option<&PackageFile> {
+        self.files.get(path)
+    }
+
+    pub fn get_algorithms(&self) -> BTreeSet<HashAlgorithm> {
+        self.files
+            .values()
+            .map(|package_file| package_file.algorithm)
+            .collect()
+    }
+}
+
+fn get_package_file(
+    package: &str,
+    hash: &str,
+    algorithm: HashAlgorithm,
+    conffile: bool,
+) -> Option<PackageFile> {
+    // dpkg writes placeholders like "newconffile" for hashes it doesn't know yet
+    if hash.is_empty() || hex::decode(hash).is_err() {
+        return None;
+    }
+    Some(PackageFile {
+        package: package.to_string(),
+        algorithm,
+        hash: hash.to_uppercase(),
+        conffile,
+    })
+}
+
+// Returns the installed package names and the config files with their md5s. Config files aren't
+// in the md5sums files, only here.
+pub fn parse_dpkg_status(data: &str) -> (HashSet<String>, Vec<(PathBuf, PackageFile)>) {
+    let mut installed: HashSet<String> = HashSet::new();
+    let mut conffiles: Vec<(PathBuf, PackageFile)> = Vec::new();
+    // Paragraphs are separated by a blank line, one per package
+    for paragraph in data.split("\n\n") {
+        let mut package = "";
+        let mut is_installed = false;
+        let mut paragraph_conffiles: Vec<(&str, &str)> = Vec::new();
+        let mut in_conffiles = false;
+        for line in paragraph.lines() {
+            if let Some(conffile) = line.strip_prefix(' ') {
+                if in_conffiles {
+                    let fields: Vec<&str> = conffile.split_whitespace().collect();
+                    // Obsolete config files belong to an older version of the package
+                    if fields.len() == 2 {
+                        paragraph_conffiles.push((fields[0], fields[1]));
+                    }
+                }
+                continue;
+            }
+            in_conffiles = false;
+            match line.split_once(':') {
+                Some(("Package", value)) => package = value.trim(),
+                Some(("Status", value)) => is_installed = value.trim().ends_with(" installed"),
+                Some(("Conffiles", _)) => in_conffiles = true,
+                _ => {}
+            }
+        }
+        if package.is_empty() || !is_installed {
+            continue;
+        }
+        installed.insert(package.to_string());
+        for (path, hash) in paragraph_conffiles {
+            if let Some(package_file) = get_package_file(package, hash, HashAlgorithm::Md5, true) {
+                conffiles.push((PathBuf::from(path), package_file));
+            }
+        }
+    }
+    (installed, conffiles)
+}
+
+pub fn parse_dpkg_md5sums(data: &str, package: &str) -> Vec<(PathBuf, PackageFile)> {
+    // "<md5>  <path>" with the path relative to the root of the file system
+    data.lines()
+        .filter_map(|line| {
+            let (hash, path) = line.split_once(char::is_whitespace)?;
+            let path = path.trim_start();
+            let package_file = get_package_file(package, hash, HashAlgorithm::Md5, false)?;
+            Some((Path::new("/").join(path), package_file))
+        })
+        .collect()
+}
+
+pub fn load_dpkg_files(dpkg_path: &Path) -> Result<Vec<(PathBuf, PackageFile)>, String> {
+    let status_path = dpkg_path.join("status");
+    let status = read_to_string(&status_path)
+        .map_err(|e| format!("Cannot read dpkg status {}: {}", status_path.display(), e))?;
+    let (installed, mut files) = parse_dpkg_status(&status);
+
+    let md5sums_pattern = dpkg_path.join("info").join("*.md5sums");
+    let md5sums_paths = glob(md5sums_pattern.to_string_lossy().as_ref())
+        .map_err(|e| format!("Invalid dpkg path {}: {}", dpkg_path.display(), e))?;
+    for md5sums_path in md5sums_paths.flatten() {
+        // Multi-arch packages are named <package>:<arch>.md5sums
+        let Some(file_stem) = md5sums_path.file_stem().and_then(|stem| stem.to_str()) else {
+            continue;
+        };
+        let package = file_stem.split(':').next().unwrap_or(file_stem);
+        // Leftovers from removed packages would claim files that no longer belong to anything
+        if !installed.contains(package) {
+            debug!(
+                "Skipping {}: Package is not installed",
+                md5sums_path.display()
+            );
+            continue;
+        }
+        match read_to_string(&md5sums_path) {
+            Ok(data) => files.append(&mut parse_dpkg_md5sums(&data, package)),
+            Err(e) => error!("Cannot read {}: {}", md5sums_path.display(), e),
+        }
+    }
+    Ok(files)
+}
+
+pub fn get_rpm_digest_algorithm(digest_algorithm: &str) -> Option<HashAlgorithm> {
+    // These are the OpenPGP hash algorithm IDs. Packages from before the tag existed used md5.
+    match digest_algorithm {
+        "1" | "(none)" | "" => Some(HashAlgorithm::Md5),
+        "2" => Some(HashAlgorithm::Sha1),
+        "8" => Some(HashAlgorithm::Sha256),
+        "10" => Some(HashAlgorithm::Sha512),
+        _ => None,
+    }
+}
+
+pub fn parse_rpm_files(data: &str) -> Vec<(PathBuf, PackageFile)> {
+    data.lines()
+        .filter_map(|line| {
+            let fields: Vec<&str> = line.split('\t').collect();
+            let [path, hash, flags, digest_algorithm, package] = fields[..] else {
+                return None;
+            };
+            // Directories, symlinks and ghost files have no digest
+            let algorithm = get_rpm_digest_algorithm(digest_algorithm)?;
+            let package_file = get_package_file(package, hash, algorithm, flags.contains('c'))?;
+            Some((PathBuf::from(path), package_file))
+        })
+        .collect()
+}
+
+pub fn query_rpm_files(rpm_program: &str) -> Result<Vec<(PathBuf, PackageFile)>, String> {
+    // Reading the rpm database directly means handling BerkeleyDB, ndb and sqlite depending on
+    // the distro. rpm itself already knows how.
+    let output = Command::new(rpm_program)
+        .args(["-qa", "--queryformat", RPM_QUERY_FORMAT])
+        .output()
+        .map_err(|e| format!("Cannot run rpm: {}", e))?;
+    if !output.status.success() {
+        return Err(format!(
+            "rpm query failed: {}",
+            String::from_utf8_lossy(&output.stderr).trim()
+        ));
+    }
+    Ok(parse_rpm_files(&String::from_utf8_lossy(&output.stdout)))
+}
+
+pub fn load_package_database(scan_settings: &ScanSettings) -> PackageDatabase {
+    load_package_files(scan_settings, RPM_PROGRAM)
+}
+
+pub fn load_package_files(scan_settings: &ScanSettings, rpm_program: &str) -> PackageDatabase {
+    let mut database = PackageDatabase::default();
+    // Either package manager may be missing. Only finding neither is worth a warning.
+    let dpkg_path = Path::new(&scan_settings.dpkg_path);
+    if dpkg_path.exists() {
+        match load_dpkg_files(dpkg_path) {
+            Ok(files) => {
+                info!("Loaded {} dpkg file hash(es)", files.len());
+                database.add_files(files);
+            }
+            // The scan goes on with whatever package data is left, like rpm's below
+            Err(e) => warn!("{}: Files are not checked against dpkg packages", e),
+        }
+    }
+    // Querying rpm walks every installed package, which is slow. Without an rpm database there
+    // is nothing for it to find.
+    let rpm_path = Path::new(&scan_settings.rpm_path);
+    if rpm_path.exists() {
+        // An rpm database without the rpm binary happens on trimmed down images and containers
+        match query_rpm_files(rpm_program) {
+            Ok(files) => {
+                info!("Loaded {} rpm file hash(es)", files.len());
+                database.add_files(files);
+            }
+            Err(e) => warn!("{}: Files are not checked against rpm packages", e),
+        }
+    }
+    if database.is_empty() {
+        warn!("verify_packages is on, but no dpkg or rpm package data was found: Package verification skipped");
+    }
+    database
+}
+
+pub fn add_package_algorithms(scan_settings: &mut ScanSettings, database: &PackageDatabase) {
+    // Files are checked against the hashes the scan read, so the package managers' algorithms
+    // have to be among them. Hashing the file a second time just for this would double the reads
+    // and could check different content than the scan recorded.
+    for algorithm in database.get_algorithms() {
+        let file_hashes = scan_settings
+            .file_scan_settings
+            .iter_mut()
+            .map(|file_scan_setting| &mut file_scan_setting.file_hashes)
+            .filter(|file_hashes| !file_hashes.is_enabled(algorithm));
+        let mut enabled = 0;
+        for file_hashes in file_hashes {
+            file_hashes.set_algorithm(algorithm, true);
+            enabled += 1;
+        }
+        if enabled > 0 {
+            info!(
+                "Enabled {} hashing for {} file scan setting(s) this run to verify package \
+                files: osfig_settings.json is not changed",
+                algorithm, enabled
+            );
+        }
+    }
+}
+
+pub fn get_package_check(
+    result: &FileScanResult,
+    database: &PackageDatabase,
+) -> Option<PackageCheck> {
+    // Only regular files that were read carry a hash to compare. Package managers don't hash
+    // directories or symlinks.
+    if !result.exists || !result.is_file || result.is_symbolic_link || is_unreadable(&result.errors)
+    {
+        return None;
+    }
+    let Some(package_file) = database.get(&result.path) else {
+        return Some(PackageCheck {
+            status: PackageStatus::Unowned,
+            package: "".to_string(),
+            algorithm: None,
+            expected_hash: "".to_string(),
+            conffile: false,
+        });
+    };
+    // add_package_algorithms makes sure the scan hashed with the package's algorithm
+    let Some(hash) = result
+        .hashes
+        .get(&package_file.algorithm)
+        .filter(|hash| !hash.is_empty())
+    else {
+        warn!(
+            "Cannot verify {} against package {}: No {} hash was collected",
+            result.path.display(),
+            package_file.package,
+            package_file.algorithm
+        );
+        return None;
+    };
+    let status = if hash.eq_ignore_ascii_case(&package_file.hash) {
+        PackageStatus::Intact
+    } else {
+        PackageStatus::Modified
+    };
+    Some(PackageCheck {
+        status,
+        package: package_file.package.clone(),
+        algorithm: Some(package_file.algorithm),
+        expected_hash: package_file.hash.clone(),
+        conffile: package_file.conffile,
+    })
+}
+
+pub fn verify_packages(results: &mut [FileScanResult], database: &PackageDatabase) {
+    // With no package data at all every file would look unowned, which says nothing
+    if database.is_empty() {
+        return;
+    }
+    for result in results.iter_mut() {
+        result.package = get_package_check(result, database);
+        if let Some(package) = &result.package {
+            if package.status == PackageStatus::Modified && !package.conffile {
+                warn!(
+                    "{} does not match the {} hash shipped by package {}",
+                    result.path.display(),
+                    package.algorithm.map(|a| a.name()).unwrap_or_default(),
+                    package.package
+                );
+            }
+        }
+    }
+}
@@ -1,30 +1,60 @@
+use crate::changes::{get_change_type, get_changed_fields, ChangeType, FieldChange};
+use crate::scan_settings::Suppression;
+use crate::severity::Severity;
 use serde::{Deserialize, Serialize};
-use std::ops::Index;
-use winreg::enums::{
-    HKEY_CLASSES_ROOT, HKEY_CURRENT_CONFIG, HKEY_CURRENT_USER, HKEY_CURRENT_USER_LOCAL_SETTINGS,
-    HKEY_DYN_DATA, HKEY_LOCAL_MACHINE, HKEY_PERFORMANCE_DATA, HKEY_PERFORMANCE_NLSTEXT,
-    HKEY_PERFORMANCE_TEXT, HKEY_USERS,
+use std::collections::{HashMap, HashSet};
+#[cfg(windows)]
+use {
+    std::ops::Index,
+    winreg::enums::{
+        HKEY_CLASSES_ROOT, HKEY_CURRENT_CONFIG, HKEY_CURRENT_USER,
+        HKEY_CURRENT_USER_LOCAL_SETTINGS, HKEY_DYN_DATA, HKEY_LOCAL_MACHINE, HKEY_PERFORMANCE_DATA,
+        HKEY_PERFORMANCE_NLSTEXT, HKEY_PERFORMANCE_TEXT, HKEY_USERS,
+    },
+    winreg::{RegKey, HKEY},
 };
-use winreg::{RegKey, HKEY};
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Default, Clone, Serialize, Deserialize)]
 pub struct RegistryResult {
-    path: String,
-    data: RegistryData,
+    pub(crate) path: String,
+    pub(crate) data: RegistryData,
+    #[serde(default)]
+    pub(crate) is_modified: bool,
+    #[serde(default)]
+    pub(crate) change_type: ChangeType,
+    #[serde(default)]
+    pub(crate) changes: Vec<FieldChange>,
+    #[serde(default)]
+    pub(crate) changed_fields: Vec<String>,
+    #[serde(default)]
+    pub(crate) severity: Severity,
+    #[serde(default)]
+    pub(crate) suppressed: bool,
+    #[serde(default)]
+    pub(crate) suppressed_by: Option<Suppression>,
 }
 
+#[allow(unused)]
 impl RegistryResult {
     pub fn add_data(&mut self, new_data: RegistryData) {
         self.data.add_data(new_data)
     }
+
+    pub(crate) fn set_changes(&mut self, changes: Vec<FieldChange>) {
+        self.changed_fields = get_changed_fields(&changes);
+        self.change_type = get_change_type(&changes);
+        self.is_modified = !changes.is_empty();
+        self.changes = changes;
+    }
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Default, Clone, Serialize, Deserialize)]
 pub struct RegistryData {
-    keys: Vec<String>,
-    values: Vec<String>,
+    pub(crate) keys: Vec<String>,
+    pub(crate) values: Vec<String>,
 }
 
+#[allow(unused)]
 impl RegistryData {
     pub fn add_data(&mut self, new_data: RegistryData) {
         for key in new_data.keys {
@@ -176,6 +206,7 @@ pub fn scan_reg_key(full_registry_path: &String) -> RegistryResult {
     RegistryResult {
         path: result_path.to_string(),
         data: registry_data,
+        ..Default::default()
     }
 }
 
@@ -190,6 +221,7 @@ pub fn scan_reg_keys(path_list: &Vec<String>) -> Vec<RegistryResult> {
                 keys: vec![],
                 values: vec![],
             },
+            ..Default::default()
         };
 
         registry_results = add_registry_result(registry_results, scan_reg_key(path));
@@ -198,6 +230,7 @@ pub fn scan_reg_keys(path_list: &Vec<String>) -> Vec<RegistryResult> {
     registry_results
 }
 
+#[allow(unused)]
 pub fn add_registry_result(
     mut results: Vec<RegistryResult>,
     new_result: RegistryResult,
@@ -209,9 +242,11 @@ pub fn add_registry_result(
     }
     results
 }
-fn find_registry_result(results: &Vec<RegistryResult>, path: &str) -> Option<usize> {
+#[allow(unused)]
+fn find_registry_result(results: &[RegistryResult], path: &str) -> Option<usize> {
     results.iter().position(|result| result.path == path)
 }
+#[allow(unused)]
 fn update_registry_result(
     mut results: Vec<RegistryResult>,
     index: usize,
@@ -222,3 +257,105 @@ fn update_registry_result(
     results.push(existing_result);
     results
 }
+
+pub fn compare_registry_scans(
+    last_scan: &[RegistryResult],
+    this_scan: Vec<RegistryResult>,
+) -> Vec<RegistryResult> {
+    // This mirrors what scan_files does for paths: compare each key with its prior entry, then
+    // report any prior key that wasn't seen this time as deleted. Keys that were already missing
+    // last time have been reported before, so they aren't indexed at all.
+    let last_scan_index: HashMap<&str, &RegistryResult> = last_scan
+        .iter()
+        .filter(|prior_result| prior_result.change_type != ChangeType::Deleted)
+        .map(|prior_result| (prior_result.path.as_str(), prior_result))
+        .collect();
+    let mut results: Vec<RegistryResult> = Vec::with_capacity(this_scan.len());
+    for mut result in this_scan {
+        let changes = match last_scan_index.get(result.path.as_str()) {
+            Some(prior_result) => compare_registry_results(prior_result, &result),
+            // Nothing to compare against on the first scan
+            None if last_scan.is_empty() => vec![],
+            None => vec![FieldChange::new("exists", false, true)],
+        };
+        result.set_changes(changes);
+        results.push(result);
+    }
+
+    let scanned_paths: HashSet<String> = results.iter().map(|result| result.path.clone()).collect();
+    for prior_result in last_scan {
+        if prior_result.change_type == ChangeType::Deleted
+            || scanned_paths.contains(&prior_result.path)
+        {
+            continue;
+        }
+        let mut deleted_result = RegistryResult {
+            path: prior_result.path.clone(),
+            ..Default::default()
+        };
+        deleted_result.set_changes(vec![FieldChange::new("exists", true, false)]);
+        results.push(deleted_result);
+    }
+
+    results
+}
+
+pub fn compare_registry_results(
+    last_scan: &RegistryResult,
+    this_scan: &RegistryResult,
+) -> Vec<FieldChange> {
+    let mut changes: Vec<FieldChange> = Vec::new();
+
+    // Subkeys only have a name, so they can only appear or disappear
+    let old_keys: HashSet<&String> = last_scan.data.keys.iter().collect();
+    let new_keys: HashSet<&String> = this_scan.data.keys.iter().collect();
+    for key in &last_scan.data.keys {
+        if !new_keys.contains(key) {
+            changes.push(FieldChange::new("keys", key.as_str(), ""));
+        }
+    }
+    for key in &this_scan.data.keys {
+        if !old_keys.contains(key) {
+            changes.push(FieldChange::new("keys", "", key.as_str()));
+        }
+    }
+
+    // Values are stored as "name = data". Matching them by name lets new data on an existing
+    // value show up as one modification instead of a removal plus an addition.
+    let old_values: HashMap<&str, &String> = get_values_by_name(&last_scan.data.values);
+    let new_values: HashMap<&str, &String> = get_values_by_name(&this_scan.data.values);
+    for value in &last_scan.data.values {
+        match new_values.get(get_value_name(value)) {
+            Some(new_value) if *new_value != value => changes.push(FieldChange::new(
+                "values",
+                value.as_str(),
+                new_value.as_str(),
+            )),
+            Some(_) => {}
+            None => changes.push(FieldChange::new("values", value.as_str(), "")),
+        }
+    }
+    for value in &this_scan.data.values {
+        if !old_values.contains_key(get_value_name(value)) {
+            changes.push(FieldChange::new("values", "", value.as_str()));
+        }
+    }
+
+    changes
+}
+
+fn get_values_by_name(values: &[String]) -> HashMap<&str, &String> {
+    // The first value with a name wins, the same as a linear search from the front would find
+    let mut values_by_name: HashMap<&str, &String> = HashMap::with_capacity(values.len());
+    for value in values {
+        values_by_name.entry(get_value_name(value)).or_insert(value);
+    }
+    values_by_name
+}
+
+fn get_value_name(value: &str) -> &str {
+    match value.split_once(" = ") {
+        Some((name, _)) => name,
+        None => value,
+    }
+}
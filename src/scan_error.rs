@@ -0,0 +1,45 @@
+use serde::{Deserialize, Serialize};
+use std::io;
+
+#[allow(unused)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ScanOperation {
+    Metadata,
+    Hash,
+    Content,
+}
+
+#[allow(unused)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ScanError {
+    pub(crate) operation: ScanOperation,
+    // The io::ErrorKind name, e.g. PermissionDenied or NotFound
+    pub(crate) kind: String,
+    pub(crate) message: String,
+}
+
+impl ScanError {
+    pub fn new(operation: ScanOperation, error: &io::Error) -> Self {
+        Self {
+            operation,
+            kind: format!("{:?}", error.kind()),
+            message: error.to_string(),
+        }
+    }
+
+    pub fn is_unreadable(&self) -> bool {
+        // Contents that aren't UTF-8 are skipped on purpose. The file itself was still read.
+        self.kind != format!("{:?}", io::ErrorKind::InvalidData)
+    }
+}
+
+pub fn is_unreadable(errors: &[ScanError]) -> bool {
+    errors.iter().any(|error| error.is_unreadable())
+}
+
+pub fn has_metadata(errors: &[ScanError]) -> bool {
+    !errors
+        .iter()
+        .any(|error| error.operation == ScanOperation::Metadata)
+}
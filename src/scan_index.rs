@@ -0,0 +1,65 @@
+use crate::file::FileScanResult;
+use crate::hashing::HashAlgorithm;
+use std::collections::HashMap;
+use std::path::Path;
+
+// Looking up a prior result by walking the whole prior scan for every scanned path is quadratic,
+// which costs more than the hashing itself on hosts with a few hundred thousand files. The index
+// is built once per run and borrows the prior results instead of copying them.
+pub struct ScanIndex<'a> {
+    entries: &'a Vec<FileScanResult>,
+    by_path: HashMap<&'a Path, usize>,
+    by_hash: HashMap<&'a str, Vec<usize>>,
+}
+
+impl<'a> ScanIndex<'a> {
+    pub fn new(entries: &'a Vec<FileScanResult>) -> Self {
+        let mut by_path: HashMap<&'a Path, usize> = HashMap::with_capacity(entries.len());
+        let mut by_hash: HashMap<&'a str, Vec<usize>> = HashMap::new();
+
+        for (index, entry) in entries.iter().enumerate() {
+            // A path should only be in the results once, but if it isn't, the first entry is the
+            // one that gets compared.
+            by_path.entry(entry.path.as_path()).or_insert(index);
+
+            for hash in HashAlgorithm::CONTENT_ALGORITHMS.map(|algorithm| entry.get_hash(algorithm))
+            {
+                if !hash.is_empty() {
+                    by_hash.entry(hash).or_default().push(index);
+                }
+            }
+        }
+
+        Self {
+            entries,
+            by_path,
+            by_hash,
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    pub fn get(&self, path: &Path) -> Option<&'a FileScanResult> {
+        self.by_path.get(path).map(|index| &self.entries[*index])
+    }
+
+    pub fn get_by_hash(&self, hash: &str) -> Vec<&'a FileScanResult> {
+        match self.by_hash.get(hash) {
+            Some(indexes) => indexes.iter().map(|index| &self.entries[*index]).collect(),
+            None => Vec::new(),
+        }
+    }
+
+    pub fn get_by_content(&self, result: &FileScanResult) -> Vec<&'a FileScanResult> {
+        // Prefer sha256 when it's available since that is what most users enable
+        for algorithm in HashAlgorithm::CONTENT_ALGORITHMS {
+            let hash = result.get_hash(algorithm);
+            if !hash.is_empty() {
+                return self.get_by_hash(hash);
+            }
+        }
+        Vec::new()
+    }
+}
@@ -1,11 +1,67 @@
+use crate::changes::ChangeType;
+use crate::hashing::HashAlgorithm;
+use crate::merkle::DirectoryHashMode;
+use crate::severity::Severity;
+use log::warn;
 use serde::{Deserialize, Serialize};
 
 #[allow(unused)]
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Default, Clone, Serialize, Deserialize)]
 pub struct FileHashes {
     pub(crate) md5: bool,
+    #[serde(default)]
+    pub(crate) sha1: bool,
     pub(crate) sha256: bool,
+    #[serde(default)]
+    pub(crate) sha512: bool,
+    #[serde(default)]
+    pub(crate) sha3_256: bool,
     pub(crate) blake2s: bool,
+    #[serde(default)]
+    pub(crate) blake3: bool,
+    #[serde(default)]
+    pub(crate) xxh3: bool,
+    #[serde(default)]
+    pub(crate) ssdeep: bool,
+}
+
+#[allow(unused)]
+impl FileHashes {
+    pub fn is_enabled(&self, algorithm: HashAlgorithm) -> bool {
+        match algorithm {
+            HashAlgorithm::Md5 => self.md5,
+            HashAlgorithm::Sha1 => self.sha1,
+            HashAlgorithm::Sha256 => self.sha256,
+            HashAlgorithm::Sha512 => self.sha512,
+            HashAlgorithm::Sha3_256 => self.sha3_256,
+            HashAlgorithm::Blake2s => self.blake2s,
+            HashAlgorithm::Blake3 => self.blake3,
+            HashAlgorithm::Xxh3 => self.xxh3,
+            HashAlgorithm::Ssdeep => self.ssdeep,
+        }
+    }
+
+    pub fn set_algorithm(&mut self, algorithm: HashAlgorithm, is_enabled: bool) {
+        let setting = match algorithm {
+            HashAlgorithm::Md5 => &mut self.md5,
+            HashAlgorithm::Sha1 => &mut self.sha1,
+            HashAlgorithm::Sha256 => &mut self.sha256,
+            HashAlgorithm::Sha512 => &mut self.sha512,
+            HashAlgorithm::Sha3_256 => &mut self.sha3_256,
+            HashAlgorithm::Blake2s => &mut self.blake2s,
+            HashAlgorithm::Blake3 => &mut self.blake3,
+            HashAlgorithm::Xxh3 => &mut self.xxh3,
+            HashAlgorithm::Ssdeep => &mut self.ssdeep,
+        };
+        *setting = is_enabled;
+    }
+
+    pub fn get_algorithms(&self) -> Vec<HashAlgorithm> {
+        HashAlgorithm::ALL
+            .into_iter()
+            .filter(|algorithm| self.is_enabled(*algorithm))
+            .collect()
+    }
 }
 
 #[allow(unused)]
@@ -18,6 +74,66 @@ pub struct FileScanSetting {
     pub(crate) file_sacl: bool,
     pub(crate) file_content: bool,
     pub(crate) file_read_buffer_size: u64,
+    #[serde(default = "get_default_content_diff_context")]
+    pub(crate) file_content_diff_context: usize,
+    #[serde(default = "get_default_change_rule_name")]
+    pub(crate) change_rule: String,
+    #[serde(default)]
+    pub(crate) file_binary_analysis: bool,
+    #[serde(default = "get_default_binary_block_size")]
+    pub(crate) file_binary_block_size: u64,
+    #[serde(default)]
+    pub(crate) directory_hash: DirectoryHashMode,
+    #[serde(default)]
+    pub(crate) file_fast_mode: bool,
+    #[serde(default)]
+    pub(crate) file_block_hash_threshold: u64,
+    #[serde(default)]
+    pub(crate) file_mmap: bool,
+    #[serde(default)]
+    pub(crate) file_mmap_threshold: u64,
+}
+
+#[allow(unused)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ChangeRule {
+    pub(crate) name: String,
+    pub(crate) hashes: bool,
+    pub(crate) size: bool,
+    pub(crate) allow_size_growth: bool,
+    pub(crate) attributes: bool,
+    pub(crate) owner: bool,
+    pub(crate) symbolic_link: bool,
+    pub(crate) modified_time: bool,
+    pub(crate) creation_time: bool,
+    pub(crate) acl: bool,
+}
+
+#[allow(unused)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Suppression {
+    pub(crate) path_pattern: String,
+    #[serde(default)]
+    pub(crate) change_types: Vec<ChangeType>,
+    #[serde(default)]
+    pub(crate) fields: Vec<String>,
+    #[serde(default)]
+    pub(crate) hash: String,
+    pub(crate) expires: String,
+    pub(crate) justification: String,
+}
+
+#[allow(unused)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SeverityRule {
+    pub(crate) path_pattern: String,
+    #[serde(default)]
+    pub(crate) change_types: Vec<ChangeType>,
+    #[serde(default)]
+    pub(crate) fields: Vec<String>,
+    #[serde(default)]
+    pub(crate) attribute_bits_added: u32,
+    pub(crate) severity: Severity,
 }
 
 #[allow(unused)]
@@ -26,8 +142,196 @@ pub struct ScanSettings {
     pub(crate) scan_files: bool,
     pub(crate) file_scan_settings: Vec<FileScanSetting>,
     pub(crate) file_scan_delay: u16,
+    #[serde(default = "get_default_file_scan_workers")]
+    pub(crate) file_scan_workers: u16,
     pub(crate) scan_registry: bool,
     pub(crate) registry_patterns: Vec<String>,
+    #[serde(default = "get_default_change_rules")]
+    pub(crate) change_rules: Vec<ChangeRule>,
+    #[serde(default)]
+    pub(crate) suppressions: Vec<Suppression>,
+    #[serde(default = "get_default_severity_rules")]
+    pub(crate) severity_rules: Vec<SeverityRule>,
+    #[serde(default = "get_default_full_rehash_runs")]
+    pub(crate) full_rehash_runs: u32,
+    #[serde(default = "get_default_full_rehash_days")]
+    pub(crate) full_rehash_days: u32,
+    #[serde(default)]
+    pub(crate) known_good_severity: Option<Severity>,
+    #[serde(default)]
+    pub(crate) ioc_feeds: Vec<String>,
+    #[serde(default)]
+    pub(crate) verify_packages: bool,
+    #[serde(default = "get_default_dpkg_path")]
+    pub(crate) dpkg_path: String,
+    #[serde(default = "get_default_rpm_path")]
+    pub(crate) rpm_path: String,
+}
+
+// One worker scans the same way OSFIG always has. More workers are opt in, since they multiply
+// the read buffer memory and the disk load.
+pub const DEFAULT_FILE_SCAN_WORKERS: u16 = 1;
+pub const MAX_FILE_SCAN_WORKERS: u16 = 64;
+
+pub fn get_default_file_scan_workers() -> u16 {
+    DEFAULT_FILE_SCAN_WORKERS
+}
+
+pub const DEFAULT_CONTENT_DIFF_CONTEXT: usize = 3;
+
+pub fn get_default_content_diff_context() -> usize {
+    DEFAULT_CONTENT_DIFF_CONTEXT
+}
+
+pub const DEFAULT_BINARY_BLOCK_SIZE: u64 = 4_096;
+
+pub fn get_default_binary_block_size() -> u64 {
+    DEFAULT_BINARY_BLOCK_SIZE
+}
+
+// Fast mode trusts hashes from the last scan, so every so often everything is read again to
+// catch changes that put the timestamps back
+pub const DEFAULT_FULL_REHASH_RUNS: u32 = 24;
+pub const DEFAULT_FULL_REHASH_DAYS: u32 = 7;
+
+pub fn get_default_full_rehash_runs() -> u32 {
+    DEFAULT_FULL_REHASH_RUNS
+}
+
+pub fn get_default_full_rehash_days() -> u32 {
+    DEFAULT_FULL_REHASH_DAYS
+}
+
+pub const DEFAULT_DPKG_PATH: &str = "/var/lib/dpkg";
+
+pub fn get_default_dpkg_path() -> String {
+    DEFAULT_DPKG_PATH.to_string()
+}
+
+pub const DEFAULT_RPM_PATH: &str = "/var/lib/rpm";
+
+pub fn get_default_rpm_path() -> String {
+    DEFAULT_RPM_PATH.to_string()
+}
+
+const DEFAULT_CHANGE_RULE_NAME: &str = "all";
+
+pub fn get_default_change_rule_name() -> String {
+    DEFAULT_CHANGE_RULE_NAME.to_string()
+}
+
+pub fn get_default_change_rules() -> Vec<ChangeRule> {
+    vec![
+        // Any detectable change is reported
+        ChangeRule {
+            name: DEFAULT_CHANGE_RULE_NAME.to_string(),
+            hashes: true,
+            size: true,
+            allow_size_growth: false,
+            attributes: true,
+            owner: true,
+            symbolic_link: true,
+            modified_time: true,
+            creation_time: true,
+            acl: true,
+        },
+        // Content and permissions matter, timestamps are routinely touched by package managers
+        ChangeRule {
+            name: "binaries".to_string(),
+            hashes: true,
+            size: true,
+            allow_size_growth: false,
+            attributes: true,
+            owner: true,
+            symbolic_link: true,
+            modified_time: false,
+            creation_time: false,
+            acl: true,
+        },
+        // Logs are expected to grow, but should never shrink or change hands
+        ChangeRule {
+            name: "logs".to_string(),
+            hashes: false,
+            size: true,
+            allow_size_growth: true,
+            attributes: true,
+            owner: true,
+            symbolic_link: true,
+            modified_time: false,
+            creation_time: false,
+            acl: true,
+        },
+    ]
+}
+
+const LINUX_BINARY_PATTERNS: [&str; 6] = [
+    "/bin/*",
+    "/sbin/*",
+    "/usr/bin/*",
+    "/usr/sbin/*",
+    "/usr/local/bin/*",
+    "/usr/local/sbin/*",
+];
+
+pub fn get_default_severity_rules() -> Vec<SeverityRule> {
+    // setuid or setgid showing up on a Linux file is a classic persistence trick. Windows paths
+    // never start with "/", so the file attribute bits that share these values aren't caught.
+    let mut severity_rules = vec![SeverityRule {
+        path_pattern: "/*".to_string(),
+        change_types: vec![],
+        fields: vec!["attributes".to_string()],
+        attribute_bits_added: 0o6000,
+        severity: Severity::Critical,
+    }];
+
+    // Replaced or new binaries are worth a closer look than a config edit
+    for binary_pattern in LINUX_BINARY_PATTERNS {
+        severity_rules.push(SeverityRule {
+            path_pattern: binary_pattern.to_string(),
+            change_types: vec![],
+            fields: ["exists", "path"]
+                .into_iter()
+                .chain(HashAlgorithm::ALL.iter().map(|algorithm| algorithm.name()))
+                .map(|field| field.to_string())
+                .collect(),
+            attribute_bits_added: 0,
+            severity: Severity::High,
+        });
+    }
+
+    // Timestamps on their own get touched by all sorts of routine maintenance
+    severity_rules.push(SeverityRule {
+        path_pattern: "*".to_string(),
+        change_types: vec![],
+        fields: vec!["modified_time".to_string(), "creation_time".to_string()],
+        attribute_bits_added: 0,
+        severity: Severity::Low,
+    });
+
+    severity_rules
+}
+
+pub fn get_change_rule(scan_settings: &ScanSettings, name: &str) -> ChangeRule {
+    for change_rule in &scan_settings.change_rules {
+        if change_rule.name == name {
+            return change_rule.clone();
+        }
+    }
+    for change_rule in get_default_change_rules() {
+        if change_rule.name == name {
+            return change_rule;
+        }
+    }
+
+    warn!(
+        "Unknown change_rule {}: Falling back to {}",
+        name, DEFAULT_CHANGE_RULE_NAME
+    );
+    get_default_change_rule()
+}
+
+pub fn get_default_change_rule() -> ChangeRule {
+    get_default_change_rules().remove(0)
 }
 
 pub fn get_default_scan_settings() -> ScanSettings {
@@ -35,8 +339,19 @@ pub fn get_default_scan_settings() -> ScanSettings {
         scan_files: true,
         file_scan_settings: Vec::new(),
         file_scan_delay: 0,
+        file_scan_workers: DEFAULT_FILE_SCAN_WORKERS,
         scan_registry: true,
         registry_patterns: get_default_registry_paths(),
+        change_rules: get_default_change_rules(),
+        suppressions: Vec::new(),
+        severity_rules: get_default_severity_rules(),
+        full_rehash_runs: DEFAULT_FULL_REHASH_RUNS,
+        full_rehash_days: DEFAULT_FULL_REHASH_DAYS,
+        known_good_severity: None,
+        ioc_feeds: Vec::new(),
+        verify_packages: false,
+        dpkg_path: DEFAULT_DPKG_PATH.to_string(),
+        rpm_path: DEFAULT_RPM_PATH.to_string(),
     };
 
     scan_settings.file_scan_settings.push(FileScanSetting {
@@ -45,12 +360,21 @@ pub fn get_default_scan_settings() -> ScanSettings {
         file_hashes: FileHashes {
             md5: true,
             sha256: true,
-            blake2s: false,
+            ..Default::default()
         },
         file_dacl: true,
         file_sacl: false,
         file_content: false,
         file_read_buffer_size: crate::osfig_state::DEFAULT_FILE_READ_BUFFER_SIZE,
+        file_content_diff_context: DEFAULT_CONTENT_DIFF_CONTEXT,
+        change_rule: get_default_change_rule_name(),
+        file_binary_analysis: false,
+        file_binary_block_size: DEFAULT_BINARY_BLOCK_SIZE,
+        directory_hash: DirectoryHashMode::Off,
+        file_fast_mode: false,
+        file_block_hash_threshold: 0,
+        file_mmap: false,
+        file_mmap_threshold: 0,
     });
 
     scan_settings.file_scan_settings.push(FileScanSetting {
@@ -59,12 +383,21 @@ pub fn get_default_scan_settings() -> ScanSettings {
         file_hashes: FileHashes {
             md5: true,
             sha256: true,
-            blake2s: false,
+            ..Default::default()
         },
         file_dacl: false,
         file_sacl: false,
         file_content: false,
         file_read_buffer_size: crate::osfig_state::DEFAULT_FILE_READ_BUFFER_SIZE,
+        file_content_diff_context: DEFAULT_CONTENT_DIFF_CONTEXT,
+        change_rule: get_default_change_rule_name(),
+        file_binary_analysis: false,
+        file_binary_block_size: DEFAULT_BINARY_BLOCK_SIZE,
+        directory_hash: DirectoryHashMode::Off,
+        file_fast_mode: false,
+        file_block_hash_threshold: 0,
+        file_mmap: false,
+        file_mmap_threshold: 0,
     });
 
     scan_settings
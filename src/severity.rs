@@ -0,0 +1,106 @@
+use crate::changes::{ChangeType, FieldChange};
+use crate::file::{path_matches_pattern, FileScanResult};
+use crate::registry::RegistryResult;
+use crate::scan_settings::SeverityRule;
+use serde::{Deserialize, Serialize};
+use std::path::Path;
+
+#[allow(unused)]
+#[derive(
+    Debug, Default, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize,
+)]
+#[serde(rename_all = "lowercase")]
+pub enum Severity {
+    #[default]
+    None,
+    Low,
+    Medium,
+    High,
+    Critical,
+}
+
+// A change that no rule speaks to still needs someone to look at it
+pub const DEFAULT_SEVERITY: Severity = Severity::Medium;
+
+pub fn classify_changes(results: &mut [FileScanResult], severity_rules: &[SeverityRule]) {
+    for result in results.iter_mut() {
+        for change in result.changes.iter_mut() {
+            change.severity =
+                get_change_severity(&result.path, result.change_type, change, severity_rules);
+        }
+        result.severity = get_highest_severity(&result.changes);
+    }
+}
+
+pub fn classify_registry_changes(results: &mut [RegistryResult], severity_rules: &[SeverityRule]) {
+    // Rules match registry paths the same way they match file paths
+    for result in results.iter_mut() {
+        let path = Path::new(&result.path);
+        for change in result.changes.iter_mut() {
+            change.severity = get_change_severity(path, result.change_type, change, severity_rules);
+        }
+        result.severity = get_highest_severity(&result.changes);
+    }
+}
+
+pub fn get_change_severity(
+    path: &Path,
+    change_type: ChangeType,
+    change: &FieldChange,
+    severity_rules: &[SeverityRule],
+) -> Severity {
+    // Rules are checked in order and the first match wins, so specific rules go before broad ones
+    for severity_rule in severity_rules {
+        if is_severity_rule_match(path, change_type, change, severity_rule) {
+            return severity_rule.severity;
+        }
+    }
+    DEFAULT_SEVERITY
+}
+
+pub fn is_severity_rule_match(
+    path: &Path,
+    change_type: ChangeType,
+    change: &FieldChange,
+    severity_rule: &SeverityRule,
+) -> bool {
+    if !path_matches_pattern(path, &severity_rule.path_pattern) {
+        return false;
+    }
+    if !severity_rule.change_types.is_empty() && !severity_rule.change_types.contains(&change_type)
+    {
+        return false;
+    }
+    if !severity_rule.fields.is_empty() && !severity_rule.fields.contains(&change.field) {
+        return false;
+    }
+    if severity_rule.attribute_bits_added != 0 {
+        return is_attribute_bit_added(change, severity_rule.attribute_bits_added);
+    }
+    true
+}
+
+pub fn is_attribute_bit_added(change: &FieldChange, attribute_bits: u32) -> bool {
+    // Only a bit that wasn't set before counts. A setuid binary that gets rebuilt by the package
+    // manager isn't the same thing as setuid showing up on a binary that never had it.
+    if change.field != "attributes" {
+        return false;
+    }
+    match (
+        change.old_value.parse::<u32>(),
+        change.new_value.parse::<u32>(),
+    ) {
+        (Ok(old_attributes), Ok(new_attributes)) => {
+            new_attributes & !old_attributes & attribute_bits != 0
+        }
+        _ => false,
+    }
+}
+
+pub fn get_highest_severity(changes: &[FieldChange]) -> Severity {
+    changes
+        .iter()
+        .map(|change| change.severity)
+        .max()
+        .unwrap_or(Severity::None)
+}
@@ -0,0 +1,157 @@
+use crate::changes::ChangeType;
+use crate::file::{path_matches_pattern, FileScanResult};
+use crate::registry::RegistryResult;
+use crate::scan_settings::Suppression;
+use chrono::{DateTime, Utc};
+use log::{info, warn};
+use std::path::Path;
+
+pub fn apply_suppressions(
+    results: &mut [FileScanResult],
+    suppressions: &[Suppression],
+    now: DateTime<Utc>,
+) -> Vec<Suppression> {
+    let expired_suppressions = get_expired_suppressions(suppressions, now);
+    for suppression in &expired_suppressions {
+        warn!(
+            "Suppression for {} expired at {}: Changes are reported again. Justification was: {}",
+            suppression.path_pattern, suppression.expires, suppression.justification
+        );
+    }
+
+    let active_suppressions = get_active_suppressions(suppressions, &expired_suppressions);
+
+    // Suppressed changes stay in the results so they can still be reviewed later. They are only
+    // marked so reports and alerting can leave them out.
+    for result in results.iter_mut() {
+        result.suppressed = false;
+        result.suppressed_by = None;
+        for suppression in &active_suppressions {
+            if is_suppression_match(result, suppression) {
+                info!(
+                    "Suppressed change to {}: {}",
+                    result.path.display(),
+                    suppression.justification
+                );
+                result.suppressed = true;
+                result.suppressed_by = Some((*suppression).clone());
+                break;
+            }
+        }
+    }
+
+    expired_suppressions
+}
+
+pub fn apply_registry_suppressions(
+    results: &mut [RegistryResult],
+    suppressions: &[Suppression],
+    now: DateTime<Utc>,
+) {
+    // Expired suppressions were already reported by apply_suppressions, so they're only skipped
+    let expired_suppressions = get_expired_suppressions(suppressions, now);
+    let active_suppressions = get_active_suppressions(suppressions, &expired_suppressions);
+    for result in results.iter_mut() {
+        result.suppressed = false;
+        result.suppressed_by = None;
+        for suppression in &active_suppressions {
+            if is_registry_suppression_match(result, suppression) {
+                info!(
+                    "Suppressed change to {}: {}",
+                    result.path, suppression.justification
+                );
+                result.suppressed = true;
+                result.suppressed_by = Some((*suppression).clone());
+                break;
+            }
+        }
+    }
+}
+
+fn get_active_suppressions<'a>(
+    suppressions: &'a [Suppression],
+    expired_suppressions: &[Suppression],
+) -> Vec<&'a Suppression> {
+    suppressions
+        .iter()
+        .filter(|suppression| !expired_suppressions.contains(suppression))
+        .collect()
+}
+
+pub fn get_expired_suppressions(
+    suppressions: &[Suppression],
+    now: DateTime<Utc>,
+) -> Vec<Suppression> {
+    suppressions
+        .iter()
+        .filter(|suppression| is_suppression_expired(suppression, now))
+        .cloned()
+        .collect()
+}
+
+pub fn is_suppression_expired(suppression: &Suppression, now: DateTime<Utc>) -> bool {
+    // A suppression without a valid expiry would never end, which is what file_ignore_patterns
+    // is for. Treat it as expired so the change keeps getting reported.
+    match DateTime::parse_from_rfc3339(&suppression.expires) {
+        Ok(expires) => expires.with_timezone(&Utc) <= now,
+        Err(e) => {
+            warn!(
+                "Invalid suppression expiry {} for {}: Error: {}",
+                suppression.expires, suppression.path_pattern, e
+            );
+            true
+        }
+    }
+}
+
+pub fn is_suppression_match(result: &FileScanResult, suppression: &Suppression) -> bool {
+    if !is_change_suppressed(
+        &result.path,
+        result.change_type,
+        &result.changed_fields,
+        suppression,
+    ) {
+        return false;
+    }
+    if !suppression.hash.is_empty() {
+        return result
+            .hashes
+            .values()
+            .any(|hash| !hash.is_empty() && hash.eq_ignore_ascii_case(&suppression.hash));
+    }
+    true
+}
+
+pub fn is_registry_suppression_match(result: &RegistryResult, suppression: &Suppression) -> bool {
+    // Registry keys have no content hash, so a suppression pinned to one never covers them
+    suppression.hash.is_empty()
+        && is_change_suppressed(
+            Path::new(&result.path),
+            result.change_type,
+            &result.changed_fields,
+            suppression,
+        )
+}
+
+fn is_change_suppressed(
+    path: &Path,
+    change_type: ChangeType,
+    changed_fields: &[String],
+    suppression: &Suppression,
+) -> bool {
+    if change_type == ChangeType::Unchanged {
+        return false;
+    }
+    if !path_matches_pattern(path, &suppression.path_pattern) {
+        return false;
+    }
+    if !suppression.change_types.is_empty() && !suppression.change_types.contains(&change_type) {
+        return false;
+    }
+    // Every changed field has to be covered. A suppression for timestamps shouldn't hide a
+    // content change that happened at the same time.
+    suppression.fields.is_empty()
+        || changed_fields
+            .iter()
+            .all(|field| suppression.fields.contains(field))
+}
@@ -1,8 +1,68 @@
+//////////////////////////////////////////////////////////////////////////////////////////////////
+///////////////////////////////////////      FIXTURES      ///////////////////////////////////////
+//////////////////////////////////////////////////////////////////////////////////////////////////
+// Result builders shared by the test modules below, so a new FileScanResult field only has to be
+// handled in one place
+#[cfg(test)]
+mod test_fixtures {
+    use crate::changes::FieldChange;
+    use crate::file::FileScanResult;
+    use crate::hashing::HashAlgorithm;
+    use std::path::Path;
+
+    pub fn get_file_result<T: AsRef<Path>>(path: T) -> FileScanResult {
+        let mut result: FileScanResult = FileScanResult::default();
+        result.set_path(path);
+        result.exists = true;
+        result.is_file = true;
+        result
+    }
+
+    pub fn get_hashed_result(path: &str, algorithm: HashAlgorithm, hash: &str) -> FileScanResult {
+        let mut result = get_file_result(path);
+        result.hashes.insert(algorithm, hash.to_string());
+        result
+    }
+
+    pub fn get_test_result(path: &str, sha256: &str) -> FileScanResult {
+        let mut result = get_file_result(path);
+        result.size = 100;
+        result
+            .hashes
+            .insert(HashAlgorithm::Sha256, sha256.to_string());
+        result
+    }
+
+    pub fn get_test_results(count: usize, sha256: &str) -> Vec<FileScanResult> {
+        (0..count)
+            .map(|index| {
+                let mut result = get_test_result(
+                    &format!("/usr/lib/test/{}", index),
+                    &format!("{}{}", sha256, index),
+                );
+                result.size = index as u64;
+                result
+            })
+            .collect()
+    }
+
+    pub fn get_changed_result(
+        path: &str,
+        sha256: &str,
+        changes: Vec<FieldChange>,
+    ) -> FileScanResult {
+        let mut result = get_test_result(path, sha256);
+        result.set_changes(changes);
+        result
+    }
+}
+
 //////////////////////////////////////////////////////////////////////////////////////////////////
 ///////////////////////////////////////       HELPERS      ///////////////////////////////////////
 //////////////////////////////////////////////////////////////////////////////////////////////////
 #[cfg(test)]
 mod helpers_tests {
+    use crate::hashing::HashAlgorithm;
     use crate::helpers::*;
     use std::any::Any;
 
@@ -46,14 +106,859 @@ mod helpers_tests {
             ("NT AUTHORITY".to_string(), "SYSTEM".to_string()).type_id()
         )
     }
+
+    const EMPTY_MD5: &str = "D41D8CD98F00B204E9800998ECF8427E";
+    const EMPTY_SHA256: &str = "E3B0C44298FC1C149AFBF4C8996FB92427AE41E4649B934CA495991B7852B855";
+
+    #[test]
+    fn test_parse_results_legacy_hashes() {
+        // Results saved before hashes moved into a map, in both the object and list layouts
+        let legacy_file = r#"{"scantime": "", "path": "/etc/hosts", "is_dir": false,
+            "is_file": true, "is_symbolic_link": false, "is_readonly": false, "exists": true,
+            "md5": "D41D8CD98F00B204E9800998ECF8427E",
+            "sha256": "E3B0C44298FC1C149AFBF4C8996FB92427AE41E4649B934CA495991B7852B855",
+            "blake2s": "", "creation_time": "",
+            "modified_time": "", "access_time": "", "size": 1, "attributes": 0, "contents": "",
+            "is_modified": false, "content_diff": ""}"#;
+        for data in [
+            format!(r#"{{"files": [{}], "registry": []}}"#, legacy_file),
+            format!("[{}]", legacy_file),
+        ] {
+            let results = parse_results(&data).unwrap();
+            assert_eq!(results.files.len(), 1);
+            assert_eq!(
+                results.files[0].hashes,
+                crate::hashing::HashValues::from([
+                    (HashAlgorithm::Md5, EMPTY_MD5.to_string()),
+                    (HashAlgorithm::Sha256, EMPTY_SHA256.to_string()),
+                ])
+            );
+        }
+    }
+
+    #[test]
+    fn test_parse_results_legacy_unreadable_hashes() {
+        // The old hasher stored this message in place of each hash when it couldn't read the file
+        let data = r#"[{"scantime": "", "path": "/etc/shadow", "is_dir": false,
+            "is_file": true, "is_symbolic_link": false, "is_readonly": false, "exists": true,
+            "md5": "Cannot open file", "sha256": "Cannot open file", "blake2s": "Cannot open file",
+            "creation_time": "", "modified_time": "", "access_time": "", "size": 1,
+            "attributes": 0, "contents": "", "is_modified": false, "content_diff": ""}]"#;
+        let results = parse_results(data).unwrap();
+        assert!(results.files[0].hashes.is_empty());
+    }
+
+    #[test]
+    fn test_parse_results_legacy_contents() {
+        // Messages the old scanner stored as contents are dropped, real contents are kept
+        for (contents, expected) in [
+            ("Cannot open file", ""),
+            ("Not valid UTF8", ""),
+            ("127.0.0.1 localhost", "127.0.0.1 localhost"),
+        ] {
+            let data = format!(
+                r#"[{{"scantime": "", "path": "/etc/hosts", "is_dir": false, "is_file": true,
+                "is_symbolic_link": false, "is_readonly": false, "exists": true, "md5": "",
+                "sha256": "", "blake2s": "", "creation_time": "", "modified_time": "",
+                "access_time": "", "size": 1, "attributes": 0, "contents": "{}",
+                "is_modified": false, "content_diff": ""}}]"#,
+                contents
+            );
+            let results = parse_results(&data).unwrap();
+            assert_eq!(results.files[0].contents, expected);
+        }
+    }
+
+    #[test]
+    fn test_split_csv_line() {
+        assert_eq!(split_csv_line("a,b,,c"), vec!["a", "b", "", "c"]);
+        // Quoted commas and doubled quotes belong to the field
+        assert_eq!(
+            split_csv_line("\"AB\",\"my, \"\"file\"\".txt\",3\r\n"),
+            vec!["AB", "my, \"file\".txt", "3"]
+        );
+    }
+}
+//////////////////////////////////////////////////////////////////////////////////////////////////
+///////////////////////////////////////    ACKNOWLEDGE     ///////////////////////////////////////
+//////////////////////////////////////////////////////////////////////////////////////////////////
+#[cfg(test)]
+mod acknowledge_tests {
+    use crate::acknowledge::*;
+    use crate::changes::{ChangeType, FieldChange};
+    use crate::file::{get_file_changes, get_latest_results, get_newest_results, FileScanResult};
+    use crate::hashing::HashAlgorithm;
+    use crate::helpers::{load_results_from_file, store_json, ScanResults};
+    use crate::osfig_state::OsfigSettings;
+    use crate::scan_index::ScanIndex;
+    use crate::scan_settings::get_default_scan_settings;
+    use crate::tests::test_fixtures::{get_changed_result, get_test_result};
+    use std::path::PathBuf;
+
+    const ACK_TEST_SCANS_DIR: &str = "./acktest_scans";
+
+    fn teardown_acknowledge_tests() {
+        let _ = std::fs::remove_dir_all(ACK_TEST_SCANS_DIR);
+    }
+
+    #[test]
+    fn test_apply_acknowledgment() {
+        let mut approved_baseline = vec![
+            get_test_result("/etc/ssh/sshd_config", "AAAA"),
+            get_test_result("/etc/shadow", "BBBB"),
+            get_test_result("/usr/sbin/sshd", "CCCC"),
+        ];
+
+        let modified = get_changed_result(
+            "/etc/ssh/sshd_config",
+            "DDDD",
+            vec![FieldChange::new("sha256", "AAAA", "DDDD")],
+        );
+        apply_acknowledgment(&mut approved_baseline, &modified);
+        assert_eq!(approved_baseline.len(), 3);
+        assert_eq!(approved_baseline[0].get_hash(HashAlgorithm::Sha256), "DDDD");
+        // The approved entry is stored without its change records
+        assert_eq!(approved_baseline[0].change_type, ChangeType::Unchanged);
+        assert!(approved_baseline[0].changes.is_empty());
+        assert!(!approved_baseline[0].is_modified);
+
+        let mut deleted = FileScanResult::default();
+        deleted.set_path("/etc/shadow");
+        deleted.set_changes(vec![FieldChange::new("exists", true, false)]);
+        apply_acknowledgment(&mut approved_baseline, &deleted);
+        assert_eq!(approved_baseline.len(), 2);
+
+        let mut moved = get_changed_result(
+            "/usr/local/sbin/sshd",
+            "CCCC",
+            vec![FieldChange::new(
+                "path",
+                "/usr/sbin/sshd",
+                "/usr/local/sbin/sshd",
+            )],
+        );
+        moved.previous_path = Some(Box::new(PathBuf::from("/usr/sbin/sshd")));
+        apply_acknowledgment(&mut approved_baseline, &moved);
+        assert_eq!(approved_baseline.len(), 2);
+        assert_eq!(
+            approved_baseline[1].path,
+            Box::new(PathBuf::from("/usr/local/sbin/sshd"))
+        );
+        assert_eq!(approved_baseline[1].previous_path, None);
+
+        let added = get_changed_result(
+            "/etc/cron.d/new",
+            "EEEE",
+            vec![FieldChange::new("exists", false, true)],
+        );
+        apply_acknowledgment(&mut approved_baseline, &added);
+        assert_eq!(approved_baseline.len(), 3);
+
+        // Nothing changed, so there is nothing to fold in
+        let unchanged = get_test_result("/etc/hosts", "FFFF");
+        apply_acknowledgment(&mut approved_baseline, &unchanged);
+        assert_eq!(approved_baseline.len(), 3);
+    }
+
+    #[test]
+    fn test_get_latest_results_outside_baseline() {
+        let test_dir = format!("{}_outside", ACK_TEST_SCANS_DIR);
+        let _ = std::fs::remove_dir_all(&test_dir);
+        let osfig_settings = OsfigSettings {
+            scan_settings: get_default_scan_settings(),
+            scan_result_path: test_dir.clone(),
+        };
+        let approved_baseline = ScanResults {
+            files: vec![get_test_result("/etc/hosts", "AAAA")],
+            ..Default::default()
+        };
+        let _ = store_json(
+            &approved_baseline,
+            get_approved_baseline_path(&osfig_settings)
+                .to_str()
+                .unwrap(),
+        );
+        // /opt/app came into scope after the baseline was made and was reported as added once
+        let newest_results = ScanResults {
+            files: vec![
+                get_changed_result(
+                    "/etc/hosts",
+                    "BBBB",
+                    vec![FieldChange::new("sha256", "AAAA", "BBBB")],
+                ),
+                get_changed_result(
+                    "/opt/app/run.sh",
+                    "CCCC",
+                    vec![FieldChange::new("exists", false, true)],
+                ),
+            ],
+            ..Default::default()
+        };
+        let _ = store_json(
+            &newest_results,
+            &format!("{}/results-1700000000.json", test_dir),
+        );
+
+        let latest_results =
+            get_latest_results(&osfig_settings, get_newest_results(&osfig_settings).files);
+        assert_eq!(latest_results.len(), 2);
+        // The unacknowledged change is still compared against the baseline
+        assert_eq!(latest_results[0].get_hash(HashAlgorithm::Sha256), "AAAA");
+
+        // So the next scan reports /opt/app/run.sh as unchanged rather than added again
+        let last_scan = ScanIndex::new(&latest_results);
+        let mut this_scan = get_test_result("/opt/app/run.sh", "CCCC");
+        this_scan.set_changes(get_file_changes(&last_scan, &this_scan));
+        assert_eq!(this_scan.change_type, ChangeType::Unchanged);
+
+        let _ = std::fs::remove_dir_all(&test_dir);
+    }
+
+    #[test]
+    fn test_acknowledge_changes() {
+        teardown_acknowledge_tests();
+        let osfig_settings = OsfigSettings {
+            scan_settings: get_default_scan_settings(),
+            scan_result_path: ACK_TEST_SCANS_DIR.to_string(),
+        };
+
+        // Nothing to acknowledge before any scan has been saved
+        let paths = vec![PathBuf::from("/etc/ssh/sshd_config")];
+        assert!(acknowledge_changes(&osfig_settings, &paths, "tester", "Testing", None).is_err());
+
+        let latest_results = ScanResults {
+            files: vec![
+                get_changed_result(
+                    "/etc/ssh/sshd_config",
+                    "DDDD",
+                    vec![FieldChange::new("sha256", "AAAA", "DDDD")],
+                ),
+                get_changed_result(
+                    "/etc/motd",
+                    "EEEE",
+                    vec![FieldChange::new("sha256", "BBBB", "EEEE")],
+                ),
+                get_test_result("/etc/hosts", "FFFF"),
+            ],
+            ..Default::default()
+        };
+        let results_file = format!("{}/results-1700000000.json", ACK_TEST_SCANS_DIR);
+        let _ = store_json(&latest_results, &results_file);
+
+        let paths = vec![
+            PathBuf::from("/etc/ssh/sshd_config"),
+            PathBuf::from("/etc/hosts"),
+        ];
+        let acknowledgments = acknowledge_changes(
+            &osfig_settings,
+            &paths,
+            "tester",
+            "Hardening",
+            Some("OPS-1"),
+        )
+        .unwrap();
+
+        // The unchanged path is skipped
+        assert_eq!(acknowledgments.len(), 1);
+        assert_eq!(acknowledgments[0].user, "tester");
+        assert_eq!(acknowledgments[0].reason, "Hardening");
+        assert_eq!(acknowledgments[0].ticket_id, Some("OPS-1".to_string()));
+        assert_eq!(acknowledgments[0].change_type, ChangeType::Modified);
+        assert_eq!(acknowledgments[0].changed_fields, vec!["sha256"]);
+        assert!(acknowledgments[0]
+            .results_file
+            .ends_with("results-1700000000.json"));
+
+        // Only the acknowledged change made it into the baseline
+        let baseline_path = get_approved_baseline_path(&osfig_settings);
+        let approved_baseline = load_results_from_file(baseline_path.to_str().unwrap()).unwrap();
+        assert_eq!(approved_baseline.files.len(), 1);
+        assert_eq!(
+            approved_baseline.files[0].get_hash(HashAlgorithm::Sha256),
+            "DDDD"
+        );
+
+        // Later scans compare against the approved baseline, and the newest results for the
+        // paths it doesn't hold
+        let latest_results =
+            get_latest_results(&osfig_settings, get_newest_results(&osfig_settings).files);
+        assert_eq!(latest_results.len(), 3);
+        assert_eq!(
+            latest_results[0].path,
+            Box::new(PathBuf::from("/etc/ssh/sshd_config"))
+        );
+
+        // The audit log is appended to, never rewritten
+        let paths = vec![PathBuf::from("/etc/motd")];
+        let _ = acknowledge_changes(&osfig_settings, &paths, "tester", "Banner update", None);
+        let log = std::fs::read_to_string(get_acknowledgment_log_path(&osfig_settings)).unwrap();
+        let log_lines: Vec<&str> = log.lines().collect();
+        assert_eq!(log_lines.len(), 2);
+        let first: Acknowledgment = serde_json::from_str(log_lines[0]).unwrap();
+        assert_eq!(first.reason, "Hardening");
+        let second: Acknowledgment = serde_json::from_str(log_lines[1]).unwrap();
+        assert_eq!(second.reason, "Banner update");
+        assert_eq!(second.ticket_id, None);
+
+        let approved_baseline = load_results_from_file(baseline_path.to_str().unwrap()).unwrap();
+        assert_eq!(approved_baseline.files.len(), 2);
+
+        teardown_acknowledge_tests();
+    }
+}
+
+//////////////////////////////////////////////////////////////////////////////////////////////////
+///////////////////////////////////////       BINARY       ///////////////////////////////////////
+//////////////////////////////////////////////////////////////////////////////////////////////////
+#[cfg(test)]
+mod binary_tests {
+    use crate::binary::*;
+    use crate::hashing::{get_file_and_block_hashes, HashAlgorithm};
+    use crate::mmap::ReadMode;
+    use crate::scan_settings::FileHashes;
+    use std::io::Cursor;
+    use std::path::Path;
+
+    const BINARY_TEST_DIR: &str = "./binarytest";
+
+    fn teardown_binary_tests() {
+        let _ = std::fs::remove_dir_all(BINARY_TEST_DIR);
+    }
+
+    fn get_block_hash(block: &[u8]) -> String {
+        hex::encode_upper(blake3::hash(block).as_bytes())
+    }
+
+    fn get_block_hashes(data: &[u8], block_size: u64) -> BlockHashList {
+        let mut block_hasher = BlockHasher::new(block_size);
+        block_hasher.update(data);
+        block_hasher.finalize()
+    }
+
+    // Smallest useful ELF64 little endian file: a null section, an 8 byte .text and the section
+    // name table, followed by the section header table.
+    fn get_test_elf(text: &[u8; 8]) -> Vec<u8> {
+        let string_table = b"\0.text\0.shstrtab\0";
+        let mut elf: Vec<u8> = vec![0; 64];
+        elf[..4].copy_from_slice(&[0x7f, b'E', b'L', b'F']);
+        elf[4] = 2;
+        elf[5] = 1;
+        elf[0x28..0x30].copy_from_slice(&96u64.to_le_bytes());
+        elf[0x3A..0x3C].copy_from_slice(&64u16.to_le_bytes());
+        elf[0x3C..0x3E].copy_from_slice(&3u16.to_le_bytes());
+        elf[0x3E..0x40].copy_from_slice(&2u16.to_le_bytes());
+        elf.extend_from_slice(text);
+        elf.extend_from_slice(string_table);
+        elf.resize(96, 0);
+
+        let mut add_section_header = |name: u32, section_type: u32, offset: u64, size: u64| {
+            let mut section_header: Vec<u8> = vec![0; 64];
+            section_header[..4].copy_from_slice(&name.to_le_bytes());
+            section_header[4..8].copy_from_slice(&section_type.to_le_bytes());
+            section_header[0x18..0x20].copy_from_slice(&offset.to_le_bytes());
+            section_header[0x20..0x28].copy_from_slice(&size.to_le_bytes());
+            elf.extend_from_slice(&section_header);
+        };
+        add_section_header(0, 0, 0, 0);
+        add_section_header(1, 1, 64, 8);
+        add_section_header(7, 3, 72, string_table.len() as u64);
+        elf
+    }
+
+    #[test]
+    fn test_get_binary_diff() {
+        let empty_summary = BinarySummary::default();
+        let mut old_data: Vec<u8> = vec![0; 40];
+        let old_blocks = get_block_hashes(&old_data, 4);
+
+        // Two neighbouring blocks, one separate block, and 2 bytes appended at the end
+        old_data[5] = 1;
+        old_data[9] = 1;
+        old_data[20] = 1;
+        old_data.extend_from_slice(&[1, 1]);
+        let new_blocks = get_block_hashes(&old_data, 4);
+
+        let binary_diff = get_binary_diff(
+            &empty_summary,
+            &empty_summary,
+            Some(&old_blocks),
+            Some(&new_blocks),
+            40,
+            42,
+        );
+        assert_eq!(binary_diff.size_delta, 2);
+        assert_eq!(
+            binary_diff.changed_ranges,
+            vec![
+                ByteRange {
+                    offset: 4,
+                    length: 8
+                },
+                ByteRange {
+                    offset: 20,
+                    length: 4
+                },
+                ByteRange {
+                    offset: 40,
+                    length: 2
+                },
+            ]
+        );
+        assert_eq!(binary_diff.changed_bytes, 14);
+        assert!((binary_diff.percent_changed - 100.0 / 3.0).abs() < 0.001);
+
+        // Mismatched block sizes can't be lined up, and neither can a scan without blocks
+        let resized_blocks = get_block_hashes(&old_data, 8);
+        for new_blocks in [Some(&resized_blocks), None] {
+            let binary_diff = get_binary_diff(
+                &empty_summary,
+                &empty_summary,
+                Some(&old_blocks),
+                new_blocks,
+                40,
+                42,
+            );
+            assert_eq!(binary_diff.changed_bytes, 42);
+            assert_eq!(binary_diff.percent_changed, 100.0);
+        }
+
+        let binary_diff = get_binary_diff(
+            &empty_summary,
+            &empty_summary,
+            Some(&old_blocks),
+            Some(&old_blocks),
+            40,
+            40,
+        );
+        assert!(binary_diff.changed_ranges.is_empty());
+        assert_eq!(binary_diff.percent_changed, 0.0);
+    }
+
+    #[test]
+    fn test_get_elf_sections() {
+        let elf = get_test_elf(b"\x55\x48\x89\xe5\xc3\x90\x90\x90");
+        let elf_sections = get_elf_sections(&mut Cursor::new(&elf)).unwrap();
+        assert_eq!(elf_sections.len(), 2);
+        assert_eq!(elf_sections[0].name, ".text");
+        assert_eq!(elf_sections[0].offset, 64);
+        assert_eq!(elf_sections[0].size, 8);
+        assert_eq!(elf_sections[1].name, ".shstrtab");
+
+        assert!(get_elf_sections(&mut Cursor::new(b"not an elf file at all".to_vec())).is_err());
+        assert!(get_elf_sections(&mut Cursor::new(&elf[..100])).is_err());
+
+        // A header claiming a huge section table is refused before anything is allocated
+        let mut huge_elf = elf.clone();
+        huge_elf[0x3A..0x3C].copy_from_slice(&u16::MAX.to_le_bytes());
+        huge_elf[0x3C..0x3E].copy_from_slice(&u16::MAX.to_le_bytes());
+        assert_eq!(
+            get_elf_sections(&mut Cursor::new(&huge_elf)),
+            Err("ELF section header table is truncated".to_string())
+        );
+        let mut wrapped_elf = elf.clone();
+        wrapped_elf[0x28..0x30].copy_from_slice(&u64::MAX.to_le_bytes());
+        assert!(get_elf_sections(&mut Cursor::new(&wrapped_elf)).is_err());
+    }
+
+    #[test]
+    fn test_get_binary_summary() {
+        teardown_binary_tests();
+        std::fs::create_dir_all(BINARY_TEST_DIR).unwrap();
+        let old_path = Path::new(BINARY_TEST_DIR).join("old.bin");
+        let new_path = Path::new(BINARY_TEST_DIR).join("new.bin");
+        let text_path = Path::new(BINARY_TEST_DIR).join("text.txt");
+        std::fs::write(&old_path, get_test_elf(b"\x55\x48\x89\xe5\xc3\x90\x90\x90")).unwrap();
+        std::fs::write(&new_path, get_test_elf(b"\x55\x48\x89\xe5\xcc\x90\x90\x90")).unwrap();
+        std::fs::write(&text_path, "plain text").unwrap();
+
+        assert!(is_binary_file(&old_path));
+        assert!(!is_binary_file(&text_path));
+        assert!(!is_binary_file(Path::new("./binarytest/missing.bin")));
+
+        let old_summary = get_binary_summary(&old_path).unwrap();
+        let new_summary = get_binary_summary(&new_path).unwrap();
+        assert_eq!(old_summary.elf_sections.len(), 2);
+
+        let old_blocks = get_block_hashes(&std::fs::read(&old_path).unwrap(), 16);
+        let new_blocks = get_block_hashes(&std::fs::read(&new_path).unwrap(), 16);
+        assert_eq!(old_blocks.hashes.len(), 18);
+        let binary_diff = get_binary_diff(
+            &old_summary,
+            &new_summary,
+            Some(&old_blocks),
+            Some(&new_blocks),
+            288,
+            288,
+        );
+        assert_eq!(
+            binary_diff.changed_ranges,
+            vec![ByteRange {
+                offset: 64,
+                length: 16
+            }]
+        );
+        assert_eq!(binary_diff.changed_sections, vec![".text".to_string()]);
+        assert!(binary_diff.added_sections.is_empty());
+        assert!(binary_diff.removed_sections.is_empty());
+
+        // A file that isn't ELF just has no sections
+        let text_summary = get_binary_summary(&text_path).unwrap();
+        assert!(text_summary.elf_sections.is_empty());
+
+        teardown_binary_tests();
+    }
+
+    #[test]
+    fn test_block_hasher() {
+        // Reads that straddle block boundaries still hash each block on its own
+        let mut block_hasher = BlockHasher::new(4);
+        block_hasher.update(b"abc");
+        block_hasher.update(b"defgh");
+        block_hasher.update(b"ij");
+        let block_hashes = block_hasher.finalize();
+        assert_eq!(block_hashes.block_size, 4);
+        assert_eq!(
+            block_hashes.hashes,
+            vec![
+                get_block_hash(b"abcd"),
+                get_block_hash(b"efgh"),
+                get_block_hash(b"ij")
+            ]
+        );
+
+        assert!(BlockHasher::new(4).finalize().hashes.is_empty());
+    }
+
+    #[test]
+    fn test_get_block_hash_size() {
+        assert_eq!(get_block_hash_size(false, 0, 4096, 1_000_000), 0);
+        assert_eq!(get_block_hash_size(false, 1_000_000, 4096, 999_999), 0);
+        assert_eq!(get_block_hash_size(false, 1_000_000, 4096, 1_000_000), 4096);
+        // Binary files get blocks for the binary diff whatever their size
+        assert_eq!(get_block_hash_size(true, 0, 4096, 10), 4096);
+    }
+
+    #[test]
+    fn test_get_changed_block_offsets() {
+        let old_blocks = BlockHashList {
+            block_size: 4,
+            hashes: vec!["A".to_string(), "B".to_string(), "C".to_string()],
+        };
+        let mut new_blocks = old_blocks.clone();
+        assert!(get_changed_block_offsets(&old_blocks, &new_blocks).is_empty());
+
+        new_blocks.hashes[1] = "X".to_string();
+        new_blocks.hashes.push("D".to_string());
+        assert_eq!(
+            get_changed_block_offsets(&old_blocks, &new_blocks),
+            vec![4, 12]
+        );
+
+        // Blocks of a different size can't be lined up
+        new_blocks.block_size = 8;
+        assert!(get_changed_block_offsets(&old_blocks, &new_blocks).is_empty());
+    }
+
+    #[test]
+    fn test_verify_blocks() {
+        let test_path = Path::new("./blockhashtestfile");
+        std::fs::write(test_path, "aaaabbbbccccdd").unwrap();
+
+        // Block hashes come out of the same read as the file hashes
+        let file_hashes = FileHashes {
+            sha256: true,
+            ..Default::default()
+        };
+        let (hashes, block_hashes) =
+            get_file_and_block_hashes(&file_hashes, 3, test_path, 4, ReadMode::Buffered).unwrap();
+        assert!(hashes.contains_key(&HashAlgorithm::Sha256));
+        let block_hashes = block_hashes.unwrap();
+        assert_eq!(block_hashes.hashes.len(), 4);
+        assert_eq!(block_hashes.hashes[3], get_block_hash(b"dd"));
+
+        assert!(verify_blocks(test_path, &block_hashes, &[0, 1, 2, 3])
+            .unwrap()
+            .is_empty());
+
+        // Only the blocks asked for are reread
+        std::fs::write(test_path, "aaaabbbbCCCCdd").unwrap();
+        assert!(verify_blocks(test_path, &block_hashes, &[0, 1])
+            .unwrap()
+            .is_empty());
+        assert_eq!(
+            verify_blocks(test_path, &block_hashes, &[1, 2]).unwrap(),
+            vec![8]
+        );
+
+        let _ = std::fs::remove_file(test_path);
+    }
+}
+
+//////////////////////////////////////////////////////////////////////////////////////////////////
+///////////////////////////////////////      CHANGES       ///////////////////////////////////////
+//////////////////////////////////////////////////////////////////////////////////////////////////
+#[cfg(test)]
+mod changes_tests {
+    use crate::changes::*;
+    use crate::severity::Severity;
+
+    #[test]
+    fn test_get_changed_fields() {
+        let changes = vec![
+            FieldChange::new("size", 1, 2),
+            FieldChange::new("discretionary_acl", "a", "b"),
+            FieldChange::new("discretionary_acl", "c", "d"),
+        ];
+
+        // Each field is only named once, in the order it was detected
+        assert_eq!(
+            get_changed_fields(&changes),
+            vec!["size".to_string(), "discretionary_acl".to_string()]
+        );
+        assert_eq!(get_changed_fields(&vec![]).len(), 0);
+    }
+
+    #[test]
+    fn test_get_change_type() {
+        assert_eq!(get_change_type(&vec![]), ChangeType::Unchanged);
+        assert_eq!(
+            get_change_type(&vec![FieldChange::new("size", 1, 2)]),
+            ChangeType::Modified
+        );
+        assert_eq!(
+            get_change_type(&vec![FieldChange::new("exists", true, false)]),
+            ChangeType::Deleted
+        );
+        assert_eq!(
+            get_change_type(&vec![FieldChange::new("exists", false, true)]),
+            ChangeType::Added
+        );
+        assert_eq!(
+            get_change_type(&vec![
+                FieldChange::new("path", "/usr/sbin/sshd", "/tmp/.sshd"),
+                FieldChange::new("creation_time", "a", "b"),
+            ]),
+            ChangeType::Moved
+        );
+        assert_eq!(
+            get_change_type(&vec![FieldChange::new("readable", true, false)]),
+            ChangeType::Unreadable
+        );
+        // Becoming readable again is an ordinary modification
+        assert_eq!(
+            get_change_type(&vec![FieldChange::new("readable", false, true)]),
+            ChangeType::Modified
+        );
+    }
+
+    #[test]
+    fn test_apply_change_rule() {
+        let changes = vec![
+            FieldChange::new("sha256", "AAAA", "BBBB"),
+            FieldChange::new("size", 10, 20),
+            FieldChange::new("modified_time", "a", "b"),
+            FieldChange::new("owner", "0:0", "1000:1000"),
+        ];
+        let mut change_rule = crate::scan_settings::get_default_change_rules().remove(0);
+
+        // Everything is monitored by the default rule
+        assert_eq!(apply_change_rule(changes.clone(), &change_rule).len(), 4);
+
+        change_rule.hashes = false;
+        change_rule.modified_time = false;
+        change_rule.allow_size_growth = true;
+        let monitored = apply_change_rule(changes.clone(), &change_rule);
+        assert_eq!(get_changed_fields(&monitored), vec!["owner"]);
+
+        // A shrinking file is still reported when growth is allowed
+        let shrinking = vec![FieldChange::new("size", 20, 10)];
+        assert_eq!(apply_change_rule(shrinking, &change_rule).len(), 1);
+
+        // Existence changes can't be turned off
+        change_rule.size = false;
+        change_rule.owner = false;
+        let deleted = vec![FieldChange::new("exists", true, false)];
+        assert_eq!(apply_change_rule(deleted, &change_rule).len(), 1);
+    }
+
+    #[test]
+    fn test_change_summary() {
+        let mut summary = ChangeSummary::default();
+        summary.add(ChangeType::Added);
+        summary.add(ChangeType::Added);
+        summary.add(ChangeType::Modified);
+        summary.add(ChangeType::Deleted);
+        summary.add(ChangeType::Unchanged);
+        summary.add_severity(Severity::Critical);
+        summary.add_severity(Severity::None);
+
+        // Only results with hashes say whether they were verified or trusted from the cache
+        let mut file_result = crate::file::FileScanResult::default();
+        summary.add_hash_source(&file_result);
+        file_result
+            .hashes
+            .insert(crate::hashing::HashAlgorithm::Sha256, "AAAA".to_string());
+        summary.add_hash_source(&file_result);
+        file_result.hashes_cached = true;
+        summary.add_hash_source(&file_result);
+
+        assert_eq!(summary.added, 2);
+        assert_eq!(summary.modified, 1);
+        assert_eq!(summary.deleted, 1);
+        assert_eq!(summary.unchanged, 1);
+        assert_eq!(
+            summary.to_string(),
+            "Added:2, Modified:1, Deleted:1, Moved:0, Unchanged:1, Unreadable:0, \
+            Suppressed:0, Critical:1, High:0, Medium:0, Low:0, Verified:1, Cached:1, IOC:0, PackageIntact:0, PackageModified:0, Unowned:0"
+        );
+    }
+}
+//////////////////////////////////////////////////////////////////////////////////////////////////
+///////////////////////////////////////        DIFF        ///////////////////////////////////////
+//////////////////////////////////////////////////////////////////////////////////////////////////
+#[cfg(test)]
+mod diff_tests {
+    use crate::changes::ChangeType;
+    use crate::diff::*;
+    use crate::hashing::HashAlgorithm;
+    use crate::helpers::{store_json, ScanResults};
+    use crate::ioc::IocEntry;
+    use crate::scan_settings::{get_default_severity_rules, SeverityRule};
+    use crate::severity::{Severity, DEFAULT_SEVERITY};
+    use crate::tests::test_fixtures::get_test_result;
+
+    fn teardown_diff_tests() {
+        let files: Vec<&str> = vec!["./difftest_older.json", "./difftest_newer.json"];
+
+        for file in files {
+            let _ = std::fs::remove_file(file);
+        }
+    }
+
+    #[test]
+    fn test_get_diff_report() {
+        teardown_diff_tests();
+        let older = ScanResults {
+            files: vec![
+                get_test_result("/etc/passwd", "AAAA"),
+                get_test_result("/etc/shadow", "BBBB"),
+                get_test_result("/usr/sbin/sshd", "CCCC"),
+                get_test_result("/etc/hosts", "DDDD"),
+            ],
+            ..Default::default()
+        };
+        let mut newer = ScanResults {
+            files: vec![
+                get_test_result("/etc/passwd", "EEEE"),
+                get_test_result("/usr/local/bin/new", "FFFF"),
+                get_test_result("/tmp/.sshd", "CCCC"),
+                get_test_result("/etc/hosts", "DDDD"),
+            ],
+            ..Default::default()
+        };
+        // Change data stored by the newer scan must not leak into the report
+        newer.files[3].is_modified = true;
+
+        let _ = store_json(&older, "./difftest_older.json");
+        let _ = store_json(&newer, "./difftest_newer.json");
+
+        // Any change to /etc/passwd is critical. Everything else gets the default severity.
+        let severity_rules = vec![SeverityRule {
+            path_pattern: "/etc/passwd".to_string(),
+            change_types: vec![],
+            fields: vec![],
+            attribute_bits_added: 0,
+            severity: Severity::Critical,
+        }];
+        let report = get_diff_report(
+            "./difftest_older.json",
+            "./difftest_newer.json",
+            &severity_rules,
+        )
+        .unwrap();
+        assert_eq!(report.summary.added, 1);
+        assert_eq!(report.summary.modified, 1);
+        assert_eq!(report.summary.deleted, 1);
+        assert_eq!(report.summary.moved, 1);
+        assert_eq!(report.summary.unchanged, 1);
+
+        // Unchanged paths are left out of the report
+        assert_eq!(report.files.len(), 4);
+        assert_eq!(report.files[0].change_type, ChangeType::Modified);
+        assert_eq!(report.files[0].changed_fields, vec!["sha256"]);
+        assert_eq!(report.files[1].change_type, ChangeType::Added);
+        assert_eq!(report.files[2].change_type, ChangeType::Moved);
+        assert_eq!(report.files[3].change_type, ChangeType::Deleted);
+
+        // Changes are classified the same way a scan classifies them
+        assert_eq!(report.files[0].severity, Severity::Critical);
+        assert_eq!(report.files[0].changes[0].severity, Severity::Critical);
+        assert_eq!(report.files[1].severity, DEFAULT_SEVERITY);
+        assert_eq!(report.summary.critical, 1);
+        assert_eq!(report.summary.medium, 3);
+
+        // Missing files are reported as errors instead of empty results
+        assert!(get_diff_report(
+            "./difftest_missing.json",
+            "./difftest_newer.json",
+            &severity_rules
+        )
+        .is_err());
+
+        teardown_diff_tests();
+    }
+    #[test]
+    fn test_get_diff_report_ioc_matches() {
+        let older_path = "./difftest_ioc_older.json";
+        let newer_path = "./difftest_ioc_newer.json";
+        let older = ScanResults {
+            files: vec![get_test_result("/tmp/dropper", "AAAA")],
+            ..Default::default()
+        };
+        let mut newer = older.clone();
+        newer.files[0].ioc_matches = vec![IocEntry {
+            algorithm: HashAlgorithm::Sha256,
+            hash: "AAAA".to_string(),
+            name: "Dropper".to_string(),
+            source: "feed.txt".to_string(),
+        }];
+        let _ = store_json(&older, older_path);
+        let _ = store_json(&newer, newer_path);
+
+        // The file didn't change, but a match is still in the report and still critical
+        let report =
+            get_diff_report(older_path, newer_path, &get_default_severity_rules()).unwrap();
+        assert_eq!(report.summary.unchanged, 1);
+        assert_eq!(report.summary.ioc_matches, 1);
+        assert_eq!(report.files.len(), 1);
+        assert_eq!(report.files[0].change_type, ChangeType::Unchanged);
+        assert_eq!(report.files[0].severity, Severity::Critical);
+
+        let _ = std::fs::remove_file(older_path);
+        let _ = std::fs::remove_file(newer_path);
+    }
 }
 //////////////////////////////////////////////////////////////////////////////////////////////////
 ///////////////////////////////////////        FILE        ///////////////////////////////////////
 //////////////////////////////////////////////////////////////////////////////////////////////////
 #[cfg(test)]
+// These tests predate running clippy on this crate and are kept as originally written
+#[allow(
+    clippy::assertions_on_constants,
+    clippy::bool_assert_comparison,
+    clippy::get_first,
+    clippy::let_unit_value,
+    clippy::permissions_set_readonly_false
+)]
 mod file_tests {
     use crate::file::*;
+    use crate::hash_cache::FullRehashState;
+    use crate::hashing::HashAlgorithm;
+    use crate::helpers::{store_json, ScanResults};
+    use crate::merkle::DirectoryHashMode;
     use crate::osfig_state::OsfigSettings;
+    use crate::scan_error::{ScanError, ScanOperation};
+    use crate::scan_index::ScanIndex;
     use crate::scan_settings::{FileScanSetting, ScanSettings};
     #[cfg(windows)]
     use crate::win_acl::{WinAcl, WinaclEntry};
@@ -139,8 +1044,11 @@ mod file_tests {
     #[test]
     fn test_store_json() {
         //
-        let expected_value: FileScanResult = FileScanResult::default();
-        match store_json(&vec![expected_value], "tests.json") {
+        let expected_value = ScanResults {
+            files: vec![FileScanResult::default()],
+            ..Default::default()
+        };
+        match store_json(&expected_value, "tests.json") {
             Ok(_) => {
                 assert!(true);
                 teardown_file_tests()
@@ -151,8 +1059,11 @@ mod file_tests {
         }
         // An empty file handle should fail, even with our path override
         // as there won't be a filename
-        let expected_value: FileScanResult = FileScanResult::default();
-        match store_json(&vec![expected_value], "") {
+        let expected_value = ScanResults {
+            files: vec![FileScanResult::default()],
+            ..Default::default()
+        };
+        match store_json(&expected_value, "") {
             Ok(_) => {
                 assert!(false)
             }
@@ -181,11 +1092,21 @@ mod file_tests {
                 md5: true,
                 sha256: true,
                 blake2s: true,
+                ..Default::default()
             },
             file_dacl: false,
             file_sacl: false,
             file_content: false,
             file_read_buffer_size: 4096,
+            file_content_diff_context: 3,
+            change_rule: "all".to_string(),
+            file_binary_analysis: false,
+            file_binary_block_size: 4096,
+            directory_hash: DirectoryHashMode::Off,
+            file_fast_mode: false,
+            file_block_hash_threshold: 0,
+            file_mmap: false,
+            file_mmap_threshold: 0,
         };
         #[cfg(target_os = "linux")]
         let filescansetting = FileScanSetting {
@@ -195,11 +1116,21 @@ mod file_tests {
                 md5: true,
                 sha256: true,
                 blake2s: true,
+                ..Default::default()
             },
             file_dacl: false,
             file_sacl: false,
             file_content: false,
             file_read_buffer_size: 4096,
+            file_content_diff_context: 3,
+            change_rule: "all".to_string(),
+            file_binary_analysis: false,
+            file_binary_block_size: 4096,
+            directory_hash: DirectoryHashMode::Off,
+            file_fast_mode: false,
+            file_block_hash_threshold: 0,
+            file_mmap: false,
+            file_mmap_threshold: 0,
         };
 
         let osfig_settings = OsfigSettings {
@@ -207,13 +1138,24 @@ mod file_tests {
                 scan_files: true,
                 file_scan_settings: vec![filescansetting],
                 file_scan_delay: 0,
+                file_scan_workers: 1,
                 scan_registry: false,
                 registry_patterns: vec![],
+                change_rules: vec![],
+                suppressions: vec![],
+                severity_rules: vec![],
+                full_rehash_runs: 0,
+                full_rehash_days: 0,
+                known_good_severity: None,
+                ioc_feeds: vec![],
+                verify_packages: false,
+                dpkg_path: "".to_string(),
+                rpm_path: "".to_string(),
             },
             scan_result_path: "./scans".to_string(),
         };
 
-        let expected_value = scan_files(&osfig_settings);
+        let expected_value = scan_files(&osfig_settings, Vec::new(), &FullRehashState::default());
 
         assert_eq!(
             expected_value.type_id(),
@@ -254,6 +1196,9 @@ mod file_tests {
             .type_id()
         );
 
+        // The next scan compares against the files of the newest results
+        let newest_files = expected_value.clone();
+
         // Recreating the file will cause the timestamps to be altered, so our next scan is
         // for a modified file. Also change to RO file.
         let _ = std::fs::remove_file("testfile1");
@@ -262,7 +1207,7 @@ mod file_tests {
         test_file_perms.set_readonly(true);
         let _ = std::fs::set_permissions("testfile1", test_file_perms);
 
-        let expected_value = scan_files(&osfig_settings);
+        let expected_value = scan_files(&osfig_settings, newest_files, &FullRehashState::default());
 
         let json_file = File::create("tests_result.json").unwrap();
         let file_writer = std::io::BufWriter::new(json_file);
@@ -304,20 +1249,889 @@ mod file_tests {
         teardown_file_tests();
     }
     #[test]
-    fn test_get_content_diff() {
-        //Todo after refactoring settings file to include results path
-    }
-    #[test]
-    fn test_check_acl_modified() {
-        //Todo after refactoring settings file to include results path
-    }
-}
-//////////////////////////////////////////////////////////////////////////////////////////////////
-///////////////////////////////////////       HASHING      ///////////////////////////////////////
-//////////////////////////////////////////////////////////////////////////////////////////////////
-#[cfg(test)]
-mod hashing_tests {
+    fn test_compare_file_results() {
+        let mut old_result: FileScanResult = FileScanResult::default();
+        old_result.set_path("./testfile1");
+        old_result.exists = true;
+        old_result
+            .hashes
+            .insert(HashAlgorithm::Sha256, "AAAA".to_string());
+        old_result.size = 10;
+        old_result.attributes = 0o100644;
+
+        // Identical results carry no change records
+        assert_eq!(
+            compare_file_results(&old_result, &old_result.clone()).len(),
+            0
+        );
+
+        let mut new_result = old_result.clone();
+        new_result
+            .hashes
+            .insert(HashAlgorithm::Sha256, "BBBB".to_string());
+        new_result.size = 12;
+        new_result.attributes = 0o100755;
+
+        // Every differing field is reported instead of stopping at the first difference
+        let changes = compare_file_results(&old_result, &new_result);
+        assert_eq!(changes.len(), 3);
+        assert_eq!(changes[0].field, "sha256");
+        assert_eq!(changes[0].old_value, "AAAA");
+        assert_eq!(changes[0].new_value, "BBBB");
+        assert_eq!(
+            crate::changes::get_changed_fields(&changes),
+            vec!["sha256", "attributes", "size"]
+        );
+
+        // A missing hash on either side is not a change
+        new_result.hashes.remove(&HashAlgorithm::Sha256);
+        let changes = compare_file_results(&old_result, &new_result);
+        assert_eq!(changes.len(), 2);
+
+        // A path that stopped existing only reports the existence change
+        let mut missing_result: FileScanResult = FileScanResult::default();
+        missing_result.set_path("./testfile1");
+        let changes = compare_file_results(&old_result, &missing_result);
+        assert_eq!(changes.len(), 1);
+        assert_eq!(changes[0].field, "exists");
+        assert_eq!(changes[0].old_value, "true");
+        assert_eq!(changes[0].new_value, "false");
+
+        // Paths without a prior entry are new since the last scan
+        let mut other_result = new_result.clone();
+        other_result.set_path("./testfile2");
+        let changes = get_file_changes(&ScanIndex::new(&vec![old_result.clone()]), &other_result);
+        assert_eq!(changes.len(), 1);
+        assert_eq!(
+            crate::changes::get_change_type(&changes),
+            crate::changes::ChangeType::Added
+        );
+
+        // Without any prior results there is nothing to compare against
+        assert_eq!(
+            get_file_changes(&ScanIndex::new(&vec![]), &other_result).len(),
+            0
+        );
+
+        // A missing path the last scan never saw isn't added, modified or anything else
+        let mut gone_result: FileScanResult = FileScanResult::default();
+        gone_result.set_path("./testfile3");
+        assert!(get_file_changes(&ScanIndex::new(&vec![old_result]), &gone_result).is_empty());
+    }
+
+    #[test]
+    fn test_compare_unreadable_results() {
+        let mut old_result: FileScanResult = FileScanResult::default();
+        old_result.set_path("./unreadabletestfile");
+        old_result.exists = true;
+        old_result
+            .hashes
+            .insert(HashAlgorithm::Sha256, "AAAA".to_string());
+        old_result.size = 10;
+
+        // Hashing failed, so the hashes are empty and the error says why
+        let mut unreadable_result = old_result.clone();
+        unreadable_result.hashes.clear();
+        unreadable_result.errors = vec![ScanError::new(
+            ScanOperation::Hash,
+            &std::io::Error::from(std::io::ErrorKind::PermissionDenied),
+        )];
+        assert!(unreadable_result.is_unreadable());
+
+        let changes = compare_file_results(&old_result, &unreadable_result);
+        assert_eq!(changes.len(), 1);
+        assert_eq!(changes[0].field, "readable");
+        assert_eq!(changes[0].old_value, "true");
+        assert_eq!(changes[0].new_value, "false");
+        assert_eq!(
+            crate::changes::get_change_type(&changes),
+            crate::changes::ChangeType::Unreadable
+        );
+
+        // Still unreadable on the next scan is not a change
+        assert_eq!(
+            compare_file_results(&unreadable_result, &unreadable_result.clone()).len(),
+            0
+        );
+
+        // Without metadata, the default values aren't compared either
+        let mut no_metadata_result: FileScanResult = FileScanResult::default();
+        no_metadata_result.set_path("./unreadabletestfile");
+        no_metadata_result.exists = true;
+        no_metadata_result.errors = vec![ScanError::new(
+            ScanOperation::Metadata,
+            &std::io::Error::from(std::io::ErrorKind::PermissionDenied),
+        )];
+        let changes = compare_file_results(&old_result, &no_metadata_result);
+        assert_eq!(changes.len(), 1);
+        assert_eq!(changes[0].field, "readable");
+
+        // Content that isn't UTF-8 was still read
+        let mut binary_result = old_result.clone();
+        binary_result.errors = vec![ScanError::new(
+            ScanOperation::Content,
+            &std::io::Error::from(std::io::ErrorKind::InvalidData),
+        )];
+        assert!(!binary_result.is_unreadable());
+        assert_eq!(compare_file_results(&old_result, &binary_result).len(), 0);
+    }
+    #[test]
+    fn test_get_deleted_results() {
+        let mut filescansetting = FileScanSetting {
+            file_patterns: vec!["./testfile*".to_string(), "/etc/*".to_string()],
+            file_ignore_patterns: vec![],
+            file_hashes: crate::scan_settings::FileHashes {
+                md5: true,
+                sha256: true,
+                blake2s: true,
+                ..Default::default()
+            },
+            file_dacl: false,
+            file_sacl: false,
+            file_content: false,
+            file_read_buffer_size: 4096,
+            file_content_diff_context: 3,
+            change_rule: "all".to_string(),
+            file_binary_analysis: false,
+            file_binary_block_size: 4096,
+            directory_hash: DirectoryHashMode::Off,
+            file_fast_mode: false,
+            file_block_hash_threshold: 0,
+            file_mmap: false,
+            file_mmap_threshold: 0,
+        };
+
+        let mut last_scan: Vec<FileScanResult> = Vec::new();
+        for path in ["testfile1", "testfile2", "/etc/passwd", "/opt/other"] {
+            let mut scan_entry: FileScanResult = FileScanResult::default();
+            scan_entry.set_path(path);
+            scan_entry.exists = true;
+            scan_entry
+                .hashes
+                .insert(HashAlgorithm::Sha256, "AAAA".to_string());
+            last_scan.push(scan_entry);
+        }
+        // Already reported as missing by an earlier scan
+        let mut missing_entry: FileScanResult = FileScanResult::default();
+        missing_entry.set_path("testfile3");
+        last_scan.push(missing_entry);
+
+        let this_scan: Vec<FileScanResult> = vec![last_scan[0].clone()];
+
+        let mut scan_settings = crate::scan_settings::get_default_scan_settings();
+        scan_settings.file_scan_settings = vec![filescansetting.clone()];
+        let deleted_results = get_deleted_results(&last_scan, &this_scan, &scan_settings);
+        assert_eq!(deleted_results.len(), 2);
+        assert_eq!(
+            deleted_results[0].path,
+            Box::new(PathBuf::from("testfile2"))
+        );
+        assert!(!deleted_results[0].exists);
+        assert!(deleted_results[0].is_modified);
+        assert_eq!(
+            deleted_results[0].change_type,
+            crate::changes::ChangeType::Deleted
+        );
+        assert_eq!(deleted_results[0].changed_fields, vec!["exists"]);
+        assert_eq!(
+            deleted_results[1].path,
+            Box::new(PathBuf::from("/etc/passwd"))
+        );
+
+        // Ignored paths are not reported, even when they were in the last scan
+        filescansetting.file_ignore_patterns =
+            vec!["./testfile2".to_string(), "/etc/*".to_string()];
+        scan_settings.file_scan_settings = vec![filescansetting];
+        let deleted_results = get_deleted_results(&last_scan, &this_scan, &scan_settings);
+        assert_eq!(deleted_results.len(), 0);
+
+        assert!(path_matches_pattern(Path::new("testfile1"), "./testfile*"));
+        assert!(path_matches_pattern(
+            Path::new("/etc/ssh/sshd_config"),
+            "/etc/**"
+        ));
+        assert!(!path_matches_pattern(Path::new("/opt/other"), "/etc/*"));
+    }
+    #[test]
+    fn test_correlate_moved_results() {
+        let mut prior_entry: FileScanResult = FileScanResult::default();
+        prior_entry.set_path("/usr/sbin/sshd");
+        prior_entry.exists = true;
+        prior_entry.is_file = true;
+        prior_entry
+            .hashes
+            .insert(HashAlgorithm::Sha256, "AAAA".to_string());
+        prior_entry.size = 100;
+        let mut other_prior_entry = prior_entry.clone();
+        other_prior_entry.set_path("/usr/sbin/cron");
+        other_prior_entry
+            .hashes
+            .insert(HashAlgorithm::Sha256, "CCCC".to_string());
+        let last_scan = vec![prior_entry.clone(), other_prior_entry];
+
+        let mut deleted_result: FileScanResult = FileScanResult::default();
+        deleted_result.set_path("/usr/sbin/sshd");
+        deleted_result.set_changes(compare_file_results(&prior_entry, &deleted_result));
+        let mut other_deleted_result = deleted_result.clone();
+        other_deleted_result.set_path("/usr/sbin/cron");
+
+        let mut added_result = prior_entry.clone();
+        added_result.set_path("/tmp/.sshd");
+        added_result.set_changes(get_file_changes(&ScanIndex::new(&last_scan), &added_result));
+        let mut other_added_result = added_result.clone();
+        other_added_result.set_path("/tmp/.other");
+        other_added_result
+            .hashes
+            .insert(HashAlgorithm::Sha256, "BBBB".to_string());
+
+        let results = correlate_moved_results(
+            &ScanIndex::new(&last_scan),
+            vec![
+                other_added_result,
+                added_result,
+                deleted_result,
+                other_deleted_result,
+            ],
+            |_| None,
+        );
+
+        // The matching deletion and addition collapse into one move, everything else is untouched
+        assert_eq!(results.len(), 3);
+        assert_eq!(results[0].change_type, crate::changes::ChangeType::Added);
+        assert_eq!(results[1].change_type, crate::changes::ChangeType::Moved);
+        assert_eq!(results[1].path, Box::new(PathBuf::from("/tmp/.sshd")));
+        assert_eq!(
+            results[1].previous_path,
+            Some(Box::new(PathBuf::from("/usr/sbin/sshd")))
+        );
+        assert_eq!(results[1].changes[0].field, "path");
+        assert_eq!(results[1].changes[0].old_value, "/usr/sbin/sshd");
+        assert_eq!(results[1].changes[0].new_value, "/tmp/.sshd");
+        assert_eq!(results[2].change_type, crate::changes::ChangeType::Deleted);
+        assert_eq!(results[2].path, Box::new(PathBuf::from("/usr/sbin/cron")));
+
+        // Empty files all share a hash, so they are never treated as the same content
+        let mut empty_entry = prior_entry.clone();
+        empty_entry.size = 0;
+        assert!(!is_same_content(&empty_entry, &empty_entry.clone()));
+        assert!(is_same_content(&prior_entry, &prior_entry.clone()));
+    }
+    #[test]
+    fn test_deleted_and_moved_results_use_change_rule() {
+        // A rule that monitors nothing still reports paths disappearing and moving, but none of
+        // the other differences of a move
+        let mut scan_settings = crate::scan_settings::get_default_scan_settings();
+        let mut change_rule = crate::scan_settings::get_default_change_rule();
+        change_rule.name = "quiet".to_string();
+        change_rule.hashes = false;
+        change_rule.size = false;
+        change_rule.attributes = false;
+        change_rule.owner = false;
+        change_rule.symbolic_link = false;
+        change_rule.modified_time = false;
+        change_rule.creation_time = false;
+        change_rule.acl = false;
+        scan_settings.change_rules = vec![change_rule];
+        let mut filescansetting = scan_settings.file_scan_settings[0].clone();
+        filescansetting.file_patterns = vec!["/usr/sbin/*".to_string(), "/tmp/*".to_string()];
+        filescansetting.file_ignore_patterns = vec![];
+        filescansetting.change_rule = "quiet".to_string();
+        scan_settings.file_scan_settings = vec![filescansetting];
+
+        let mut prior_entry: FileScanResult = FileScanResult::default();
+        prior_entry.set_path("/usr/sbin/sshd");
+        prior_entry.exists = true;
+        prior_entry.is_file = true;
+        prior_entry.size = 100;
+        prior_entry
+            .hashes
+            .insert(HashAlgorithm::Sha256, "AAAA".to_string());
+        prior_entry.modified_time = "2024-01-01 00:00:00 UTC".to_string();
+        let last_scan = vec![prior_entry.clone()];
+
+        let mut added_result = prior_entry.clone();
+        added_result.set_path("/tmp/.sshd");
+        added_result.modified_time = "2024-06-01 00:00:00 UTC".to_string();
+        added_result.set_changes(get_file_changes(&ScanIndex::new(&last_scan), &added_result));
+
+        let mut results = vec![added_result];
+        let deleted_results = get_deleted_results(&last_scan, &results, &scan_settings);
+        assert_eq!(deleted_results.len(), 1);
+        assert_eq!(
+            deleted_results[0].change_type,
+            crate::changes::ChangeType::Deleted
+        );
+        assert_eq!(deleted_results[0].changed_fields, vec!["exists"]);
+        results.extend(deleted_results);
+
+        let moved_results =
+            correlate_moved_results(&ScanIndex::new(&last_scan), results.clone(), |path| {
+                get_path_change_rule(path, &scan_settings)
+            });
+        assert_eq!(moved_results.len(), 1);
+        assert_eq!(
+            moved_results[0].change_type,
+            crate::changes::ChangeType::Moved
+        );
+        assert_eq!(moved_results[0].changed_fields, vec!["path"]);
+
+        // Without a rule every difference of the move is kept
+        let moved_results = correlate_moved_results(&ScanIndex::new(&last_scan), results, |_| None);
+        assert_eq!(
+            moved_results[0].changed_fields,
+            vec!["path", "modified_time"]
+        );
+    }
+    #[test]
+    fn test_get_content_diff() {
+        let mut old_result: FileScanResult = FileScanResult::default();
+        old_result.set_path("/etc/ssh/sshd_config");
+        old_result.contents = "1\n2\n3\n4\n5\n6\n7\n8\n9\n10\n".to_string();
+        let mut new_result = old_result.clone();
+        new_result.contents = "1\n2\nthree\n4\n5\n6\n7\n8\n9\n10\n11\n".to_string();
+
+        let (content_diff, content_diff_hunks) =
+            get_content_diff(&new_result, &ScanIndex::new(&vec![old_result.clone()]), 1);
+
+        // Changes further apart than the context become separate hunks
+        assert_eq!(content_diff_hunks.len(), 2);
+        assert_eq!(
+            content_diff_hunks[0],
+            DiffHunk {
+                old_start: 2,
+                old_lines: 3,
+                new_start: 2,
+                new_lines: 3,
+                lines: [" 2", "-3", "+three", " 4"]
+                    .iter()
+                    .map(|line| line.to_string())
+                    .collect(),
+            }
+        );
+        assert_eq!(content_diff_hunks[1].old_start, 10);
+        assert_eq!(content_diff_hunks[1].old_lines, 1);
+        assert_eq!(content_diff_hunks[1].new_lines, 2);
+
+        // The patch text uses standard unified diff headers
+        assert!(content_diff.starts_with("--- a/etc/ssh/sshd_config\n+++ b/etc/ssh/sshd_config\n"));
+        assert!(content_diff.contains("@@ -2,3 +2,3 @@\n 2\n-3\n+three\n 4\n"));
+        assert!(content_diff.contains("@@ -10 +10,2 @@\n 10\n+11\n"));
+
+        // Enough context to overlap merges the hunks
+        let (_, content_diff_hunks) =
+            get_content_diff(&new_result, &ScanIndex::new(&vec![old_result.clone()]), 4);
+        assert_eq!(content_diff_hunks.len(), 1);
+
+        // Paths without a prior entry have no diff
+        new_result.set_path("/etc/other");
+        let (content_diff, content_diff_hunks) =
+            get_content_diff(&new_result, &ScanIndex::new(&vec![old_result]), 3);
+        assert_eq!(content_diff, "");
+        assert_eq!(content_diff_hunks.len(), 0);
+    }
+    #[test]
+    fn test_check_acl_modified() {
+        //Todo after refactoring settings file to include results path
+    }
+
+    #[test]
+    fn test_scan_file_fast_mode() {
+        let test_path = "./fastmodetestfile";
+        std::fs::write(test_path, "aaaabbbbcccc").unwrap();
+        let filescansetting = FileScanSetting {
+            file_patterns: vec![test_path.to_string()],
+            file_ignore_patterns: vec![],
+            file_hashes: crate::scan_settings::FileHashes {
+                sha256: true,
+                ..Default::default()
+            },
+            file_dacl: false,
+            file_sacl: false,
+            file_content: false,
+            file_read_buffer_size: 4096,
+            file_content_diff_context: 3,
+            change_rule: "all".to_string(),
+            file_binary_analysis: false,
+            file_binary_block_size: 4,
+            directory_hash: DirectoryHashMode::Off,
+            file_fast_mode: true,
+            file_block_hash_threshold: 1,
+            file_mmap: false,
+            file_mmap_threshold: 0,
+        };
+        let change_rule = crate::scan_settings::get_change_rule(
+            &crate::scan_settings::get_default_scan_settings(),
+            "all",
+        );
+        let scan = |last_scan: &Vec<FileScanResult>, cache_run: Option<u32>| {
+            let glob_match = glob::glob(test_path).unwrap().next().unwrap();
+            scan_file(
+                &filescansetting,
+                &change_rule,
+                &glob_match,
+                &ScanIndex::new(last_scan),
+                cache_run,
+            )
+        };
+
+        // A full rehash reads the file and its blocks
+        let first_result = scan(&vec![], None);
+        assert!(!first_result.hashes_cached);
+        assert_eq!(first_result.block_hashes.as_ref().unwrap().hashes.len(), 3);
+
+        // Nothing touched the file, so the hashes are reused
+        let cached_result = scan(&vec![first_result.clone()], Some(1));
+        assert!(cached_result.hashes_cached);
+        assert_eq!(cached_result.hashes, first_result.hashes);
+        assert!(!cached_result.is_modified);
+
+        // The spot checked block no longer matches what the last scan saw, so the whole file is
+        // read again
+        let mut tampered_result = first_result.clone();
+        tampered_result
+            .hashes
+            .insert(HashAlgorithm::Sha256, "AAAA".to_string());
+        tampered_result.block_hashes.as_mut().unwrap().hashes[1] = "0000".to_string();
+        let rehashed_result = scan(&vec![tampered_result], Some(1));
+        assert!(!rehashed_result.hashes_cached);
+        assert_eq!(rehashed_result.changed_block_offsets, vec![4]);
+
+        let _ = std::fs::remove_file(test_path);
+    }
+
+    #[test]
+    fn test_scan_entries_workers() {
+        let test_dir = Path::new("./workerstest");
+        let _ = std::fs::remove_dir_all(test_dir);
+        std::fs::create_dir_all(test_dir).unwrap();
+        for index in 0..20 {
+            // Uneven sizes so the workers finish out of order
+            std::fs::write(
+                test_dir.join(format!("file{:02}", index)),
+                "x".repeat((20 - index) * 1000),
+            )
+            .unwrap();
+        }
+        let entries: Vec<glob::GlobResult> = glob::glob("./workerstest/file*").unwrap().collect();
+
+        let mut scan_settings = crate::scan_settings::get_default_scan_settings();
+        let mut filescansetting = scan_settings.file_scan_settings[1].clone();
+        filescansetting.file_hashes = crate::scan_settings::FileHashes {
+            sha256: true,
+            ..Default::default()
+        };
+        let change_rule = crate::scan_settings::get_change_rule(&scan_settings, "all");
+        let last_scan: Vec<FileScanResult> = vec![];
+        let last_scan_index = ScanIndex::new(&last_scan);
+
+        let single_results = scan_entries(
+            &filescansetting,
+            &change_rule,
+            &entries,
+            &last_scan_index,
+            None,
+            &scan_settings,
+        );
+        scan_settings.file_scan_workers = 4;
+        let worker_results = scan_entries(
+            &filescansetting,
+            &change_rule,
+            &entries,
+            &last_scan_index,
+            None,
+            &scan_settings,
+        );
+
+        // Same results in the same order, however many workers scanned them
+        assert_eq!(worker_results.len(), 20);
+        for (single_result, worker_result) in single_results.iter().zip(worker_results.iter()) {
+            assert_eq!(single_result.path, worker_result.path);
+            assert_eq!(single_result.hashes, worker_result.hashes);
+        }
+        assert!(worker_results[0].path.ends_with("file00"));
+        assert!(worker_results[19].path.ends_with("file19"));
+
+        let _ = std::fs::remove_dir_all(test_dir);
+    }
+
+    #[test]
+    fn test_scan_file_binary_blocks() {
+        let test_path = Path::new("./binaryblockstestfile");
+        let mut data: Vec<u8> = vec![0; 40];
+        std::fs::write(test_path, &data).unwrap();
+
+        let scan_settings = crate::scan_settings::get_default_scan_settings();
+        let mut filescansetting = scan_settings.file_scan_settings[1].clone();
+        filescansetting.file_binary_analysis = true;
+        filescansetting.file_binary_block_size = 16;
+        filescansetting.file_block_hash_threshold = 0;
+        let change_rule = crate::scan_settings::get_change_rule(&scan_settings, "all");
+        let scan = |last_scan: &Vec<FileScanResult>| {
+            scan_file(
+                &filescansetting,
+                &change_rule,
+                &Ok(test_path.to_path_buf()),
+                &ScanIndex::new(last_scan),
+                None,
+            )
+        };
+
+        // Binary files get block hashes from the hashing read even below the threshold
+        let old_result = scan(&vec![]);
+        assert!(old_result.binary_summary.is_some());
+        assert_eq!(old_result.block_hashes.as_ref().unwrap().hashes.len(), 3);
+
+        data[20] = 1;
+        std::fs::write(test_path, &data).unwrap();
+        let new_result = scan(&vec![old_result]);
+        assert!(new_result.is_modified);
+        assert_eq!(new_result.changed_block_offsets, vec![16]);
+        let binary_diff = new_result.binary_diff.unwrap();
+        assert_eq!(binary_diff.changed_bytes, 16);
+
+        let _ = std::fs::remove_file(test_path);
+    }
+
+    #[test]
+    #[cfg(target_os = "linux")]
+    fn test_scan_file_non_utf8_name() {
+        use std::os::unix::ffi::OsStrExt;
+
+        let test_dir = Path::new("./nonutf8test");
+        let _ = std::fs::remove_dir_all(test_dir);
+        std::fs::create_dir_all(test_dir).unwrap();
+        let test_path = test_dir.join(std::ffi::OsStr::from_bytes(b"file\xff"));
+        std::fs::write(&test_path, "contents").unwrap();
+
+        let scan_settings = crate::scan_settings::get_default_scan_settings();
+        let filescansetting = scan_settings.file_scan_settings[1].clone();
+        let change_rule = crate::scan_settings::get_change_rule(&scan_settings, "all");
+        let last_scan: Vec<FileScanResult> = vec![];
+        let last_scan_index = ScanIndex::new(&last_scan);
+        let result = scan_file(
+            &filescansetting,
+            &change_rule,
+            &Ok(test_path.clone()),
+            &last_scan_index,
+            None,
+        );
+
+        // A file name that isn't valid UTF-8 is still scanned instead of panicking the scan
+        assert_eq!(*result.path, test_path);
+        assert!(result.exists);
+        assert!(!result.modified_time.is_empty());
+
+        let _ = std::fs::remove_dir_all(test_dir);
+    }
+
+    #[test]
+    #[cfg(target_os = "linux")]
+    fn test_scan_file_dangling_symlink() {
+        let test_dir = Path::new("./danglingtest");
+        let _ = std::fs::remove_dir_all(test_dir);
+        std::fs::create_dir_all(test_dir).unwrap();
+        let link_path = test_dir.join("link");
+        std::os::unix::fs::symlink("./missing", &link_path).unwrap();
+
+        let scan_settings = crate::scan_settings::get_default_scan_settings();
+        let filescansetting = scan_settings.file_scan_settings[1].clone();
+        let change_rule = crate::scan_settings::get_change_rule(&scan_settings, "all");
+        let mut prior_result: FileScanResult = FileScanResult::default();
+        prior_result.set_path("./danglingtest/other");
+        prior_result.exists = true;
+        let last_scan = vec![prior_result];
+        let result = scan_file(
+            &filescansetting,
+            &change_rule,
+            &Ok(link_path),
+            &ScanIndex::new(&last_scan),
+            None,
+        );
+
+        // Used to come out modified with exists false -> false
+        assert!(!result.exists);
+        assert!(!result.is_modified);
+        assert!(result.changes.is_empty());
+
+        let _ = std::fs::remove_dir_all(test_dir);
+    }
+
+    #[test]
+    fn test_get_timestamp() {
+        assert_eq!(get_timestamp(0, 0), "1970-01-01 00:00:00 UTC");
+        // Out of range for chrono, which used to panic the scan
+        assert_eq!(get_timestamp(i64::MAX, 0), "");
+    }
+}
+//////////////////////////////////////////////////////////////////////////////////////////////////
+///////////////////////////////////////       FUZZY        ///////////////////////////////////////
+//////////////////////////////////////////////////////////////////////////////////////////////////
+#[cfg(test)]
+mod fuzzy_tests {
+    use crate::file::{get_similarity, FileScanResult};
+    use crate::fuzzy::*;
+    use crate::hashing::HashAlgorithm;
+    use crate::scan_index::ScanIndex;
+
+    // Repeatable text-like data without needing a fixture file
+    fn get_test_data(seed: u64, length: usize) -> Vec<u8> {
+        let mut state = seed;
+        (0..length)
+            .map(|_| {
+                state = state
+                    .wrapping_mul(6364136223846793005)
+                    .wrapping_add(1442695040888963407);
+                b"abcdefghijklmnopqrstuvwxyz \n"[(state >> 33) as usize % 28]
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_get_ssdeep() {
+        assert_eq!(get_ssdeep(b""), "3::");
+
+        let data = get_test_data(1, 20_000);
+        let digest = get_ssdeep(&data);
+        let parts: Vec<&str> = digest.split(':').collect();
+        assert_eq!(parts.len(), 3);
+        // 20000 bytes needs a block size of at least 20000 / 64
+        assert!(parts[0].parse::<u64>().unwrap() * 64 >= 20_000);
+        assert!(parts[1].len() >= 32 && parts[1].len() <= 64);
+        assert!(parts[2].len() <= 32);
+
+        // Feeding the data in pieces can't change the digest
+        let mut hasher = SsdeepHasher::default();
+        for piece in data.chunks(7) {
+            hasher.update(piece);
+        }
+        assert_eq!(hasher.finalize(), digest);
+    }
+
+    #[test]
+    fn test_ssdeep_known_answers() {
+        // Digests and score from the reference ssdeep library (libfuzzy), as published in the
+        // python-ssdeep documentation. The inputs differ only in the case of "Ctph".
+        let lower_digest =
+            get_ssdeep(b"Also called fuzzy hashes, Ctph can match inputs that have homologies.");
+        let upper_digest =
+            get_ssdeep(b"Also called fuzzy hashes, CTPH can match inputs that have homologies.");
+        assert_eq!(lower_digest, "3:AXGBicFlgVNhBGcL6wCrFQEv:AXGHsNhxLsr2C");
+        assert_eq!(upper_digest, "3:AXGBicFlIHBGcL6wCrFQEv:AXGH6xLsr2C");
+        assert_eq!(compare_ssdeep(&lower_digest, &upper_digest), Some(22));
+    }
+
+    #[test]
+    fn test_compare_ssdeep() {
+        let data = get_test_data(1, 20_000);
+        let digest = get_ssdeep(&data);
+        assert_eq!(compare_ssdeep(&digest, &digest), Some(100));
+
+        // A small patch keeps most of the digest
+        let mut patched_data = data.clone();
+        patched_data[10_000..10_010].copy_from_slice(b"PATCHED!!!");
+        let patched_score = compare_ssdeep(&digest, &get_ssdeep(&patched_data)).unwrap();
+        assert!((80..100).contains(&patched_score));
+
+        // A complete replacement shares nothing
+        let replaced_score = compare_ssdeep(&digest, &get_ssdeep(&get_test_data(2, 20_000)));
+        assert_eq!(replaced_score, Some(0));
+
+        // Block sizes too far apart can't be compared
+        assert_eq!(
+            compare_ssdeep(&digest, &get_ssdeep(&get_test_data(1, 200_000))),
+            Some(0)
+        );
+        assert_eq!(compare_ssdeep(&digest, "not a digest"), None);
+    }
+
+    #[test]
+    fn test_get_similarity() {
+        let data = get_test_data(3, 20_000);
+        let mut patched_data = data.clone();
+        patched_data[5_000] = b'!';
+
+        let mut old_result: FileScanResult = FileScanResult::default();
+        old_result.set_path("/etc/test.conf");
+        old_result
+            .hashes
+            .insert(HashAlgorithm::Ssdeep, get_ssdeep(&data));
+        let old_results = vec![old_result];
+        let old_index = ScanIndex::new(&old_results);
+
+        let mut new_result: FileScanResult = FileScanResult::default();
+        new_result.set_path("/etc/test.conf");
+        assert_eq!(get_similarity(&new_result, &old_index), None);
+
+        new_result
+            .hashes
+            .insert(HashAlgorithm::Ssdeep, get_ssdeep(&patched_data));
+        let similarity = get_similarity(&new_result, &old_index).unwrap();
+        assert!(similarity >= 80);
+
+        new_result.set_path("/etc/other.conf");
+        assert_eq!(get_similarity(&new_result, &old_index), None);
+    }
+}
+//////////////////////////////////////////////////////////////////////////////////////////////////
+///////////////////////////////////////     HASH_CACHE     ///////////////////////////////////////
+//////////////////////////////////////////////////////////////////////////////////////////////////
+#[cfg(test)]
+mod hash_cache_tests {
+    use crate::file::FileScanResult;
+    use crate::hash_cache::*;
+    use crate::hashing::HashAlgorithm;
+    use crate::scan_error::{ScanError, ScanOperation};
+    use crate::scan_settings::{get_default_scan_settings, FileHashes};
+    use chrono::{DateTime, Duration, Utc};
+
+    #[test]
+    fn test_is_full_rehash_due() {
+        let now = "2024-06-01 12:00:00 UTC".parse::<DateTime<Utc>>().unwrap();
+        let mut scan_settings = get_default_scan_settings();
+        scan_settings.full_rehash_runs = 4;
+        scan_settings.full_rehash_days = 7;
+        let recent_state = FullRehashState {
+            runs_since_full_rehash: 1,
+            last_full_rehash: (now - Duration::days(1)).to_string(),
+        };
+
+        // Without fast mode there's no cache to distrust
+        assert!(is_full_rehash_due(&scan_settings, &recent_state, now));
+
+        scan_settings.file_scan_settings[0].file_fast_mode = true;
+        assert!(!is_full_rehash_due(&scan_settings, &recent_state, now));
+
+        // Never having done a full rehash means doing one now
+        assert!(is_full_rehash_due(
+            &scan_settings,
+            &FullRehashState::default(),
+            now
+        ));
+
+        // Every 4th run
+        let mut state = recent_state.clone();
+        state.runs_since_full_rehash = 3;
+        assert!(is_full_rehash_due(&scan_settings, &state, now));
+
+        // Or every 7 days
+        let mut state = recent_state.clone();
+        state.last_full_rehash = (now - Duration::days(7)).to_string();
+        assert!(is_full_rehash_due(&scan_settings, &state, now));
+
+        // Zero turns a schedule off
+        scan_settings.full_rehash_runs = 0;
+        scan_settings.full_rehash_days = 0;
+        assert!(!is_full_rehash_due(&scan_settings, &state, now));
+    }
+
+    #[test]
+    fn test_get_next_full_rehash_state() {
+        let now = "2024-06-01 12:00:00 UTC".parse::<DateTime<Utc>>().unwrap();
+        let state = FullRehashState {
+            runs_since_full_rehash: 2,
+            last_full_rehash: "2024-05-30 12:00:00 UTC".to_string(),
+        };
+
+        let next_state = get_next_full_rehash_state(&state, false, now);
+        assert_eq!(next_state.runs_since_full_rehash, 3);
+        assert_eq!(next_state.last_full_rehash, state.last_full_rehash);
+
+        let next_state = get_next_full_rehash_state(&state, true, now);
+        assert_eq!(next_state.runs_since_full_rehash, 0);
+        assert_eq!(next_state.last_full_rehash, now.to_string());
+    }
+
+    #[test]
+    fn test_get_cached_hashes() {
+        let stat = FileStat {
+            inode: 42,
+            size: 10,
+            modified_time: "2024-06-01 12:00:00.5 UTC".to_string(),
+            change_time: "2024-06-01 12:00:00.5 UTC".to_string(),
+        };
+        let mut prior_result = FileScanResult {
+            exists: true,
+            inode: stat.inode,
+            size: stat.size,
+            modified_time: stat.modified_time.clone(),
+            change_time: stat.change_time.clone(),
+            ..Default::default()
+        };
+        prior_result
+            .hashes
+            .insert(HashAlgorithm::Md5, "AAAA".to_string());
+        prior_result
+            .hashes
+            .insert(HashAlgorithm::Sha256, "BBBB".to_string());
+
+        // Only the hashes enabled now are reused
+        let file_hashes = FileHashes {
+            sha256: true,
+            ..Default::default()
+        };
+        let cached_hashes = get_cached_hashes(&file_hashes, &prior_result, &stat).unwrap();
+        assert_eq!(cached_hashes.len(), 1);
+        assert_eq!(cached_hashes[&HashAlgorithm::Sha256], "BBBB");
+
+        // Any part of the stat tuple changing means reading the file again
+        for changed_stat in [
+            FileStat {
+                inode: 43,
+                ..stat.clone()
+            },
+            FileStat {
+                size: 11,
+                ..stat.clone()
+            },
+            FileStat {
+                modified_time: "2024-06-01 12:00:01 UTC".to_string(),
+                ..stat.clone()
+            },
+            FileStat {
+                change_time: "2024-06-01 12:00:01 UTC".to_string(),
+                ..stat.clone()
+            },
+        ] {
+            assert!(get_cached_hashes(&file_hashes, &prior_result, &changed_stat).is_none());
+        }
+
+        // Contents that aren't UTF-8 still leave the hashes good to reuse, a failed read doesn't
+        prior_result.errors = vec![ScanError::new(
+            ScanOperation::Content,
+            &std::io::Error::from(std::io::ErrorKind::InvalidData),
+        )];
+        assert!(get_cached_hashes(&file_hashes, &prior_result, &stat).is_some());
+        prior_result.errors = vec![ScanError::new(
+            ScanOperation::Hash,
+            &std::io::Error::from(std::io::ErrorKind::PermissionDenied),
+        )];
+        assert!(get_cached_hashes(&file_hashes, &prior_result, &stat).is_none());
+        prior_result.errors = vec![];
+
+        // A newly enabled algorithm has nothing to reuse
+        let file_hashes = FileHashes {
+            sha256: true,
+            blake3: true,
+            ..Default::default()
+        };
+        assert!(get_cached_hashes(&file_hashes, &prior_result, &stat).is_none());
+
+        // Results from before the change time was recorded are never trusted
+        prior_result.change_time = "".to_string();
+        let stat = FileStat {
+            change_time: "".to_string(),
+            ..stat
+        };
+        assert!(get_cached_hashes(&FileHashes::default(), &prior_result, &stat).is_none());
+    }
+}
+//////////////////////////////////////////////////////////////////////////////////////////////////
+///////////////////////////////////////       HASHING      ///////////////////////////////////////
+//////////////////////////////////////////////////////////////////////////////////////////////////
+#[cfg(test)]
+mod hashing_tests {
     use crate::hashing::*;
+    use crate::mmap::ReadMode;
     use crate::scan_settings::FileHashes;
     use std::any::Any;
     use std::fs::File;
@@ -346,131 +2160,607 @@ mod hashing_tests {
         std::thread::sleep(std::time::Duration::from_millis(150));
     }
 
-    #[test]
-    fn test_md5() {
-        setup_hash_tests();
-        // Placeholder
-        let expected_value_md5: String = String::from("2CEDB0215290E9A96103135E2843FA79");
-        assert_eq!(
-            get_md5(Path::new("./hashtestfile"), 4096),
-            expected_value_md5
-        );
-        teardown_hash_tests();
-    }
-
-    #[test]
-    fn test_sha256() {
-        setup_hash_tests();
-        let expected_value_sha256: String =
-            String::from("96640A0073CD72CB62AE9403105FB97D4635E7FF87658C1AF0034242B7BED840");
-        assert_eq!(
-            get_sha256(Path::new("./hashtestfile"), 4096),
-            expected_value_sha256
-        );
-        teardown_hash_tests();
-    }
-
-    #[test]
-    fn test_blake2s() {
-        setup_hash_tests();
-        let expected_value_blake2s: String =
-            String::from("2F777E0B8C11400C57CCE39AA8741E759E5FE44C0D31016B4BB1714908AF4B9F");
-        assert_eq!(
-            get_blake2s(Path::new("./hashtestfile"), 4096),
-            expected_value_blake2s
-        );
-        teardown_hash_tests();
-    }
     #[test]
     fn test_hashes() {
         setup_hash_tests();
 
         // Check known hashes for all types
-        let expected_hashes: HashValues = HashValues {
-            md5: "2CEDB0215290E9A96103135E2843FA79".to_string(),
-            sha256: "96640A0073CD72CB62AE9403105FB97D4635E7FF87658C1AF0034242B7BED840".to_string(),
-            blake2s: "2F777E0B8C11400C57CCE39AA8741E759E5FE44C0D31016B4BB1714908AF4B9F".to_string(),
-        };
+        let expected_hashes: HashValues = HashValues::from([
+            (
+                HashAlgorithm::Md5,
+                "2CEDB0215290E9A96103135E2843FA79".to_string(),
+            ),
+            (
+                HashAlgorithm::Sha256,
+                "96640A0073CD72CB62AE9403105FB97D4635E7FF87658C1AF0034242B7BED840".to_string(),
+            ),
+            (
+                HashAlgorithm::Blake2s,
+                "2F777E0B8C11400C57CCE39AA8741E759E5FE44C0D31016B4BB1714908AF4B9F".to_string(),
+            ),
+        ]);
 
-        let hash_results = get_all_hashes(
+        let hash_results = get_file_hashes(
             &FileHashes {
                 md5: true,
                 sha256: true,
                 blake2s: true,
+                ..Default::default()
             },
             4096,
             Path::new("./hashtestfile"),
-        );
+        )
+        .unwrap();
 
         assert_eq!(hash_results.type_id(), expected_hashes.type_id());
-        assert_eq!(expected_hashes.md5, hash_results.md5);
-        assert_eq!(expected_hashes.sha256, hash_results.sha256);
-        assert_eq!(expected_hashes.blake2s, hash_results.blake2s);
+        assert_eq!(expected_hashes, hash_results);
 
         // Validate config allows not grabbing some hashes
         // Honestly if you want to know why these tests are here, look at the hashing.rs file
         // that was updated in this same commit. Tests are your friend... but apparently I'm not
-        let expected_hashes: HashValues = HashValues {
-            md5: "2CEDB0215290E9A96103135E2843FA79".to_string(),
-            sha256: "".to_string(),
-            blake2s: "".to_string(),
+        for algorithm in [
+            HashAlgorithm::Md5,
+            HashAlgorithm::Sha256,
+            HashAlgorithm::Blake2s,
+        ] {
+            let mut file_hashes = FileHashes::default();
+            file_hashes.set_algorithm(algorithm, true);
+            let hash_results =
+                get_file_hashes(&file_hashes, 4096, Path::new("./hashtestfile")).unwrap();
+            assert_eq!(hash_results.len(), 1);
+            assert_eq!(hash_results[&algorithm], expected_hashes[&algorithm]);
+        }
+
+        teardown_hash_tests();
+    }
+
+    #[test]
+    fn test_additional_hashes() {
+        // Own file so the other hashing tests can't remove it mid-read
+        let test_path = Path::new("./hashalgtestfile");
+        std::fs::write(test_path, "Test contents").unwrap();
+
+        let file_hashes = FileHashes {
+            sha1: true,
+            sha512: true,
+            sha3_256: true,
+            blake3: true,
+            xxh3: true,
+            ..Default::default()
         };
-        let hash_results = get_all_hashes(
-            &FileHashes {
-                md5: true,
-                sha256: false,
-                blake2s: false,
-            },
-            4096,
-            Path::new("./hashtestfile"),
+        let hash_results = get_file_hashes(&file_hashes, 4096, test_path).unwrap();
+        assert_eq!(
+            hash_results.keys().copied().collect::<Vec<HashAlgorithm>>(),
+            file_hashes.get_algorithms()
+        );
+        assert_eq!(
+            hash_results[&HashAlgorithm::Sha1],
+            "4B310CC065C65AB16A935F2A435941C16199045E"
+        );
+        assert_eq!(
+            hash_results[&HashAlgorithm::Sha512],
+            "C08ABC541CE91BF1800AD356CC15BA5BAE0645A334A785B256B7F3EB4F0BBD31E52ED9A5088D39041A18855A9180FAA7F687D835D84263A669CC2000B08FEA98"
         );
-        assert_eq!(expected_hashes.md5, hash_results.md5);
-        assert_eq!(expected_hashes.sha256, hash_results.sha256);
-        assert_eq!(expected_hashes.blake2s, hash_results.blake2s);
+        assert_eq!(
+            hash_results[&HashAlgorithm::Sha3_256],
+            "2BF08F9AAB1A237B54DFA01459F55BECFA25DAA6D0F2FAA58360368E7AF59E61"
+        );
+        assert_eq!(
+            hash_results[&HashAlgorithm::Blake3],
+            "28DFFC95B08970B3E5A775DE1D9A4F0D6C0E8C6F1A2E1B2FB661644085A4F6A0"
+        );
+        assert_eq!(hash_results[&HashAlgorithm::Xxh3], "DF278E10D3762E67");
+
+        // Names round trip so they can be used as change fields
+        for algorithm in HashAlgorithm::ALL {
+            assert_eq!(HashAlgorithm::from_name(algorithm.name()), Some(algorithm));
+        }
+        assert_eq!(HashAlgorithm::from_name("size"), None);
+
+        let _ = std::fs::remove_file(test_path);
+    }
 
-        let expected_hashes: HashValues = HashValues {
-            md5: "".to_string(),
-            sha256: "96640A0073CD72CB62AE9403105FB97D4635E7FF87658C1AF0034242B7BED840".to_string(),
-            blake2s: "".to_string(),
+    #[test]
+    fn test_get_file_hashes() {
+        // Own file so the other hashing tests can't remove it mid-read
+        let test_path = Path::new("./hashpasstestfile");
+        std::fs::write(test_path, "Test contents").unwrap();
+        let all_hashes = FileHashes {
+            md5: true,
+            sha256: true,
+            blake2s: true,
+            ..Default::default()
         };
-        let hash_results = get_all_hashes(
-            &FileHashes {
-                md5: false,
-                sha256: true,
-                blake2s: false,
-            },
+
+        // Buffers smaller than the file, not a multiple of it, and larger than it all have to
+        // give the same single pass results
+        for read_buffer_size in [1, 3, 4096] {
+            let hash_results = get_file_hashes(&all_hashes, read_buffer_size, test_path).unwrap();
+            assert_eq!(
+                hash_results[&HashAlgorithm::Md5],
+                "2CEDB0215290E9A96103135E2843FA79"
+            );
+            assert_eq!(
+                hash_results[&HashAlgorithm::Sha256],
+                "96640A0073CD72CB62AE9403105FB97D4635E7FF87658C1AF0034242B7BED840"
+            );
+            assert_eq!(
+                hash_results[&HashAlgorithm::Blake2s],
+                "2F777E0B8C11400C57CCE39AA8741E759E5FE44C0D31016B4BB1714908AF4B9F"
+            );
+        }
+
+        assert!(get_file_hashes(&all_hashes, 4096, Path::new("./missinghashtestfile")).is_err());
+        let (hash_results, _) = get_all_hashes_with_blocks(
+            &all_hashes,
             4096,
-            Path::new("./hashtestfile"),
+            Path::new("./missinghashtestfile"),
+            0,
+            ReadMode::Buffered,
+        )
+        .unwrap();
+        assert!(hash_results.is_empty());
+
+        let _ = std::fs::remove_file(test_path);
+    }
+}
+//////////////////////////////////////////////////////////////////////////////////////////////////
+///////////////////////////////////////        IOC         ///////////////////////////////////////
+//////////////////////////////////////////////////////////////////////////////////////////////////
+#[cfg(test)]
+mod ioc_tests {
+    use crate::changes::ChangeType;
+    use crate::hashing::HashAlgorithm;
+    use crate::ioc::*;
+    use crate::scan_settings::Suppression;
+    use crate::severity::Severity;
+    use crate::tests::test_fixtures::get_hashed_result;
+    use std::path::Path;
+
+    const MD5_BAD: &str = "44D88612FEA8A8F36DE82E1278ABB02F";
+    const SHA1_BAD: &str = "3395856CE81F2B7382DEE72602F798B642F14140";
+    const SHA256_BAD: &str = "275a021bbfb6489e54d471899f7db9d1663fc695ec2fe2a2c4538aabf651fd0f";
+
+    #[test]
+    fn test_get_ioc_hash() {
+        assert_eq!(
+            get_ioc_hash(SHA256_BAD),
+            Some((HashAlgorithm::Sha256, SHA256_BAD.to_uppercase()))
         );
-        assert_eq!(expected_hashes.md5, hash_results.md5);
-        assert_eq!(expected_hashes.sha256, hash_results.sha256);
-        assert_eq!(expected_hashes.blake2s, hash_results.blake2s);
+        assert_eq!(get_ioc_hash(MD5_BAD).unwrap().0, HashAlgorithm::Md5);
+        assert_eq!(get_ioc_hash(SHA1_BAD).unwrap().0, HashAlgorithm::Sha1);
+        // Right length, but not a hash
+        assert_eq!(get_ioc_hash("first_seen,malware_family,signature"), None);
+        assert_eq!(get_ioc_hash(&"0".repeat(128)), None);
+    }
 
-        let expected_hashes: HashValues = HashValues {
-            md5: "".to_string(),
-            sha256: "".to_string(),
-            blake2s: "2F777E0B8C11400C57CCE39AA8741E759E5FE44C0D31016B4BB1714908AF4B9F".to_string(),
-        };
-        let hash_results = get_all_hashes(
-            &FileHashes {
-                md5: false,
-                sha256: false,
-                blake2s: true,
-            },
-            4096,
-            Path::new("./hashtestfile"),
+    #[test]
+    fn test_parse_ioc_feed_plain_text() {
+        let feed = format!(
+            "# Daily feed\n\n{}\n{}  EICAR test file\nnot a hash\n",
+            SHA256_BAD, MD5_BAD
+        );
+        let entries = parse_ioc_feed(&feed, "daily.txt");
+        assert_eq!(entries.len(), 2);
+        assert_eq!(entries[0].algorithm, HashAlgorithm::Sha256);
+        assert_eq!(entries[0].name, "");
+        assert_eq!(entries[1].algorithm, HashAlgorithm::Md5);
+        assert_eq!(entries[1].name, "EICAR test file");
+        assert_eq!(entries[1].source, "daily.txt");
+    }
+
+    #[test]
+    fn test_parse_ioc_feed_csv() {
+        let feed = format!(
+            "\"first_seen\",\"sha256_hash\",\"md5_hash\",\"sha1_hash\",\"signature\"\n\
+             \"2024-01-01 00:00:00\",\"{}\",\"{}\",\"{}\",\"EICAR, test\"\n",
+            SHA256_BAD, MD5_BAD, SHA1_BAD
         );
-        assert_eq!(expected_hashes.md5, hash_results.md5);
-        assert_eq!(expected_hashes.sha256, hash_results.sha256);
-        assert_eq!(expected_hashes.blake2s, hash_results.blake2s);
+        let entries = parse_ioc_feed(&feed, "feed.csv");
+        // The header row has no hashes, and each hash in a row is its own entry
+        assert_eq!(entries.len(), 3);
+        assert_eq!(entries[0].algorithm, HashAlgorithm::Sha256);
+        assert_eq!(entries[1].algorithm, HashAlgorithm::Md5);
+        assert_eq!(entries[2].algorithm, HashAlgorithm::Sha1);
+        // The name is the first field that isn't a hash
+        assert_eq!(entries[2].name, "2024-01-01 00:00:00");
+    }
 
-        teardown_hash_tests();
+    #[test]
+    fn test_apply_ioc_matches() {
+        let database = IocDatabase::new(parse_ioc_feed(SHA256_BAD, "feed.txt"));
+
+        let mut infected = get_hashed_result(
+            "/tmp/dropper",
+            HashAlgorithm::Sha256,
+            &SHA256_BAD.to_uppercase(),
+        );
+        infected.suppressed = true;
+        infected.suppressed_by = Some(Suppression {
+            path_pattern: "/tmp/*".to_string(),
+            change_types: vec![],
+            fields: vec![],
+            hash: "".to_string(),
+            expires: "".to_string(),
+            justification: "".to_string(),
+        });
+        let clean = get_hashed_result("/usr/bin/ls", HashAlgorithm::Sha256, "AAAA");
+        let mut results = vec![infected, clean];
+        apply_ioc_matches(&mut results, &database);
+
+        // An unchanged file is still a critical finding, and suppressions don't hide it
+        assert_eq!(results[0].change_type, ChangeType::Unchanged);
+        assert_eq!(results[0].ioc_matches.len(), 1);
+        assert_eq!(results[0].ioc_matches[0].source, "feed.txt");
+        assert_eq!(results[0].severity, Severity::Critical);
+        assert!(!results[0].suppressed);
+        assert!(results[0].suppressed_by.is_none());
+
+        assert!(results[1].ioc_matches.is_empty());
+        assert_eq!(results[1].severity, Severity::None);
+
+        // Only the algorithm the file was hashed with can match
+        let md5_only = get_hashed_result("/tmp/dropper", HashAlgorithm::Md5, MD5_BAD);
+        assert!(database.get_matches(&md5_only).is_empty());
+    }
+
+    #[test]
+    fn test_load_ioc_feeds() {
+        let test_dir = Path::new("./ioctest_feeds");
+        let _ = std::fs::remove_dir_all(test_dir);
+        std::fs::create_dir_all(test_dir).unwrap();
+        std::fs::write(test_dir.join("a.txt"), format!("{}\n", SHA256_BAD)).unwrap();
+        std::fs::write(test_dir.join("b.csv"), format!("{},Test\n", MD5_BAD)).unwrap();
+
+        let database = load_ioc_feeds(&[
+            "./ioctest_feeds/*".to_string(),
+            "./ioctest_feeds/missing.txt".to_string(),
+        ]);
+        assert_eq!(database.len(), 2);
+        assert_eq!(
+            database.get_algorithms().into_iter().collect::<Vec<_>>(),
+            vec![HashAlgorithm::Md5, HashAlgorithm::Sha256]
+        );
+
+        let _ = std::fs::remove_dir_all(test_dir);
+    }
+}
+//////////////////////////////////////////////////////////////////////////////////////////////////
+///////////////////////////////////////     KNOWN_GOOD     ///////////////////////////////////////
+//////////////////////////////////////////////////////////////////////////////////////////////////
+#[cfg(test)]
+mod known_good_tests {
+    use crate::changes::{ChangeType, FieldChange};
+    use crate::hashing::HashAlgorithm;
+    use crate::known_good::*;
+    use crate::severity::Severity;
+    use crate::tests::test_fixtures::get_hashed_result;
+    use std::path::Path;
+
+    const SHA256_LS: &str = "A2E1F5E3A4B8C6D7E8F90123456789ABCDEF0123456789ABCDEF0123456789AB";
+    const SHA256_CAT: &str = "b7c2f5e3a4b8c6d7e8f90123456789abcdef0123456789abcdef0123456789ab";
+    const MD5_LS: &str = "0123456789ABCDEF0123456789ABCDEF";
+    const SHA1_LS: &str = "0123456789ABCDEF0123456789ABCDEF01234567";
+
+    #[test]
+    fn test_parse_sha256sum_manifest() {
+        let manifest = format!(
+            "# made with sha256sum\n{}  /usr/bin/ls\n{} *./bin/cat\n\n{}  ls\n",
+            SHA256_LS, SHA256_CAT, MD5_LS
+        );
+        let entries = parse_manifest(
+            ManifestFormat::Sha256sum,
+            manifest.as_bytes(),
+            Some(Path::new("/usr")),
+            "SHA256SUMS",
+        )
+        .unwrap();
+        assert_eq!(entries.len(), 3);
+        assert_eq!(entries[0].algorithm, HashAlgorithm::Sha256);
+        assert_eq!(entries[0].path, "/usr/bin/ls");
+        // Relative paths are joined to the root and hashes are stored uppercase
+        assert_eq!(entries[1].path, "/usr/bin/cat");
+        assert_eq!(entries[1].hash, SHA256_CAT.to_uppercase());
+        // The algorithm comes from the hash length
+        assert_eq!(entries[2].algorithm, HashAlgorithm::Md5);
+        assert_eq!(entries[2].source, "SHA256SUMS");
+
+        assert!(parse_manifest(
+            ManifestFormat::Sha256sum,
+            "ABC  /bin/ls".as_bytes(),
+            None,
+            ""
+        )
+        .is_err());
+        assert!(parse_manifest(ManifestFormat::Sha256sum, MD5_LS.as_bytes(), None, "").is_err());
+    }
+
+    #[test]
+    fn test_parse_hashdeep_manifest() {
+        let manifest = format!(
+            "%%%% HASHDEEP-1.0\n%%%% size,md5,sha256,filename\n## Invoked from: /\n## \n\
+             1024,{},{},/usr/bin/ls\n2048,{},{},/srv/a,b.txt\n",
+            MD5_LS, SHA256_LS, MD5_LS, SHA256_CAT
+        );
+        let entries =
+            parse_manifest(ManifestFormat::Hashdeep, manifest.as_bytes(), None, "").unwrap();
+        assert_eq!(entries.len(), 4);
+        assert_eq!(entries[0].algorithm, HashAlgorithm::Md5);
+        assert_eq!(entries[1].algorithm, HashAlgorithm::Sha256);
+        assert_eq!(entries[1].path, "/usr/bin/ls");
+        // The file name is the last column, so its commas are kept
+        assert_eq!(entries[3].path, "/srv/a,b.txt");
+
+        let no_header = format!("1024,{},/usr/bin/ls\n", MD5_LS);
+        assert!(parse_manifest(ManifestFormat::Hashdeep, no_header.as_bytes(), None, "").is_err());
+    }
+
+    #[test]
+    fn test_parse_nsrl_manifest() {
+        let manifest = format!(
+            "\"SHA-1\",\"MD5\",\"CRC32\",\"FileName\",\"FileSize\",\"ProductCode\",\"OpSystemCode\",\"SpecialCode\"\n\
+             \"{}\",\"{}\",\"12345678\",\"ls\",1024,100,\"358\",\"\"\n",
+            SHA1_LS, MD5_LS
+        );
+        let entries = parse_manifest(ManifestFormat::Nsrl, manifest.as_bytes(), None, "").unwrap();
+        assert_eq!(entries.len(), 2);
+        assert_eq!(entries[0].algorithm, HashAlgorithm::Sha1);
+        assert_eq!(entries[1].algorithm, HashAlgorithm::Md5);
+        assert_eq!(entries[1].path, "ls");
+    }
+
+    #[test]
+    fn test_get_known_good_status() {
+        let manifest = format!("{}  /usr/bin/ls\n{}  cat\n", SHA256_LS, SHA256_CAT);
+        let database = KnownGoodDatabase::new(
+            parse_manifest(ManifestFormat::Sha256sum, manifest.as_bytes(), None, "").unwrap(),
+        );
+
+        let known_good = get_hashed_result("/usr/bin/ls", HashAlgorithm::Sha256, SHA256_LS);
+        assert_eq!(database.get_status(&known_good), KnownGoodStatus::KnownGood);
+
+        let copied = get_hashed_result("/tmp/ls", HashAlgorithm::Sha256, SHA256_LS);
+        assert_eq!(
+            database.get_status(&copied),
+            KnownGoodStatus::KnownGoodOtherPath
+        );
+
+        // A bare file name matches in any directory, and case doesn't matter
+        let named = get_hashed_result("/bin/cat", HashAlgorithm::Sha256, SHA256_CAT);
+        assert_eq!(database.get_status(&named), KnownGoodStatus::KnownGood);
+
+        // The same hex under a different algorithm is a different hash
+        let other_algorithm = get_hashed_result("/usr/bin/ls", HashAlgorithm::Blake3, SHA256_LS);
+        assert_eq!(
+            database.get_status(&other_algorithm),
+            KnownGoodStatus::Unknown
+        );
+
+        // md5 and sha1 can be collided, so a match on them alone isn't known good
+        let manifest = format!("{}  /usr/bin/ls\n{}  /usr/bin/ls\n", MD5_LS, SHA1_LS);
+        let database = KnownGoodDatabase::new(
+            parse_manifest(ManifestFormat::Sha256sum, manifest.as_bytes(), None, "").unwrap(),
+        );
+        let mut weak = get_hashed_result("/usr/bin/ls", HashAlgorithm::Md5, MD5_LS);
+        weak.hashes.insert(HashAlgorithm::Sha1, SHA1_LS.to_string());
+        assert_eq!(
+            database.get_status(&weak),
+            KnownGoodStatus::KnownGoodWeakHash
+        );
+    }
+
+    #[test]
+    fn test_known_good_database_lookup() {
+        let manifest = format!("{}  /usr/bin/ls\n{}  /usr/bin/ls\n", SHA256_LS, SHA256_LS);
+        let entries =
+            parse_manifest(ManifestFormat::Sha256sum, manifest.as_bytes(), None, "").unwrap();
+        // The same entry twice is only stored once
+        let database = KnownGoodDatabase::new(entries);
+        assert_eq!(
+            database
+                .get_entries(HashAlgorithm::Sha256, &SHA256_LS.to_lowercase())
+                .len(),
+            1
+        );
+        assert!(database
+            .get_entries(HashAlgorithm::Sha512, SHA256_LS)
+            .is_empty());
+        assert!(KnownGoodDatabase::default().is_empty());
+
+        // Enough entries for the binary search to land mid-line, with a hash shared by two paths
+        let mut entries: Vec<KnownGoodEntry> = (0..500)
+            .map(|index| KnownGoodEntry {
+                algorithm: HashAlgorithm::Sha256,
+                hash: format!("{:064X}", index * 7),
+                path: format!("/usr/lib/file{}", "x".repeat(index % 13)),
+                source: "".to_string(),
+            })
+            .collect();
+        entries.push(KnownGoodEntry {
+            algorithm: HashAlgorithm::Sha256,
+            hash: format!("{:064X}", 70),
+            path: "/opt/other".to_string(),
+            source: "".to_string(),
+        });
+        let database = KnownGoodDatabase::new(entries);
+        for index in [0, 1, 250, 499] {
+            let hash = format!("{:064X}", index * 7);
+            assert_eq!(database.get_entries(HashAlgorithm::Sha256, &hash).len(), 1);
+            // Falls between two stored hashes
+            let missing = format!("{:064X}", index * 7 + 1);
+            assert!(database
+                .get_entries(HashAlgorithm::Sha256, &missing)
+                .is_empty());
+        }
+        assert_eq!(
+            database
+                .get_entries(HashAlgorithm::Sha256, &format!("{:064X}", 70))
+                .len(),
+            2
+        );
+    }
+
+    #[test]
+    fn test_apply_known_good() {
+        let manifest = format!("{}  /usr/bin/ls\n", SHA256_LS);
+        let database = KnownGoodDatabase::new(
+            parse_manifest(ManifestFormat::Sha256sum, manifest.as_bytes(), None, "").unwrap(),
+        );
+        let mut hash_change = FieldChange::new("sha256", "AAAA", SHA256_LS);
+        hash_change.severity = Severity::High;
+        let mut owner_change = FieldChange::new("owner", "0:0", "1000:1000");
+        owner_change.severity = Severity::Medium;
+        let mut result = get_hashed_result("/usr/bin/ls", HashAlgorithm::Sha256, SHA256_LS);
+        result.set_changes(vec![hash_change.clone(), owner_change]);
+        assert_eq!(result.change_type, ChangeType::Modified);
+
+        // Without a severity the status is still recorded
+        let mut results = vec![result.clone()];
+        apply_known_good(&mut results, &database, None);
+        assert_eq!(results[0].known_good, KnownGoodStatus::KnownGood);
+        assert_eq!(results[0].severity, Severity::High);
+
+        // Only the content changes are lowered. The owner change keeps its severity.
+        let mut results = vec![result.clone()];
+        apply_known_good(&mut results, &database, Some(Severity::Low));
+        assert_eq!(results[0].changes[0].severity, Severity::Low);
+        assert_eq!(results[0].changes[1].severity, Severity::Medium);
+        assert_eq!(results[0].severity, Severity::Medium);
+
+        // A known hash at another path isn't vouched for
+        let mut copied = get_hashed_result("/tmp/ls", HashAlgorithm::Sha256, SHA256_LS);
+        copied.set_changes(vec![hash_change.clone()]);
+        let mut results = vec![copied];
+        apply_known_good(&mut results, &database, Some(Severity::Low));
+        assert_eq!(results[0].known_good, KnownGoodStatus::KnownGoodOtherPath);
+        assert_eq!(results[0].severity, Severity::High);
+
+        // Neither is a file that only matched on md5
+        let database = KnownGoodDatabase::new(
+            parse_manifest(
+                ManifestFormat::Sha256sum,
+                format!("{}  /usr/bin/ls\n", MD5_LS).as_bytes(),
+                None,
+                "",
+            )
+            .unwrap(),
+        );
+        let mut weak = get_hashed_result("/usr/bin/ls", HashAlgorithm::Md5, MD5_LS);
+        weak.set_changes(vec![FieldChange {
+            field: "md5".to_string(),
+            ..hash_change.clone()
+        }]);
+        let mut results = vec![weak];
+        apply_known_good(&mut results, &database, Some(Severity::Low));
+        assert_eq!(results[0].known_good, KnownGoodStatus::KnownGoodWeakHash);
+        assert_eq!(results[0].severity, Severity::High);
+    }
+
+    #[test]
+    fn test_import_manifest() {
+        let test_dir = Path::new("./knowngoodtest");
+        let _ = std::fs::remove_dir_all(test_dir);
+        std::fs::create_dir_all(test_dir).unwrap();
+        let manifest_path = test_dir.join("SHA256SUMS");
+        std::fs::write(&manifest_path, format!("{}  /usr/bin/ls\n", SHA256_LS)).unwrap();
+        let database_path = test_dir.join("known_good").join("hashes.txt");
+
+        let added = import_manifest(
+            &database_path,
+            ManifestFormat::Sha256sum,
+            &manifest_path,
+            None,
+        );
+        assert_eq!(added, Ok(1));
+        // Importing again adds nothing new
+        let added = import_manifest(
+            &database_path,
+            ManifestFormat::Sha256sum,
+            &manifest_path,
+            None,
+        );
+        assert_eq!(added, Ok(0));
+        // Stored sorted, one entry per line
+        assert_eq!(
+            std::fs::read_to_string(&database_path).unwrap(),
+            format!(
+                "sha256\t{}\t/usr/bin/ls\t{}\n",
+                SHA256_LS,
+                manifest_path.display()
+            )
+        );
+
+        let database = load_known_good_database(&database_path).unwrap();
+        let result = get_hashed_result("/usr/bin/ls", HashAlgorithm::Sha256, SHA256_LS);
+        assert_eq!(database.get_status(&result), KnownGoodStatus::KnownGood);
+
+        assert!(import_manifest(
+            &database_path,
+            ManifestFormat::Sha256sum,
+            &test_dir.join("missing"),
+            None
+        )
+        .is_err());
+
+        let _ = std::fs::remove_dir_all(test_dir);
+    }
+
+    #[test]
+    fn test_import_runs() {
+        let test_dir = Path::new("./knowngoodrunstest");
+        let _ = std::fs::remove_dir_all(test_dir);
+        let database_path = test_dir.join("known_good").join("hashes.txt");
+        let database = KnownGoodDatabase::new(
+            parse_manifest(
+                ManifestFormat::Sha256sum,
+                format!("{}  /usr/bin/ls\n", SHA256_LS).as_bytes(),
+                None,
+                "old",
+            )
+            .unwrap(),
+        );
+
+        // Runs of two entries, so the manifest is spread over two run files and what's left
+        let manifest = format!(
+            "{cat}  /usr/bin/cat\n{ls}  /usr/bin/ls\n{ls}  /bin/ls\n\
+            {cat}  /usr/bin/cat\n{ls}  /usr/sbin/ls\n",
+            cat = SHA256_CAT,
+            ls = SHA256_LS
+        );
+        let mut runs = ImportRuns::new(&database_path, 2).unwrap();
+        read_manifest(
+            ManifestFormat::Sha256sum,
+            manifest.as_bytes(),
+            None,
+            "new",
+            |entry| runs.add(entry).map_err(|e| e.to_string()),
+        )
+        .unwrap();
+        assert!(database_path.with_extension("run1").exists());
+
+        // The duplicate within the manifest and the entry already in the database aren't added
+        assert_eq!(runs.save(&database).unwrap(), 3);
+        assert_eq!(
+            std::fs::read_to_string(&database_path).unwrap(),
+            format!(
+                "sha256\t{ls}\t/bin/ls\tnew\nsha256\t{ls}\t/usr/bin/ls\told\n\
+                sha256\t{ls}\t/usr/sbin/ls\tnew\nsha256\t{cat}\t/usr/bin/cat\tnew\n",
+                ls = SHA256_LS,
+                cat = SHA256_CAT.to_uppercase()
+            )
+        );
+        assert!(!database_path.with_extension("run0").exists());
+        assert!(!database_path.with_extension("run1").exists());
+
+        let _ = std::fs::remove_dir_all(test_dir);
     }
 }
 //////////////////////////////////////////////////////////////////////////////////////////////////
 ///////////////////////////////////////      LOGGING       ///////////////////////////////////////
 //////////////////////////////////////////////////////////////////////////////////////////////////
 #[cfg(test)]
+// Kept as originally written, like file_tests
+#[allow(clippy::assertions_on_constants)]
 mod logging_tests {
     use crate::logging::*;
     use log::info;
@@ -507,29 +2797,289 @@ mod logging_tests {
             assert!(false);
         }
 
-        let expected_type = String::new();
-        assert_eq!(
-            return_default_logging_config().type_id(),
-            expected_type.type_id()
-        );
+        let expected_type = String::new();
+        assert_eq!(
+            return_default_logging_config().type_id(),
+            expected_type.type_id()
+        );
+
+        let log_config_file = File::open("./config/osfig_log_settings.yml");
+        let mut log_contents = Vec::new();
+        log_config_file
+            .unwrap()
+            .read_to_end(&mut log_contents)
+            .unwrap();
+
+        assert_eq!(return_default_logging_config().into_bytes(), log_contents);
+
+        let logfile = File::open("./logs/osfig.log");
+        let mut log_contents = Vec::new();
+        logfile.unwrap().read_to_end(&mut log_contents).unwrap();
+
+        let log_contents = String::from_utf8(log_contents).unwrap();
+        assert!(log_contents.contains(test_string));
+
+        teardown_logging_tests();
+    }
+}
+
+//////////////////////////////////////////////////////////////////////////////////////////////////
+///////////////////////////////////////       MERKLE       ///////////////////////////////////////
+//////////////////////////////////////////////////////////////////////////////////////////////////
+#[cfg(test)]
+mod merkle_tests {
+    use crate::changes::ChangeType;
+    use crate::merkle::*;
+    use std::path::{Path, PathBuf};
+
+    const MERKLE_TEST_DIR: &str = "./merkletest";
+
+    fn setup_merkle_tests() {
+        teardown_merkle_tests();
+        std::fs::create_dir_all(format!("{}/tree/sub/deeper", MERKLE_TEST_DIR)).unwrap();
+        std::fs::write(format!("{}/tree/a.conf", MERKLE_TEST_DIR), "a").unwrap();
+        std::fs::write(format!("{}/tree/sub/b.ko", MERKLE_TEST_DIR), "b").unwrap();
+        std::fs::write(format!("{}/tree/sub/deeper/c.ko", MERKLE_TEST_DIR), "c").unwrap();
+    }
+
+    fn teardown_merkle_tests() {
+        let _ = std::fs::remove_dir_all(MERKLE_TEST_DIR);
+    }
+
+    fn get_change(path: &str, change_type: ChangeType) -> DirectoryEntryChange {
+        DirectoryEntryChange {
+            path: Box::new(PathBuf::from(path)),
+            change_type,
+        }
+    }
+
+    #[test]
+    fn test_get_directory_hash() {
+        setup_merkle_tests();
+        let tree_path = Path::new(MERKLE_TEST_DIR).join("tree");
+
+        assert!(get_directory_hash(&tree_path, DirectoryHashMode::Off, 4096).is_none());
+        assert!(get_directory_hash(
+            &tree_path.join("a.conf"),
+            DirectoryHashMode::Recursive,
+            4096
+        )
+        .is_none());
+
+        let recursive_hash =
+            get_directory_hash(&tree_path, DirectoryHashMode::Recursive, 4096).unwrap();
+        let one_level_hash =
+            get_directory_hash(&tree_path, DirectoryHashMode::OneLevel, 4096).unwrap();
+        // Children are sorted by name so the digest doesn't depend on read_dir order
+        let names: Vec<&str> = recursive_hash
+            .entries
+            .iter()
+            .map(|entry| entry.name.as_str())
+            .collect();
+        assert_eq!(names, vec!["a.conf", "sub"]);
+        assert_eq!(recursive_hash.entries[1].entry_type, "dir");
+        assert_eq!(recursive_hash.entries[1].entries.len(), 2);
+        assert!(one_level_hash.entries[1].entries.is_empty());
+        assert_eq!(
+            recursive_hash.digest,
+            get_entries_digest(&recursive_hash.entries)
+        );
+        assert_eq!(
+            get_directory_hash(&tree_path, DirectoryHashMode::Recursive, 4096).unwrap(),
+            recursive_hash
+        );
+
+        // A change deep in the tree changes the top level digest, but only a recursive hash
+        // can see it
+        std::fs::write(
+            format!("{}/tree/sub/deeper/c.ko", MERKLE_TEST_DIR),
+            "patched",
+        )
+        .unwrap();
+        std::fs::write(format!("{}/tree/sub/new.ko", MERKLE_TEST_DIR), "new").unwrap();
+        std::fs::remove_file(format!("{}/tree/a.conf", MERKLE_TEST_DIR)).unwrap();
+        let changed_hash =
+            get_directory_hash(&tree_path, DirectoryHashMode::Recursive, 4096).unwrap();
+        assert_ne!(changed_hash.digest, recursive_hash.digest);
+
+        let changes = get_directory_changes(&recursive_hash, &changed_hash, Path::new("/tree"));
+        assert_eq!(
+            changes,
+            vec![
+                get_change("/tree/a.conf", ChangeType::Deleted),
+                get_change("/tree/sub/deeper/c.ko", ChangeType::Modified),
+                get_change("/tree/sub/new.ko", ChangeType::Added),
+            ]
+        );
+        assert!(get_directory_changes(&changed_hash, &changed_hash, Path::new("/tree")).is_empty());
+
+        teardown_merkle_tests();
+    }
+
+    #[test]
+    #[cfg(target_os = "linux")]
+    fn test_get_directory_hash_unreadable() {
+        use std::os::unix::fs::PermissionsExt;
+
+        setup_merkle_tests();
+        let tree_path = Path::new(MERKLE_TEST_DIR).join("tree");
+        let sub_path = tree_path.join("sub");
+        assert_ne!(UNREADABLE_DIRECTORY_DIGEST, get_entries_digest(&[]));
+
+        let readable_hash =
+            get_directory_hash(&tree_path, DirectoryHashMode::Recursive, 4096).unwrap();
+        std::fs::set_permissions(&sub_path, std::fs::Permissions::from_mode(0o000)).unwrap();
+        // Root can list the directory anyway, so there is nothing more to check
+        let is_listable = std::fs::read_dir(&sub_path).is_ok();
+        let unreadable_hash =
+            get_directory_hash(&tree_path, DirectoryHashMode::Recursive, 4096).unwrap();
+        let unreadable_sub_hash =
+            get_directory_hash(&sub_path, DirectoryHashMode::Recursive, 4096).unwrap();
+        std::fs::set_permissions(&sub_path, std::fs::Permissions::from_mode(0o755)).unwrap();
+        if is_listable {
+            teardown_merkle_tests();
+            return;
+        }
+
+        // The populated directory doesn't look like an empty one that lost its read permission
+        let sub_entry = &unreadable_hash.entries[1];
+        assert_eq!(sub_entry.hash, UNREADABLE_DIRECTORY_DIGEST);
+        assert!(sub_entry.entries.is_empty());
+        assert_eq!(unreadable_sub_hash.digest, UNREADABLE_DIRECTORY_DIGEST);
+        assert_ne!(unreadable_hash.digest, readable_hash.digest);
+        assert_eq!(
+            get_directory_changes(&readable_hash, &unreadable_hash, Path::new("/tree")),
+            vec![get_change("/tree/sub", ChangeType::Modified)]
+        );
+
+        teardown_merkle_tests();
+    }
+}
+
+//////////////////////////////////////////////////////////////////////////////////////////////////
+///////////////////////////////////////        MMAP        ///////////////////////////////////////
+//////////////////////////////////////////////////////////////////////////////////////////////////
+#[cfg(test)]
+mod mmap_tests {
+    use crate::hashing::get_file_and_block_hashes;
+    use crate::mmap::*;
+    use crate::scan_settings::FileHashes;
+    use std::fs::File;
+    use std::path::Path;
+    use std::time::Instant;
+
+    fn get_test_data(length: usize) -> Vec<u8> {
+        (0..length).map(|index| (index * 31 % 251) as u8).collect()
+    }
+
+    #[test]
+    fn test_get_read_mode() {
+        assert_eq!(get_read_mode(false, 0, 1_000_000), ReadMode::Buffered);
+        assert_eq!(get_read_mode(true, 0, 10), ReadMode::Mmap);
+        assert_eq!(get_read_mode(false, 1_000, 999), ReadMode::Buffered);
+        assert_eq!(get_read_mode(false, 1_000, 1_000), ReadMode::Mmap);
+    }
+
+    #[test]
+    fn test_mmap_hashes() {
+        let test_path = Path::new("./mmaptestfile");
+        std::fs::write(test_path, get_test_data(100_000)).unwrap();
+        let file_hashes = FileHashes {
+            md5: true,
+            sha256: true,
+            blake3: true,
+            ssdeep: true,
+            ..Default::default()
+        };
+
+        // Both ways of reading the file have to agree on every hash
+        let buffered_hashes =
+            get_file_and_block_hashes(&file_hashes, 4096, test_path, 8192, ReadMode::Buffered)
+                .unwrap();
+        let mapped_hashes =
+            get_file_and_block_hashes(&file_hashes, 4096, test_path, 8192, ReadMode::Mmap).unwrap();
+        assert_eq!(buffered_hashes, mapped_hashes);
+
+        // Empty files can't be mapped, so they are read the usual way
+        std::fs::write(test_path, "").unwrap();
+        let buffered_hashes =
+            get_file_and_block_hashes(&file_hashes, 4096, test_path, 0, ReadMode::Buffered)
+                .unwrap();
+        let mapped_hashes =
+            get_file_and_block_hashes(&file_hashes, 4096, test_path, 0, ReadMode::Mmap).unwrap();
+        assert_eq!(buffered_hashes, mapped_hashes);
+
+        let _ = std::fs::remove_file(test_path);
+    }
 
-        let log_config_file = File::open("./config/osfig_log_settings.yml");
-        let mut log_contents = Vec::new();
-        log_config_file
-            .unwrap()
-            .read_to_end(&mut log_contents)
-            .unwrap();
+    #[test]
+    #[cfg(target_os = "linux")]
+    fn test_read_mapped_truncated() {
+        let test_path = Path::new("./mmaptruncatetestfile");
+        std::fs::write(test_path, get_test_data(1_000_000)).unwrap();
+        let file = File::open(test_path).unwrap();
+        let truncating_file = File::options().write(true).open(test_path).unwrap();
 
-        assert_eq!(return_default_logging_config().into_bytes(), log_contents);
+        // Reading the mapping after the file shrinks would normally kill the process with SIGBUS
+        let mut byte_sum: u64 = 0;
+        let completed = read_mapped(&file, 1_000_000, |data| {
+            truncating_file.set_len(10_000).unwrap();
+            byte_sum = data.iter().map(|byte| *byte as u64).sum();
+        })
+        .unwrap();
+        assert!(!completed);
+        assert!(byte_sum > 0);
 
-        let logfile = File::open("./logs/osfig.log");
-        let mut log_contents = Vec::new();
-        logfile.unwrap().read_to_end(&mut log_contents).unwrap();
+        // An untouched file reads through
+        let completed = read_mapped(&file, 10_000, |_| {}).unwrap();
+        assert!(completed);
 
-        let log_contents = String::from_utf8(log_contents).unwrap();
-        assert!(log_contents.contains(test_string));
+        let _ = std::fs::remove_file(test_path);
+    }
 
-        teardown_logging_tests();
+    #[test]
+    #[ignore]
+    fn bench_mmap_hashing() {
+        // cargo test --release bench_mmap_hashing -- --ignored --nocapture
+        let test_path = Path::new("./mmapbenchfile");
+        let file_hashes = FileHashes {
+            sha256: true,
+            blake3: true,
+            ..Default::default()
+        };
+        for length in [64 * 1024, 16 * 1024 * 1024, 512 * 1024 * 1024] {
+            std::fs::write(test_path, get_test_data(length)).unwrap();
+            for read_buffer_size in [4096, 65536] {
+                let start = Instant::now();
+                let buffered_hashes = get_file_and_block_hashes(
+                    &file_hashes,
+                    read_buffer_size,
+                    test_path,
+                    0,
+                    ReadMode::Buffered,
+                )
+                .unwrap();
+                let buffered_time = start.elapsed();
+
+                let start = Instant::now();
+                let mapped_hashes = get_file_and_block_hashes(
+                    &file_hashes,
+                    read_buffer_size,
+                    test_path,
+                    0,
+                    ReadMode::Mmap,
+                )
+                .unwrap();
+                let mapped_time = start.elapsed();
+                assert_eq!(buffered_hashes, mapped_hashes);
+
+                println!(
+                    "{:>10} bytes, {:>6} byte buffer: buffered {:?}, mmap {:?}",
+                    length, read_buffer_size, buffered_time, mapped_time
+                );
+            }
+        }
+        let _ = std::fs::remove_file(test_path);
     }
 }
 
@@ -537,6 +3087,8 @@ mod logging_tests {
 ///////////////////////////////////////     OSFIG_STATE    ///////////////////////////////////////
 //////////////////////////////////////////////////////////////////////////////////////////////////
 #[cfg(test)]
+// Kept as originally written, like file_tests
+#[allow(clippy::bool_assert_comparison)]
 mod osfig_state_tests {
     use crate::osfig_state::*;
     use crate::scan_settings::{FileScanSetting, ScanSettings};
@@ -575,8 +3127,19 @@ mod osfig_state_tests {
                     scan_files: false,
                     file_scan_settings: vec![],
                     file_scan_delay: 0,
+                    file_scan_workers: 1,
                     scan_registry: false,
                     registry_patterns: vec![],
+                    change_rules: vec![],
+                    suppressions: vec![],
+                    severity_rules: vec![],
+                    full_rehash_runs: 0,
+                    full_rehash_days: 0,
+                    known_good_severity: None,
+                    ioc_feeds: vec![],
+                    verify_packages: false,
+                    dpkg_path: "".to_string(),
+                    rpm_path: "".to_string(),
                 },
                 scan_result_path: "./scans".to_string(),
             }
@@ -618,8 +3181,19 @@ mod osfig_state_tests {
                     scan_files: false,
                     file_scan_settings: vec![],
                     file_scan_delay: 0,
+                    file_scan_workers: 1,
                     scan_registry: false,
                     registry_patterns: vec![],
+                    change_rules: vec![],
+                    suppressions: vec![],
+                    severity_rules: vec![],
+                    full_rehash_runs: 0,
+                    full_rehash_days: 0,
+                    known_good_severity: None,
+                    ioc_feeds: vec![],
+                    verify_packages: false,
+                    dpkg_path: "".to_string(),
+                    rpm_path: "".to_string(),
                 },
                 scan_result_path: "./scans".to_string(),
             }
@@ -630,16 +3204,309 @@ mod osfig_state_tests {
     }
 }
 
+//////////////////////////////////////////////////////////////////////////////////////////////////
+///////////////////////////////////////      PACKAGE       ///////////////////////////////////////
+//////////////////////////////////////////////////////////////////////////////////////////////////
+#[cfg(test)]
+mod package_tests {
+    use crate::hashing::HashAlgorithm;
+    use crate::package::*;
+    use crate::tests::test_fixtures::get_file_result;
+    use std::path::{Path, PathBuf};
+
+    const PACKAGE_TEST_DPKG_DIR: &str = "./pkgtest_dpkg";
+    const PACKAGE_TEST_ROOT_DIR: &str = "./pkgtest_root";
+    // md5 of "hello\n"
+    const MD5_HELLO: &str = "b1946ac92492d2347c6235b4d2611184";
+
+    const DPKG_STATUS: &str = "Package: coreutils
+Status: install ok installed
+Architecture: amd64
+Version: 9.4-3
+
+Package: libc6
+Status: install ok installed
+Multi-Arch: same
+Conffiles:
+ /etc/ld.so.conf.d/x86_64-linux-gnu.conf d4e7a7b88a71b5ffd9e2644e71a0cfab
+ /etc/old.conf 0123456789abcdef0123456789abcdef obsolete
+ /etc/new.conf newconffile
+Description: GNU C Library
+ Contains the standard libraries.
+
+Package: removed
+Status: deinstall ok config-files
+Conffiles:
+ /etc/removed.conf 0123456789abcdef0123456789abcdef
+";
+
+    fn teardown_package_tests() {
+        let _ = std::fs::remove_dir_all(PACKAGE_TEST_DPKG_DIR);
+        let _ = std::fs::remove_dir_all(PACKAGE_TEST_ROOT_DIR);
+    }
+
+    fn setup_dpkg_fixture(md5sums: &[(&str, String)]) {
+        let info_dir = Path::new(PACKAGE_TEST_DPKG_DIR).join("info");
+        std::fs::create_dir_all(&info_dir).unwrap();
+        std::fs::write(Path::new(PACKAGE_TEST_DPKG_DIR).join("status"), DPKG_STATUS).unwrap();
+        for (file_name, contents) in md5sums {
+            std::fs::write(info_dir.join(file_name), contents).unwrap();
+        }
+    }
+
+    #[test]
+    fn test_parse_dpkg_status() {
+        let (installed, conffiles) = parse_dpkg_status(DPKG_STATUS);
+        assert!(installed.contains("coreutils"));
+        assert!(installed.contains("libc6"));
+        assert!(!installed.contains("removed"));
+
+        // Obsolete config files and placeholder hashes are skipped
+        assert_eq!(conffiles.len(), 1);
+        let (path, package_file) = &conffiles[0];
+        assert_eq!(
+            path,
+            &PathBuf::from("/etc/ld.so.conf.d/x86_64-linux-gnu.conf")
+        );
+        assert_eq!(package_file.package, "libc6");
+        assert_eq!(package_file.algorithm, HashAlgorithm::Md5);
+        assert_eq!(package_file.hash, "D4E7A7B88A71B5FFD9E2644E71A0CFAB");
+        assert!(package_file.conffile);
+    }
+
+    #[test]
+    fn test_parse_dpkg_md5sums() {
+        let md5sums = format!(
+            "{}  usr/bin/ls\n{}  usr/share/doc/my file\n",
+            MD5_HELLO, MD5_HELLO
+        );
+        let files = parse_dpkg_md5sums(&md5sums, "coreutils");
+        assert_eq!(files.len(), 2);
+        assert_eq!(files[0].0, PathBuf::from("/usr/bin/ls"));
+        assert_eq!(files[0].1.package, "coreutils");
+        assert!(!files[0].1.conffile);
+        assert_eq!(files[1].0, PathBuf::from("/usr/share/doc/my file"));
+    }
+
+    #[test]
+    fn test_parse_rpm_files() {
+        let rpm_output = format!(
+            "/usr/bin/bash\t{}\t\t8\tbash\n/etc/bashrc\t{}\tc\t1\tsetup\n/usr/share/doc\t\t\t8\tbash\n",
+            "A".repeat(64),
+            MD5_HELLO
+        );
+        let files = parse_rpm_files(&rpm_output);
+        // The directory has no digest
+        assert_eq!(files.len(), 2);
+        assert_eq!(files[0].0, PathBuf::from("/usr/bin/bash"));
+        assert_eq!(files[0].1.algorithm, HashAlgorithm::Sha256);
+        assert!(!files[0].1.conffile);
+        assert_eq!(files[1].1.algorithm, HashAlgorithm::Md5);
+        assert_eq!(files[1].1.package, "setup");
+        assert!(files[1].1.conffile);
+
+        assert_eq!(get_rpm_digest_algorithm("(none)"), Some(HashAlgorithm::Md5));
+        assert_eq!(get_rpm_digest_algorithm("3"), None);
+    }
+
+    #[test]
+    fn test_verify_packages() {
+        teardown_package_tests();
+        std::fs::create_dir_all(PACKAGE_TEST_ROOT_DIR).unwrap();
+        let root = std::fs::canonicalize(PACKAGE_TEST_ROOT_DIR).unwrap();
+        let intact_path = root.join("intact");
+        let modified_path = root.join("modified");
+        let unowned_path = root.join("unowned");
+        let removed_path = root.join("removed");
+        for path in [&intact_path, &modified_path, &unowned_path, &removed_path] {
+            std::fs::write(path, "hello\n").unwrap();
+        }
+        std::fs::write(&modified_path, "tampered\n").unwrap();
+        // md5sums paths have no leading slash
+        let md5sums_line = |path: &Path| {
+            format!(
+                "{}  {}\n",
+                MD5_HELLO,
+                path.to_string_lossy().trim_start_matches('/')
+            )
+        };
+        setup_dpkg_fixture(&[
+            ("coreutils.md5sums", md5sums_line(&intact_path)),
+            ("libc6:amd64.md5sums", md5sums_line(&modified_path)),
+            ("removed.md5sums", md5sums_line(&removed_path)),
+        ]);
+
+        let mut database = PackageDatabase::default();
+        database.add_files(load_dpkg_files(Path::new(PACKAGE_TEST_DPKG_DIR)).unwrap());
+        // Two md5sums files and one config file. The removed package's files aren't owned.
+        assert_eq!(database.len(), 3);
+
+        let mut directory = get_file_result(&root);
+        directory.is_file = false;
+        directory.is_dir = true;
+        let mut results = vec![
+            get_file_result(&intact_path),
+            get_file_result(&modified_path),
+            get_file_result(&unowned_path),
+            get_file_result(&removed_path),
+            directory,
+        ];
+        // Files are checked against the hashes the scan collected, not read again
+        results[0]
+            .hashes
+            .insert(HashAlgorithm::Md5, MD5_HELLO.to_uppercase());
+        results[1].hashes.insert(
+            HashAlgorithm::Md5,
+            "AAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAA".to_string(),
+        );
+        let mut not_hashed = results[0].clone();
+        not_hashed.hashes.clear();
+        results.push(not_hashed);
+        verify_packages(&mut results, &database);
+
+        let intact = results[0].package.as_ref().unwrap();
+        assert_eq!(intact.status, PackageStatus::Intact);
+        assert_eq!(intact.package, "coreutils");
+        assert_eq!(intact.algorithm, Some(HashAlgorithm::Md5));
+        assert_eq!(intact.expected_hash, MD5_HELLO.to_uppercase());
+
+        let modified = results[1].package.as_ref().unwrap();
+        assert_eq!(modified.status, PackageStatus::Modified);
+        assert_eq!(modified.package, "libc6");
+
+        assert_eq!(
+            results[2].package.as_ref().unwrap().status,
+            PackageStatus::Unowned
+        );
+        assert_eq!(results[2].package.as_ref().unwrap().package, "");
+        assert_eq!(
+            results[3].package.as_ref().unwrap().status,
+            PackageStatus::Unowned
+        );
+        assert!(results[4].package.is_none());
+        // Without an md5 from the scan there's nothing to compare
+        assert!(results[5].package.is_none());
+
+        // No package data at all doesn't make every file unowned
+        let mut unowned_result = get_file_result(&unowned_path);
+        verify_packages(
+            std::slice::from_mut(&mut unowned_result),
+            &PackageDatabase::default(),
+        );
+        assert!(unowned_result.package.is_none());
+
+        // Without a status file there's no telling what is installed
+        let _ = std::fs::remove_file(Path::new(PACKAGE_TEST_DPKG_DIR).join("status"));
+        assert!(load_dpkg_files(Path::new(PACKAGE_TEST_DPKG_DIR)).is_err());
+
+        teardown_package_tests();
+    }
+
+    #[test]
+    fn test_load_package_files_degraded() {
+        // Kept apart from the shared fixture directories so it can run alongside the other tests
+        let dpkg_dir = format!("{}_degraded", PACKAGE_TEST_DPKG_DIR);
+        let _ = std::fs::remove_dir_all(&dpkg_dir);
+        std::fs::create_dir_all(&dpkg_dir).unwrap();
+        std::fs::write(Path::new(&dpkg_dir).join("status"), DPKG_STATUS).unwrap();
+        let mut scan_settings = crate::scan_settings::get_default_scan_settings();
+        scan_settings.dpkg_path = dpkg_dir.clone();
+        // An rpm database exists, but the rpm binary doesn't
+        scan_settings.rpm_path = dpkg_dir.clone();
+        let missing_rpm = format!("{}/rpm", dpkg_dir);
+        assert!(query_rpm_files(&missing_rpm).is_err());
+
+        // dpkg files are still checked
+        let database = load_package_files(&scan_settings, &missing_rpm);
+        assert_eq!(database.len(), 1);
+        assert!(database
+            .get(Path::new("/etc/ld.so.conf.d/x86_64-linux-gnu.conf"))
+            .is_some());
+
+        // Without a dpkg status file either there is nothing to check, but the scan goes on
+        std::fs::remove_file(Path::new(&dpkg_dir).join("status")).unwrap();
+        assert!(load_package_files(&scan_settings, &missing_rpm).is_empty());
+
+        let _ = std::fs::remove_dir_all(&dpkg_dir);
+    }
+
+    #[test]
+    fn test_add_package_algorithms() {
+        let mut database = PackageDatabase::default();
+        database.add_files(parse_rpm_files(&format!(
+            "/usr/bin/bash\t{}\t\t8\tbash\n/etc/bashrc\t{}\tc\t1\tsetup\n",
+            "A".repeat(64),
+            MD5_HELLO
+        )));
+        let mut scan_settings = crate::scan_settings::get_default_scan_settings();
+        for file_scan_setting in scan_settings.file_scan_settings.iter_mut() {
+            file_scan_setting.file_hashes = crate::scan_settings::FileHashes {
+                sha256: true,
+                ..Default::default()
+            };
+        }
+        add_package_algorithms(&mut scan_settings, &database);
+        for file_scan_setting in &scan_settings.file_scan_settings {
+            assert_eq!(
+                file_scan_setting.file_hashes.get_algorithms(),
+                vec![HashAlgorithm::Md5, HashAlgorithm::Sha256]
+            );
+        }
+    }
+
+    #[test]
+    #[cfg(unix)]
+    fn test_add_files_resolves_symlinked_dirs() {
+        // Packages list /bin/ls even where /bin is a link to /usr/bin
+        let test_dir = Path::new("./pkgtest_symlink");
+        let _ = std::fs::remove_dir_all(test_dir);
+        std::fs::create_dir_all(test_dir.join("usr").join("bin")).unwrap();
+        std::os::unix::fs::symlink("usr/bin", test_dir.join("bin")).unwrap();
+        let root = std::fs::canonicalize(test_dir).unwrap();
+
+        let files = parse_dpkg_md5sums(
+            &format!(
+                "{}  {}\n",
+                MD5_HELLO,
+                root.join("bin")
+                    .join("ls")
+                    .to_string_lossy()
+                    .trim_start_matches('/')
+            ),
+            "coreutils",
+        );
+        let mut database = PackageDatabase::default();
+        database.add_files(files);
+        assert!(database.get(&root.join("bin").join("ls")).is_some());
+        assert!(database
+            .get(&root.join("usr").join("bin").join("ls"))
+            .is_some());
+
+        let _ = std::fs::remove_dir_all(test_dir);
+    }
+}
 //////////////////////////////////////////////////////////////////////////////////////////////////
 ///////////////////////////////////////      REGISTRY      ///////////////////////////////////////
 //////////////////////////////////////////////////////////////////////////////////////////////////
 #[cfg(test)]
-#[cfg(windows)]
 mod registry_tests {
+    use crate::changes::ChangeType;
+    use crate::helpers::ScanResults;
+    use crate::registry::*;
+    use crate::scan_settings::{SeverityRule, Suppression};
+    use crate::severity::{Severity, DEFAULT_SEVERITY};
     use std::any::Any;
 
-    #[cfg(windows)]
-    use crate::registry::*;
+    fn get_test_registry_result(path: &str, keys: Vec<&str>, values: Vec<&str>) -> RegistryResult {
+        RegistryResult {
+            path: path.to_string(),
+            data: RegistryData {
+                keys: keys.iter().map(|key| key.to_string()).collect(),
+                values: values.iter().map(|value| value.to_string()).collect(),
+            },
+            ..Default::default()
+        }
+    }
 
     #[test]
     fn test_example() {
@@ -647,6 +3514,315 @@ mod registry_tests {
         let expected_type: String = String::from("");
         assert_eq!(String::from("").type_id(), expected_type.type_id());
     }
+
+    #[test]
+    fn test_compare_registry_results() {
+        let old_result = get_test_registry_result(
+            "HKLM\\SOFTWARE\\Microsoft\\Windows\\CurrentVersion\\Run",
+            vec!["Policies", "Legacy"],
+            vec![
+                "SecurityHealth = C:\\Windows\\system32\\SecurityHealthSystray.exe",
+                "OneDrive = 1",
+            ],
+        );
+
+        // Identical results carry no change records
+        assert!(compare_registry_results(&old_result, &old_result.clone()).is_empty());
+
+        let new_result = get_test_registry_result(
+            "HKLM\\SOFTWARE\\Microsoft\\Windows\\CurrentVersion\\Run",
+            vec!["Policies", "Updater"],
+            vec![
+                "SecurityHealth = C:\\Users\\Public\\evil.exe",
+                "Backdoor = 1",
+            ],
+        );
+        let changes = compare_registry_results(&old_result, &new_result);
+        assert_eq!(changes.len(), 5);
+
+        assert_eq!(changes[0].field, "keys");
+        assert_eq!(changes[0].old_value, "Legacy");
+        assert_eq!(changes[0].new_value, "");
+        assert_eq!(changes[1].field, "keys");
+        assert_eq!(changes[1].old_value, "");
+        assert_eq!(changes[1].new_value, "Updater");
+
+        // New data on an existing value is one modification, not a removal and an addition
+        assert_eq!(changes[2].field, "values");
+        assert_eq!(
+            changes[2].old_value,
+            "SecurityHealth = C:\\Windows\\system32\\SecurityHealthSystray.exe"
+        );
+        assert_eq!(
+            changes[2].new_value,
+            "SecurityHealth = C:\\Users\\Public\\evil.exe"
+        );
+        assert_eq!(changes[3].old_value, "OneDrive = 1");
+        assert_eq!(changes[3].new_value, "");
+        assert_eq!(changes[4].old_value, "");
+        assert_eq!(changes[4].new_value, "Backdoor = 1");
+    }
+
+    #[test]
+    fn test_compare_registry_scans() {
+        let last_scan = vec![
+            get_test_registry_result("HKLM\\SOFTWARE\\Unchanged", vec!["A"], vec![]),
+            get_test_registry_result("HKLM\\SOFTWARE\\Modified", vec![], vec!["Value = 1"]),
+            get_test_registry_result("HKLM\\SOFTWARE\\Removed", vec!["B"], vec![]),
+        ];
+        let this_scan = vec![
+            get_test_registry_result("HKLM\\SOFTWARE\\Unchanged", vec!["A"], vec![]),
+            get_test_registry_result("HKLM\\SOFTWARE\\Modified", vec![], vec!["Value = 2"]),
+            get_test_registry_result("HKLM\\SOFTWARE\\New", vec![], vec!["Value = 1"]),
+        ];
+
+        let results = compare_registry_scans(&last_scan, this_scan.clone());
+        assert_eq!(results.len(), 4);
+        assert_eq!(results[0].change_type, ChangeType::Unchanged);
+        assert!(!results[0].is_modified);
+        assert_eq!(results[1].change_type, ChangeType::Modified);
+        assert_eq!(results[1].changed_fields, vec!["values"]);
+        assert_eq!(results[2].change_type, ChangeType::Added);
+        assert_eq!(results[3].path, "HKLM\\SOFTWARE\\Removed");
+        assert_eq!(results[3].change_type, ChangeType::Deleted);
+
+        // A deletion is only reported once
+        let results = compare_registry_scans(&results, this_scan.clone());
+        assert_eq!(results.len(), 3);
+        assert!(results.iter().all(|result| !result.is_modified));
+
+        // Nothing is flagged on the first scan
+        let results = compare_registry_scans(&[], this_scan);
+        assert!(results.iter().all(|result| !result.is_modified));
+    }
+
+    #[test]
+    fn test_registry_severity_and_suppression() {
+        let last_scan = vec![
+            get_test_registry_result("HKLM\\SOFTWARE\\Run", vec![], vec!["A = 1"]),
+            get_test_registry_result("HKLM\\SOFTWARE\\Vendor", vec![], vec!["B = 1"]),
+        ];
+        let this_scan = vec![
+            get_test_registry_result("HKLM\\SOFTWARE\\Run", vec![], vec!["A = 2"]),
+            get_test_registry_result("HKLM\\SOFTWARE\\Vendor", vec![], vec!["B = 2"]),
+        ];
+        let mut scan_results = ScanResults {
+            registry: compare_registry_scans(&last_scan, this_scan),
+            ..Default::default()
+        };
+
+        // Registry changes go through the same severity rules and suppressions as files
+        scan_results.classify_changes(&[SeverityRule {
+            path_pattern: "HKLM*Run".to_string(),
+            change_types: vec![],
+            fields: vec!["values".to_string()],
+            attribute_bits_added: 0,
+            severity: Severity::Critical,
+        }]);
+        assert_eq!(scan_results.registry[0].severity, Severity::Critical);
+        assert_eq!(
+            scan_results.registry[0].changes[0].severity,
+            Severity::Critical
+        );
+        assert_eq!(scan_results.registry[1].severity, DEFAULT_SEVERITY);
+
+        let suppression = Suppression {
+            path_pattern: "HKLM*Vendor".to_string(),
+            change_types: vec![ChangeType::Modified],
+            fields: vec![],
+            hash: "".to_string(),
+            expires: "2999-01-01T00:00:00Z".to_string(),
+            justification: "Vendor update".to_string(),
+        };
+        scan_results.apply_suppressions(std::slice::from_ref(&suppression));
+        assert!(!scan_results.registry[0].suppressed);
+        assert!(scan_results.registry[1].suppressed);
+        assert_eq!(
+            scan_results.registry[1].suppressed_by,
+            Some(suppression.clone())
+        );
+
+        // There's no hash on a registry key for a hash pinned suppression to match
+        scan_results.apply_suppressions(&[Suppression {
+            hash: "AAAA".to_string(),
+            ..suppression
+        }]);
+        assert!(!scan_results.registry[1].suppressed);
+
+        scan_results.summarize_changes();
+        assert_eq!(scan_results.summary.critical, 1);
+        assert_eq!(scan_results.summary.medium, 1);
+    }
+}
+
+//////////////////////////////////////////////////////////////////////////////////////////////////
+///////////////////////////////////////     SCAN_ERROR     ///////////////////////////////////////
+//////////////////////////////////////////////////////////////////////////////////////////////////
+#[cfg(test)]
+mod scan_error_tests {
+    use crate::scan_error::*;
+    use std::io;
+
+    #[test]
+    fn test_scan_error() {
+        let scan_error = ScanError::new(
+            ScanOperation::Hash,
+            &io::Error::new(io::ErrorKind::PermissionDenied, "Permission denied"),
+        );
+        assert_eq!(scan_error.kind, "PermissionDenied");
+        assert_eq!(scan_error.message, "Permission denied");
+        assert_eq!(
+            serde_json::to_value(&scan_error).unwrap()["operation"],
+            "hash"
+        );
+        assert!(scan_error.is_unreadable());
+
+        let content_error = ScanError::new(
+            ScanOperation::Content,
+            &io::Error::from(io::ErrorKind::InvalidData),
+        );
+        assert!(!content_error.is_unreadable());
+        assert!(!is_unreadable(std::slice::from_ref(&content_error)));
+        assert!(is_unreadable(&[content_error.clone(), scan_error]));
+
+        assert!(has_metadata(&[content_error]));
+        assert!(!has_metadata(&[ScanError::new(
+            ScanOperation::Metadata,
+            &io::Error::from(io::ErrorKind::NotFound),
+        )]));
+    }
+}
+
+//////////////////////////////////////////////////////////////////////////////////////////////////
+///////////////////////////////////////     SCAN_INDEX     ///////////////////////////////////////
+//////////////////////////////////////////////////////////////////////////////////////////////////
+#[cfg(test)]
+mod scan_index_tests {
+    use crate::file::{get_file_changes, FileScanResult};
+    use crate::hashing::HashAlgorithm;
+    use crate::scan_index::*;
+    use crate::tests::test_fixtures::get_test_results;
+    use std::path::Path;
+    use std::time::Instant;
+
+    #[test]
+    fn test_scan_index() {
+        let mut results = get_test_results(3, "AAAA");
+        results[2]
+            .hashes
+            .insert(HashAlgorithm::Blake2s, "BBBB".to_string());
+        // A duplicated path should resolve to its first entry
+        let mut duplicate = results[0].clone();
+        duplicate
+            .hashes
+            .insert(HashAlgorithm::Sha256, "CCCC".to_string());
+        results.push(duplicate);
+
+        let index = ScanIndex::new(&results);
+        assert!(!index.is_empty());
+        assert_eq!(
+            index
+                .get(Path::new("/usr/lib/test/1"))
+                .unwrap()
+                .get_hash(HashAlgorithm::Sha256),
+            "AAAA1"
+        );
+        assert_eq!(
+            index
+                .get(Path::new("/usr/lib/test/0"))
+                .unwrap()
+                .get_hash(HashAlgorithm::Sha256),
+            "AAAA0"
+        );
+        assert!(index.get(Path::new("/usr/lib/test/9")).is_none());
+
+        assert_eq!(index.get_by_hash("AAAA2").len(), 1);
+        assert_eq!(index.get_by_hash("BBBB")[0].path, results[2].path);
+        assert_eq!(index.get_by_hash("DDDD").len(), 0);
+
+        // Content lookups prefer sha256 and fall back to blake2s
+        let mut lookup: FileScanResult = FileScanResult::default();
+        lookup
+            .hashes
+            .insert(HashAlgorithm::Blake2s, "BBBB".to_string());
+        assert_eq!(index.get_by_content(&lookup).len(), 1);
+        lookup
+            .hashes
+            .insert(HashAlgorithm::Sha256, "AAAA1".to_string());
+        assert_eq!(
+            index.get_by_content(&lookup)[0].get_hash(HashAlgorithm::Sha256),
+            "AAAA1"
+        );
+
+        assert!(ScanIndex::new(&vec![]).is_empty());
+    }
+
+    // Benchmark for comparing a scan against its prior results. Ignored by default since it
+    // allocates a couple million results. Run with:
+    // cargo test --release -- --ignored --nocapture bench_
+    #[test]
+    #[ignore]
+    fn bench_scan_index_scaling() {
+        for count in [10_000, 100_000, 1_000_000, 2_000_000] {
+            let last_scan = get_test_results(count, "AAAA");
+
+            let start = Instant::now();
+            let index = ScanIndex::new(&last_scan);
+            let build_time = start.elapsed();
+
+            // Every other path is modified so both outcomes of the comparison are exercised
+            let start = Instant::now();
+            let mut modified = 0;
+            for (position, prior_entry) in last_scan.iter().enumerate() {
+                let mut result = prior_entry.clone();
+                if position % 2 == 0 {
+                    result
+                        .hashes
+                        .insert(HashAlgorithm::Sha256, "BBBB".to_string());
+                }
+                if !get_file_changes(&index, &result).is_empty() {
+                    modified += 1;
+                }
+            }
+            let compare_time = start.elapsed();
+            assert_eq!(modified, count / 2);
+
+            println!(
+                "{:>9} entries: index built in {:?}, compared in {:?} ({:?} per entry)",
+                count,
+                build_time,
+                compare_time,
+                compare_time / count as u32
+            );
+        }
+    }
+
+    #[test]
+    #[ignore]
+    fn bench_linear_lookup_scaling() {
+        // The lookup this index replaced, for comparison with bench_scan_index_scaling. It grows
+        // quadratically, so it stops at sizes that still finish in reasonable time.
+        for count in [5_000, 10_000, 20_000] {
+            let last_scan = get_test_results(count, "AAAA");
+
+            let start = Instant::now();
+            let mut found = 0;
+            for result in &last_scan {
+                if last_scan.iter().any(|entry| entry.path == result.path) {
+                    found += 1;
+                }
+            }
+            let compare_time = start.elapsed();
+            assert_eq!(found, count);
+
+            println!(
+                "{:>9} entries: linear lookups in {:?} ({:?} per entry)",
+                count,
+                compare_time,
+                compare_time / count as u32
+            );
+        }
+    }
 }
 
 //////////////////////////////////////////////////////////////////////////////////////////////////
@@ -657,6 +3833,26 @@ mod scan_settings_tests {
     use crate::scan_settings::*;
     use std::any::Any;
 
+    #[test]
+    fn test_get_change_rule() {
+        let mut scan_settings = get_default_scan_settings();
+        assert_eq!(get_change_rule(&scan_settings, "logs").name, "logs");
+        assert!(get_change_rule(&scan_settings, "logs").allow_size_growth);
+
+        // User defined rules replace the built in rule of the same name
+        let mut custom_rule = get_change_rule(&scan_settings, "all");
+        custom_rule.name = "logs".to_string();
+        scan_settings.change_rules = vec![custom_rule];
+        assert!(!get_change_rule(&scan_settings, "logs").allow_size_growth);
+        // Built in rules remain available without being listed in the settings
+        assert_eq!(get_change_rule(&scan_settings, "binaries").name, "binaries");
+
+        // Unknown names fall back to monitoring everything
+        let fallback_rule = get_change_rule(&scan_settings, "missing");
+        assert_eq!(fallback_rule.name, "all");
+        assert!(fallback_rule.hashes && fallback_rule.modified_time);
+    }
+
     #[test]
     fn test_example() {
         // Placeholder
@@ -665,6 +3861,264 @@ mod scan_settings_tests {
     }
 }
 
+//////////////////////////////////////////////////////////////////////////////////////////////////
+///////////////////////////////////////      SEVERITY      ///////////////////////////////////////
+//////////////////////////////////////////////////////////////////////////////////////////////////
+#[cfg(test)]
+mod severity_tests {
+    use crate::changes::{ChangeType, FieldChange};
+    use crate::scan_settings::{get_default_severity_rules, SeverityRule};
+    use crate::severity::*;
+    use crate::tests::test_fixtures::get_changed_result;
+    use std::path::Path;
+
+    #[test]
+    fn test_is_attribute_bit_added() {
+        let setuid_added = FieldChange::new("attributes", 0o100755u32, 0o104755u32);
+        assert!(is_attribute_bit_added(&setuid_added, 0o6000));
+        assert!(!is_attribute_bit_added(&setuid_added, 0o2000));
+
+        // The bit was already there, so it didn't appear
+        let setuid_kept = FieldChange::new("attributes", 0o104755u32, 0o104750u32);
+        assert!(!is_attribute_bit_added(&setuid_kept, 0o6000));
+
+        let other_field = FieldChange::new("size", 0o100755u32, 0o104755u32);
+        assert!(!is_attribute_bit_added(&other_field, 0o6000));
+    }
+
+    #[test]
+    fn test_get_change_severity() {
+        let severity_rules = vec![
+            SeverityRule {
+                path_pattern: "/etc/shadow".to_string(),
+                change_types: vec![ChangeType::Modified],
+                fields: vec![],
+                attribute_bits_added: 0,
+                severity: Severity::Critical,
+            },
+            SeverityRule {
+                path_pattern: "/etc/*".to_string(),
+                change_types: vec![],
+                fields: vec!["sha256".to_string()],
+                attribute_bits_added: 0,
+                severity: Severity::High,
+            },
+        ];
+        let hash_change = FieldChange::new("sha256", "AAAA", "BBBB");
+        let size_change = FieldChange::new("size", 1, 2);
+
+        assert_eq!(
+            get_change_severity(
+                Path::new("/etc/shadow"),
+                ChangeType::Modified,
+                &size_change,
+                &severity_rules
+            ),
+            Severity::Critical
+        );
+        // The first matching rule wins
+        assert_eq!(
+            get_change_severity(
+                Path::new("/etc/shadow"),
+                ChangeType::Modified,
+                &hash_change,
+                &severity_rules
+            ),
+            Severity::Critical
+        );
+        assert_eq!(
+            get_change_severity(
+                Path::new("/etc/hosts"),
+                ChangeType::Modified,
+                &hash_change,
+                &severity_rules
+            ),
+            Severity::High
+        );
+        // Nothing matched
+        assert_eq!(
+            get_change_severity(
+                Path::new("/etc/hosts"),
+                ChangeType::Modified,
+                &size_change,
+                &severity_rules
+            ),
+            DEFAULT_SEVERITY
+        );
+        assert_eq!(
+            get_change_severity(
+                Path::new("/var/log/syslog"),
+                ChangeType::Modified,
+                &hash_change,
+                &severity_rules
+            ),
+            DEFAULT_SEVERITY
+        );
+    }
+
+    #[test]
+    fn test_classify_changes() {
+        let mut results = vec![
+            get_changed_result(
+                "/usr/bin/passwd",
+                "BBBB",
+                vec![
+                    FieldChange::new("attributes", 0o100755u32, 0o104755u32),
+                    FieldChange::new("modified_time", "1", "2"),
+                ],
+            ),
+            get_changed_result(
+                "/etc/motd",
+                "BBBB",
+                vec![FieldChange::new("modified_time", "1", "2")],
+            ),
+            get_changed_result(
+                "/usr/sbin/sshd",
+                "BBBB",
+                vec![FieldChange::new("sha256", "A", "B")],
+            ),
+            get_changed_result(
+                "/etc/hosts",
+                "BBBB",
+                vec![FieldChange::new("sha256", "A", "B")],
+            ),
+            get_changed_result("/etc/passwd", "BBBB", vec![]),
+        ];
+
+        classify_changes(&mut results, &get_default_severity_rules());
+
+        // Each change record carries its own severity and the result carries the highest one
+        assert_eq!(results[0].changes[0].severity, Severity::Critical);
+        assert_eq!(results[0].changes[1].severity, Severity::Low);
+        assert_eq!(results[0].severity, Severity::Critical);
+        assert_eq!(results[1].severity, Severity::Low);
+        assert_eq!(results[2].severity, Severity::High);
+        assert_eq!(results[3].severity, Severity::Medium);
+        assert_eq!(results[4].severity, Severity::None);
+    }
+}
+
+//////////////////////////////////////////////////////////////////////////////////////////////////
+///////////////////////////////////////    SUPPRESSION     ///////////////////////////////////////
+//////////////////////////////////////////////////////////////////////////////////////////////////
+#[cfg(test)]
+mod suppression_tests {
+    use crate::changes::{ChangeType, FieldChange};
+    use crate::scan_settings::Suppression;
+    use crate::suppression::*;
+    use crate::tests::test_fixtures::get_changed_result;
+    use chrono::{DateTime, Utc};
+
+    fn get_now() -> DateTime<Utc> {
+        DateTime::parse_from_rfc3339("2024-06-01T00:00:00Z")
+            .unwrap()
+            .with_timezone(&Utc)
+    }
+
+    fn get_test_suppression(path_pattern: &str, expires: &str) -> Suppression {
+        Suppression {
+            path_pattern: path_pattern.to_string(),
+            change_types: vec![],
+            fields: vec![],
+            hash: "".to_string(),
+            expires: expires.to_string(),
+            justification: "Rollout".to_string(),
+        }
+    }
+
+    #[test]
+    fn test_is_suppression_expired() {
+        let now = get_now();
+        assert!(!is_suppression_expired(
+            &get_test_suppression("/etc/*", "2024-06-02T00:00:00Z"),
+            now
+        ));
+        assert!(!is_suppression_expired(
+            &get_test_suppression("/etc/*", "2024-06-01T02:00:00+01:00"),
+            now
+        ));
+        assert!(is_suppression_expired(
+            &get_test_suppression("/etc/*", "2024-05-31T23:59:59Z"),
+            now
+        ));
+        // A suppression that can't be parsed must not suppress anything forever
+        assert!(is_suppression_expired(
+            &get_test_suppression("/etc/*", "next week"),
+            now
+        ));
+    }
+
+    #[test]
+    fn test_is_suppression_match() {
+        let hash_change = vec![FieldChange::new("sha256", "AAAA", "BBBB")];
+        let time_change = vec![FieldChange::new("modified_time", "1", "2")];
+        let result = get_changed_result("/etc/nginx/nginx.conf", "BBBB", hash_change.clone());
+
+        let mut suppression = get_test_suppression("/etc/nginx/*", "2024-06-02T00:00:00Z");
+        assert!(is_suppression_match(&result, &suppression));
+        assert!(!is_suppression_match(
+            &get_changed_result("/etc/passwd", "BBBB", hash_change.clone()),
+            &suppression
+        ));
+
+        // Unchanged results are never suppressed
+        let mut unchanged = result.clone();
+        unchanged.set_changes(vec![]);
+        assert!(!is_suppression_match(&unchanged, &suppression));
+
+        suppression.change_types = vec![ChangeType::Deleted];
+        assert!(!is_suppression_match(&result, &suppression));
+        suppression.change_types = vec![ChangeType::Modified];
+        assert!(is_suppression_match(&result, &suppression));
+
+        // Every changed field has to be covered by the suppression
+        suppression.fields = vec!["modified_time".to_string()];
+        assert!(!is_suppression_match(&result, &suppression));
+        let mut both_changes = hash_change.clone();
+        both_changes.extend(time_change.clone());
+        assert!(!is_suppression_match(
+            &get_changed_result("/etc/nginx/nginx.conf", "BBBB", both_changes),
+            &suppression
+        ));
+        assert!(is_suppression_match(
+            &get_changed_result("/etc/nginx/nginx.conf", "BBBB", time_change),
+            &suppression
+        ));
+
+        // Only the expected content is suppressed
+        suppression.fields = vec![];
+        suppression.hash = "bbbb".to_string();
+        assert!(is_suppression_match(&result, &suppression));
+        suppression.hash = "CCCC".to_string();
+        assert!(!is_suppression_match(&result, &suppression));
+    }
+
+    #[test]
+    fn test_apply_suppressions() {
+        let hash_change = vec![FieldChange::new("sha256", "AAAA", "BBBB")];
+        let mut results = vec![
+            get_changed_result("/etc/nginx/nginx.conf", "BBBB", hash_change.clone()),
+            get_changed_result("/etc/ssh/sshd_config", "BBBB", hash_change.clone()),
+            get_changed_result("/etc/passwd", "BBBB", hash_change),
+        ];
+        let suppressions = vec![
+            get_test_suppression("/etc/nginx/*", "2024-06-02T00:00:00Z"),
+            get_test_suppression("/etc/ssh/*", "2024-05-01T00:00:00Z"),
+        ];
+
+        let expired_suppressions = apply_suppressions(&mut results, &suppressions, get_now());
+
+        // Suppressed changes stay in the results
+        assert_eq!(results.len(), 3);
+        assert!(results[0].suppressed);
+        assert_eq!(results[0].suppressed_by, Some(suppressions[0].clone()));
+        assert_eq!(results[0].change_type, ChangeType::Modified);
+        assert!(!results[1].suppressed);
+        assert!(!results[2].suppressed);
+        assert_eq!(expired_suppressions, vec![suppressions[1].clone()]);
+    }
+}
+
 //////////////////////////////////////////////////////////////////////////////////////////////////
 ///////////////////////////////////////       WIN_ACL      ///////////////////////////////////////
 //////////////////////////////////////////////////////////////////////////////////////////////////